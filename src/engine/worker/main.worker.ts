@@ -18,7 +18,8 @@ async function initWasm() {
       components: DistComponent[],
       initWealth: number,
       steps: number,
-      numTrials: number
+      numTrials: number,
+      seed = 0
     ): number => {
       const data: number[] = [];
       for (const c of components) {
@@ -36,7 +37,8 @@ async function initWasm() {
         new Float64Array(data),
         initWealth,
         steps,
-        numTrials
+        numTrials,
+        BigInt(seed)
       );
     };
 
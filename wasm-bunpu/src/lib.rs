@@ -1,6 +1,20 @@
 use wasm_bindgen::prelude::*;
-use rand::Rng;
-use js_sys::Float64Array;
+use rand::distributions::Open01;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use js_sys::{Array, Float64Array};
+use std::cell::Cell;
+use std::rc::Rc;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Start the web-worker thread pool used by the `parallel` feature; await
+/// `initThreadPool(navigator.hardwareConcurrency)` once before running
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
 
 // Better panic messages in debug mode
 #[cfg(feature = "console_error_panic_hook")]
@@ -109,8 +123,8 @@ impl AliasTable {
         let mut small: Vec<usize> = Vec::new();
         let mut large: Vec<usize> = Vec::new();
 
-        for i in 0..n {
-            if prob[i] < 1.0 {
+        for (i, &pi) in prob.iter().enumerate() {
+            if pi < 1.0 {
                 small.push(i);
             } else {
                 large.push(i);
@@ -144,14 +158,22 @@ impl AliasTable {
         if self.components.is_empty() {
             return 0.0;
         }
+        let idx = self.pick(rng);
+        self.sample_component(idx, rng)
+    }
 
+    /// Index of a component drawn with probability proportional to its weight
+    fn pick(&self, rng: &mut impl Rng) -> usize {
         let n = self.components.len();
         let u: f64 = rng.gen::<f64>() * n as f64;
         let i = u as usize;
         let y = u - i as f64;
 
-        let idx = if y < self.prob[i.min(n - 1)] { i.min(n - 1) } else { self.alias[i.min(n - 1)] };
-        
+        if y < self.prob[i.min(n - 1)] { i.min(n - 1) } else { self.alias[i.min(n - 1)] }
+    }
+
+    /// Draw from within component `idx`
+    fn sample_component(&self, idx: usize, rng: &mut impl Rng) -> f64 {
         match &self.components[idx] {
             Component::Atom { x, .. } => *x,
             Component::Bin { a, b, .. } => a + rng.gen::<f64>() * (b - a),
@@ -163,376 +185,10016 @@ impl AliasTable {
     }
 }
 
-/// Run Monte Carlo simulation
-/// 
-/// # Arguments
-/// * `components_data` - Flat array of component data
-/// * `init_wealth` - Initial wealth
-/// * `steps` - Number of steps per trial
-/// * `num_trials` - Number of simulation trials
-/// 
-/// # Returns
-/// Number of trials that resulted in ruin
+// ===========================================
+// RNG Backends
+// ===========================================
+
+/// Random number generator used by the `*_with_rng` sampling entry points
 #[wasm_bindgen]
-pub fn run_monte_carlo(
-    components_data: Float64Array,
-    init_wealth: f64,
-    steps: u32,
-    num_trials: u32,
-) -> u32 {
-    #[cfg(feature = "console_error_panic_hook")]
-    set_panic_hook();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngAlgorithm {
+    /// xoshiro256++: fastest, good statistical quality
+    Xoshiro256PlusPlus = 0,
+    /// PCG64 (XSL RR 128/64): fast, small state, well studied
+    Pcg64 = 1,
+    /// ChaCha20: slower but cryptographically strong stream
+    ChaCha20 = 2,
+}
 
-    let data: Vec<f64> = components_data.to_vec();
-    let components = parse_components(&data);
-    let alias_table = AliasTable::new(components);
+/// SplitMix64 step, used to expand a u64 seed into larger generator states
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Fill bytes from a 64-bit generator, little-endian
+fn fill_bytes_from_u64(rng: &mut impl RngCore, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+        let bytes = rng.next_u64().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
 
-    let mut rng = rand::thread_rng();
-    let mut ruin_count: u32 = 0;
+/// xoshiro256++ (Blackman & Vigna)
+struct Xoshiro256PlusPlus {
+    s: [u64; 4],
+}
 
-    for _ in 0..num_trials {
-        let mut wealth = init_wealth;
-        
-        for _ in 0..steps {
-            wealth += alias_table.sample(&mut rng);
-            if wealth <= 0.0 {
-                ruin_count += 1;
-                break;
-            }
-        }
+impl Xoshiro256PlusPlus {
+    fn from_seed_u64(seed: u64) -> Self {
+        let mut sm = seed;
+        Self { s: [splitmix64(&mut sm), splitmix64(&mut sm), splitmix64(&mut sm), splitmix64(&mut sm)] }
+    }
+}
+
+impl RngCore for Xoshiro256PlusPlus {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
     }
 
-    ruin_count
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_from_u64(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// PCG64: 128-bit LCG with the XSL RR output function (O'Neill), the same
+/// generator as rand_pcg's `Lcg128Xsl64` (advance, then output)
+struct Pcg64 {
+    state: u128,
+    increment: u128,
+}
 
-    #[test]
-    fn test_parse_components() {
-        // atom: type=0, x=10, p=0.5
-        let data = vec![0.0, 10.0, 0.5];
-        let comps = parse_components(&data);
-        assert_eq!(comps.len(), 1);
+impl Pcg64 {
+    const MULTIPLIER: u128 = 0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645;
+
+    /// Same construction as `Lcg128Xsl64::new(state, stream)`
+    fn new(state: u128, stream: u128) -> Self {
+        let mut rng = Self { state: 0, increment: (stream << 1) | 1 };
+        rng.state = state.wrapping_add(rng.increment);
+        rng.step();
+        rng
+    }
+
+    fn from_seed_u64(seed: u64) -> Self {
+        let mut sm = seed;
+        let state = ((splitmix64(&mut sm) as u128) << 64) | splitmix64(&mut sm) as u128;
+        let stream = ((splitmix64(&mut sm) as u128) << 64) | splitmix64(&mut sm) as u128;
+        Self::new(state, stream)
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.increment);
     }
 }
 
-/// Serialize components back to flat array format
-fn serialize_components(components: &[Component]) -> Vec<f64> {
-    let mut result = Vec::new();
-    for c in components {
-        match c {
-            Component::Atom { x, p } => {
-                result.push(0.0);
-                result.push(*x);
-                result.push(*p);
-            }
-            Component::Bin { a, b, p } => {
-                result.push(1.0);
-                result.push(*a);
-                result.push(*b);
-                result.push(*p);
-            }
-            Component::Tail { x0, mass, lambda, is_right } => {
-                result.push(2.0);
-                result.push(*x0);
-                result.push(*mass);
-                result.push(*lambda);
-                result.push(if *is_right { 1.0 } else { 0.0 });
-            }
+impl RngCore for Pcg64 {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.step();
+        let state = self.state;
+        let rot = (state >> 122) as u32;
+        let xsl = ((state >> 64) as u64) ^ (state as u64);
+        xsl.rotate_right(rot)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_from_u64(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A seeded generator of the selected algorithm, or the default `StdRng`
+/// used by the plain seeded entry points
+enum SimRng {
+    Std(Box<StdRng>),
+    Xoshiro(Xoshiro256PlusPlus),
+    Pcg(Pcg64),
+    ChaCha(Box<ChaCha20Rng>),
+}
+
+impl SimRng {
+    fn new(algorithm: RngAlgorithm, seed: u64) -> Self {
+        match algorithm {
+            RngAlgorithm::Xoshiro256PlusPlus => SimRng::Xoshiro(Xoshiro256PlusPlus::from_seed_u64(seed)),
+            RngAlgorithm::Pcg64 => SimRng::Pcg(Pcg64::from_seed_u64(seed)),
+            RngAlgorithm::ChaCha20 => SimRng::ChaCha(Box::new(ChaCha20Rng::seed_from_u64(seed))),
         }
     }
-    result
+
+    /// Same stream as `StdRng::seed_from_u64(seed)`
+    fn std(seed: u64) -> Self {
+        SimRng::Std(Box::new(StdRng::seed_from_u64(seed)))
+    }
 }
 
-/// Convolve two components
-fn convolve_pair(c1: &Component, c2: &Component) -> Option<Component> {
-    match (c1, c2) {
-        // Atom + Atom = Atom
-        (Component::Atom { x: x1, p: p1 }, Component::Atom { x: x2, p: p2 }) => {
-            Some(Component::Atom { x: x1 + x2, p: p1 * p2 })
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            SimRng::Std(r) => r.next_u32(),
+            SimRng::Xoshiro(r) => r.next_u32(),
+            SimRng::Pcg(r) => r.next_u32(),
+            SimRng::ChaCha(r) => r.next_u32(),
         }
-        // Atom + Bin = shifted Bin
-        (Component::Atom { x, p: p1 }, Component::Bin { a, b, p: p2 }) |
-        (Component::Bin { a, b, p: p2 }, Component::Atom { x, p: p1 }) => {
-            Some(Component::Bin { a: a + x, b: b + x, p: p1 * p2 })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            SimRng::Std(r) => r.next_u64(),
+            SimRng::Xoshiro(r) => r.next_u64(),
+            SimRng::Pcg(r) => r.next_u64(),
+            SimRng::ChaCha(r) => r.next_u64(),
         }
-        // Bin + Bin = approximated Bin (matching mean and variance)
-        (Component::Bin { a: a1, b: b1, p: p1 }, Component::Bin { a: a2, b: b2, p: p2 }) => {
-            let w1 = b1 - a1;
-            let w2 = b2 - a2;
-            let v1 = w1 * w1 / 12.0;
-            let v2 = w2 * w2 / 12.0;
-            let new_var = v1 + v2;
-            let new_width = (12.0 * new_var).sqrt();
-            let center1 = (a1 + b1) / 2.0;
-            let center2 = (a2 + b2) / 2.0;
-            let new_mean = center1 + center2;
-            Some(Component::Bin {
-                a: new_mean - new_width / 2.0,
-                b: new_mean + new_width / 2.0,
-                p: p1 * p2,
-            })
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            SimRng::Std(r) => r.fill_bytes(dest),
+            SimRng::Xoshiro(r) => r.fill_bytes(dest),
+            SimRng::Pcg(r) => r.fill_bytes(dest),
+            SimRng::ChaCha(r) => r.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            SimRng::Std(r) => r.try_fill_bytes(dest),
+            SimRng::Xoshiro(r) => r.try_fill_bytes(dest),
+            SimRng::Pcg(r) => r.try_fill_bytes(dest),
+            SimRng::ChaCha(r) => r.try_fill_bytes(dest),
         }
-        // Tail combinations - skip (mass loss, handled in JS)
-        _ => None
     }
 }
 
-/// Convolve two distributions
-/// Returns flat array of result components
-#[wasm_bindgen]
-pub fn convolve_distributions(
-    dist1_data: Float64Array,
-    dist2_data: Float64Array,
-) -> Float64Array {
-    #[cfg(feature = "console_error_panic_hook")]
-    set_panic_hook();
+/// Passes a generator through while recording every 64-bit word drawn
+struct RecordingRng<'a, R> {
+    inner: &'a mut R,
+    words: Vec<u64>,
+}
 
-    let data1: Vec<f64> = dist1_data.to_vec();
-    let data2: Vec<f64> = dist2_data.to_vec();
-    
-    let comps1 = parse_components(&data1);
-    let comps2 = parse_components(&data2);
-    
-    let mut result: Vec<Component> = Vec::with_capacity(comps1.len() * comps2.len());
-    
-    for c1 in &comps1 {
-        for c2 in &comps2 {
-            if let Some(c) = convolve_pair(c1, c2) {
-                result.push(c);
+impl<R: RngCore> RngCore for RecordingRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let word = self.inner.next_u64();
+        self.words.push(word);
+        word
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_from_u64(self, dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Antithetic replay of recorded words: yields their bitwise complements,
+/// so every uniform u becomes 1 - 2^-53 - u, then falls back to the
+/// generator once the recording runs out
+struct AntitheticRng<'a, R> {
+    inner: &'a mut R,
+    words: &'a [u64],
+    pos: usize,
+}
+
+impl<R: RngCore> RngCore for AntitheticRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self.words.get(self.pos) {
+            Some(word) => {
+                self.pos += 1;
+                !word
             }
+            None => self.inner.next_u64(),
         }
     }
-    
-    let serialized = serialize_components(&result);
-    Float64Array::from(serialized.as_slice())
-}
 
-// ===========================================
-// Dist Operations - Phase 1 Full Rust Implementation
-// ===========================================
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_from_u64(self, dest);
+    }
 
-/// Get weight of a component
-fn get_weight(c: &Component) -> f64 {
-    match c {
-        Component::Atom { p, .. } => *p,
-        Component::Bin { p, .. } => *p,
-        Component::Tail { mass, .. } => *mass,
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
     }
 }
 
-/// Calculate mean of distribution
+/// How the engine generates the uniforms driving each trial
 #[wasm_bindgen]
-pub fn dist_mean(components_data: Float64Array) -> f64 {
-    let data: Vec<f64> = components_data.to_vec();
-    let components = parse_components(&data);
-    
-    let total_p: f64 = components.iter().map(get_weight).sum();
-    if total_p == 0.0 {
-        return 0.0;
-    }
-    
-    let mut sum = 0.0;
-    for c in &components {
-        match c {
-            Component::Atom { x, p } => {
-                sum += x * p;
-            }
-            Component::Bin { a, b, p } => {
-                let center = (a + b) / 2.0;
-                sum += center * p;
-            }
-            Component::Tail { x0, mass, lambda, is_right } => {
-                // Mean of exponential part: x0 ± 1/lambda
-                let exp_mean = if *is_right { x0 + 1.0 / lambda } else { x0 - 1.0 / lambda };
-                sum += exp_mean * mass;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplingScheme {
+    /// Independent draws from the configured generator
+    PseudoRandom = 0,
+    /// Scrambled Halton sequence: trial i is point i and its first
+    /// `HALTON_MAX_DIMENSIONS` uniforms are successive coordinates; later
+    /// uniforms are pseudo-random
+    Halton = 1,
+}
+
+/// Quasi-random coordinates per trial; uniforms past these are padded with
+/// pseudo-random draws
+///
+/// A step consumes about two uniforms (component choice and position within
+/// it, more with a claim-count model), so only the first dozen or so steps
+/// of a trial are quasi-random. Halton points in the large prime bases of
+/// higher dimensions are no more even than random ones, so extending the
+/// sequence would not help; the gain is in the early steps, which decide
+/// most ruin events for a well-capitalized start.
+const HALTON_MAX_DIMENSIONS: usize = 32;
+
+/// Owen-scrambled Halton sequence
+///
+/// Coordinate k uses the k-th prime base p. Its radical-inverse digits are
+/// scrambled in nested fashion: each digit goes through a random affine
+/// permutation d -> (a d + b) mod p (a non-zero) chosen by a hash of the
+/// seed, the dimension and all the digits before it, so points keep the
+/// stratification of the unscrambled sequence while each one is uniformly
+/// distributed. Digits are scrambled down to double precision, including
+/// the trailing zeros of the index.
+struct HaltonSequence {
+    bases: Vec<u64>,
+    /// Digits needed to resolve 53 bits in each base
+    digits: Vec<u32>,
+    seed: u64,
+    index: u64,
+}
+
+impl HaltonSequence {
+    fn new(seed: u64) -> Self {
+        let mut bases: Vec<u64> = Vec::with_capacity(HALTON_MAX_DIMENSIONS);
+        let mut candidate: u64 = 2;
+        while bases.len() < HALTON_MAX_DIMENSIONS {
+            if !(2..).take_while(|d| d * d <= candidate).any(|d| candidate.is_multiple_of(d)) {
+                bases.push(candidate);
             }
+            candidate += 1;
         }
+        let digits = bases.iter().map(|&b| (53.0 / (b as f64).log2()).ceil() as u32).collect();
+        HaltonSequence { bases, digits, seed, index: 0 }
+    }
+
+    /// Coordinate `dim` (below `HALTON_MAX_DIMENSIONS`) of point `index`
+    fn coordinate(&self, index: u64, dim: usize) -> f64 {
+        let base = self.bases[dim];
+        let mut node = self.seed ^ (dim as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let mut n = index;
+        let mut value = 0.0;
+        let mut scale = 1.0 / base as f64;
+        for _ in 0..self.digits[dim] {
+            let d = n % base;
+            n /= base;
+            let key = splitmix64(&mut node);
+            let (a, b) = (1 + key % (base - 1), (key >> 32) % base);
+            value += ((a * d + b) % base) as f64 * scale;
+            scale /= base as f64;
+            // The next digit's permutation depends on every digit so far
+            node = key.wrapping_add(d);
+        }
+        value
     }
-    sum / total_p
 }
 
-/// Calculate variance of distribution
-#[wasm_bindgen]
-pub fn dist_variance(components_data: Float64Array) -> f64 {
-    let data: Vec<f64> = components_data.to_vec();
-    let components = parse_components(&data);
-    
-    let total_p: f64 = components.iter().map(get_weight).sum();
-    if total_p == 0.0 {
-        return 0.0;
+/// One Halton point presented as a generator: the k-th word drawn encodes
+/// coordinate k, or comes from `padding` past `HALTON_MAX_DIMENSIONS`
+struct HaltonPoint<'a, R> {
+    sequence: &'a HaltonSequence,
+    padding: &'a mut R,
+    index: u64,
+    dim: usize,
+}
+
+impl<R: RngCore> RngCore for HaltonPoint<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
     }
-    
-    // Calculate mean first
-    let mean = dist_mean(components_data.clone());
-    
-    let mut sum_sq = 0.0;
-    for c in &components {
-        match c {
-            Component::Atom { x, p } => {
-                sum_sq += (x - mean).powi(2) * p;
-            }
-            Component::Bin { a, b, p } => {
-                let center = (a + b) / 2.0;
-                let width = b - a;
-                // Variance = (diff from mean)^2 + internal variance
-                let internal_var = width * width / 12.0;
-                sum_sq += ((center - mean).powi(2) + internal_var) * p;
-            }
-            Component::Tail { x0, mass, lambda, is_right } => {
-                let exp_mean = if *is_right { x0 + 1.0 / lambda } else { x0 - 1.0 / lambda };
-                let exp_var = 1.0 / (lambda * lambda);
-                sum_sq += ((exp_mean - mean).powi(2) + exp_var) * mass;
-            }
+
+    fn next_u64(&mut self) -> u64 {
+        if self.dim >= HALTON_MAX_DIMENSIONS {
+            return self.padding.next_u64();
         }
+        let u = self.sequence.coordinate(self.index, self.dim);
+        self.dim += 1;
+        // Scale to the full word so `gen::<f64>()` recovers u
+        (u * 18446744073709551616.0) as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_from_u64(self, dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
     }
-    sum_sq / total_p
 }
 
-/// Calculate standard deviation
-#[wasm_bindgen]
-pub fn dist_std(components_data: Float64Array) -> f64 {
-    dist_variance(components_data).sqrt()
+/// Per-trial state carried by a `StepSource` between steps
+#[derive(Clone, Copy, Default)]
+struct SourceState {
+    /// Current regime of a regime-switching source
+    regime: usize,
+    /// Previous latent Gaussian of an autocorrelated source
+    latent: Option<f64>,
+    /// Accumulated log likelihood ratio of an exponentially tilted source
+    log_weight: f64,
 }
 
-/// Calculate P(X > x) - probability of exceeding x
-#[wasm_bindgen]
-pub fn dist_prob_gt(components_data: Float64Array, x: f64) -> f64 {
-    let data: Vec<f64> = components_data.to_vec();
-    let components = parse_components(&data);
-    
-    let total_p: f64 = components.iter().map(get_weight).sum();
-    if total_p == 0.0 {
-        return 0.0;
+/// Source of the per-step increments of a trial
+trait StepSource {
+    /// State at the start of a trial
+    fn initial_state(&self) -> SourceState {
+        SourceState::default()
     }
-    
-    let mut prob = 0.0;
-    for c in &components {
-        match c {
-            Component::Atom { x: ax, p } => {
-                if *ax > x {
-                    prob += p;
-                }
-            }
-            Component::Bin { a, b, p } => {
-                if *a > x {
-                    prob += p;
-                } else if *b > x {
-                    // Partial overlap
-                    let fraction = (b - x) / (b - a);
-                    prob += p * fraction;
-                }
+
+    /// Increment for 1-based step `step`
+    fn draw(&self, state: &mut SourceState, step: u32, rng: &mut impl Rng) -> f64;
+}
+
+impl StepSource for AliasTable {
+    fn draw(&self, _state: &mut SourceState, _step: u32, rng: &mut impl Rng) -> f64 {
+        self.sample(rng)
+    }
+}
+
+/// Step increments configured on a `MonteCarloEngine`
+enum Increments {
+    /// The same distribution every step
+    Iid(AliasTable),
+    /// `tables[k]` drives steps in block k (`block_len` steps each); the last
+    /// table keeps applying after the schedule runs out
+    Schedule { tables: Vec<AliasTable>, block_len: u32 },
+    /// Markov regime switching: each step draws from the current regime's
+    /// table, then moves to regime j with probability `transition[i * n + j]`
+    /// (rows normalized, cumulative)
+    Regimes { tables: Vec<AliasTable>, cumulative: Vec<f64>, initial: usize },
+    /// AR(1) Gaussian copula: latent z_t = rho * z_{t-1} + sqrt(1 - rho^2) * e_t
+    /// and the increment is Q(Phi(z_t)), so the marginal is unchanged
+    Autocorrelated { table: QuantileTable, rho: f64 },
+    /// Importance sampling: draws from the exponentially tilted density
+    /// e^{theta x} f(x) / M(theta), accumulating the likelihood ratio
+    /// M(theta) e^{-theta x} per step. `table` holds the tilted components;
+    /// `components` the original ones.
+    Tilted { table: AliasTable, components: Vec<Component>, theta: f64, log_mgf: f64 },
+}
+
+/// Exponentially tilted components, e^{theta x} f(x) / M(theta), with
+/// ln M(theta); tilted bins keep their bounds but are sampled from a
+/// truncated exponential (see `tilted_bin_sample`)
+fn tilt_components(components: &[Component], theta: f64) -> Result<(Vec<Component>, f64), String> {
+    let m = mixture_mgf(components, theta);
+    if m.is_nan() {
+        return Err(format!("tilt {} is outside the MGF's region of convergence", theta));
+    }
+    let tilted = components
+        .iter()
+        .map(|c| match *c {
+            Component::Atom { x, p } => Component::Atom { x, p: p * (theta * x).exp() },
+            Component::Bin { a, b, p } => Component::Bin { a, b, p: p * component_mgf(c, theta) },
+            Component::Tail { x0, mass, lambda, is_right } => Component::Tail {
+                x0,
+                mass: mass * component_mgf(c, theta),
+                lambda: if is_right { lambda - theta } else { lambda + theta },
+                is_right,
+            },
+        })
+        .collect();
+    Ok((tilted, m.ln()))
+}
+
+/// Inverse-CDF draw from density proportional to e^{theta x} on [a, b],
+/// anchored at the heavier end so the exponentials cannot overflow
+fn tilted_bin_sample(a: f64, b: f64, theta: f64, u: f64) -> f64 {
+    let w = b - a;
+    if theta == 0.0 || w == 0.0 {
+        a + u * w
+    } else if theta > 0.0 {
+        b + (u * (-theta * w).exp_m1()).ln_1p() / theta
+    } else {
+        a + (u * (theta * w).exp_m1()).ln_1p() / theta
+    }
+}
+
+/// Number of cells in a `QuantileTable`
+const QUANTILE_TABLE_CELLS: usize = 1024;
+
+/// Tabulated quantile function for inverse-transform sampling
+///
+/// Interior cells interpolate linearly between Q(k / N) and Q((k + 1) / N);
+/// the two outer cells and cells spanning an atom evaluate the quantile
+/// exactly, so unbounded tails are kept and draws stay on the support.
+struct QuantileTable {
+    components: Vec<Component>,
+    knots: Vec<f64>,
+    /// Cells whose quantile jumps across an atom
+    exact: Vec<bool>,
+}
+
+impl QuantileTable {
+    fn new(components: Vec<Component>) -> Self {
+        let n = QUANTILE_TABLE_CELLS;
+        let knots: Vec<f64> = (0..=n).map(|k| mixture_quantile(&components, k as f64 / n as f64)).collect();
+        let atoms = point_masses(&components);
+        let exact = knots
+            .windows(2)
+            .map(|w| w[0] < w[1] && atoms.iter().any(|&(x, _)| w[0] <= x && x <= w[1]))
+            .collect();
+        QuantileTable { components, knots, exact }
+    }
+
+    fn value(&self, u: f64) -> f64 {
+        if self.components.is_empty() {
+            return 0.0;
+        }
+        let n = QUANTILE_TABLE_CELLS;
+        let pos = u * n as f64;
+        let k = pos as usize;
+        if k == 0 || k >= n - 1 || self.exact[k] {
+            return mixture_quantile(&self.components, u);
+        }
+        let (lo, hi) = (self.knots[k], self.knots[k + 1]);
+        lo + (pos - k as f64) * (hi - lo)
+    }
+}
+
+impl StepSource for Increments {
+    fn initial_state(&self) -> SourceState {
+        match self {
+            Increments::Regimes { initial, .. } => SourceState { regime: *initial, ..SourceState::default() },
+            _ => SourceState::default(),
+        }
+    }
+
+    fn draw(&self, state: &mut SourceState, step: u32, rng: &mut impl Rng) -> f64 {
+        match self {
+            Increments::Iid(table) => table.sample(rng),
+            Increments::Schedule { tables, block_len } => {
+                let block = ((step - 1) / block_len) as usize;
+                tables[block.min(tables.len() - 1)].sample(rng)
             }
-            Component::Tail { x0, mass, lambda, is_right } => {
-                if *is_right {
-                    // Right tail: P(X > x) where X ~ x0 + Exp(lambda)
-                    if x < *x0 {
-                        prob += mass;
-                    } else {
-                        prob += mass * (-(x - x0) * lambda).exp();
-                    }
-                } else {
-                    // Left tail: P(X > x) where X ~ x0 - Exp(lambda)
-                    if x >= *x0 {
-                        // All mass is <= x0, so P(X > x) = 0
-                    } else {
-                        // P(x0 - Exp > x) = P(Exp < x0 - x) = 1 - exp(-lambda*(x0-x))
-                        prob += mass * (1.0 - (-(x0 - x) * lambda).exp());
-                    }
+            Increments::Regimes { tables, cumulative, .. } => {
+                let n = tables.len();
+                let increment = tables[state.regime].sample(rng);
+                let row = &cumulative[state.regime * n..(state.regime + 1) * n];
+                let u: f64 = rng.gen();
+                state.regime = row.partition_point(|&c| c <= u).min(n - 1);
+                increment
+            }
+            Increments::Autocorrelated { table, rho } => {
+                let e = normal_quantile(rng.sample(Open01));
+                let z = match state.latent {
+                    Some(prev) => rho * prev + (1.0 - rho * rho).sqrt() * e,
+                    None => e,
+                };
+                state.latent = Some(z);
+                table.value(normal_cdf(z))
+            }
+            Increments::Tilted { table, theta, log_mgf, .. } => {
+                if table.components.is_empty() {
+                    return 0.0;
                 }
+                let idx = table.pick(rng);
+                let x = match table.components[idx] {
+                    Component::Bin { a, b, .. } => tilted_bin_sample(a, b, *theta, rng.gen()),
+                    _ => table.sample_component(idx, rng),
+                };
+                state.log_weight += log_mgf - theta * x;
+                x
             }
         }
     }
-    prob / total_p
 }
 
-/// Mix two distributions: result = (1-p)*dist1 + p*dist2
-#[wasm_bindgen]
-pub fn dist_mix(
-    dist1_data: Float64Array,
-    dist2_data: Float64Array,
-    p: f64,
-) -> Float64Array {
-    let data1: Vec<f64> = dist1_data.to_vec();
-    let data2: Vec<f64> = dist2_data.to_vec();
-    
-    let comps1 = parse_components(&data1);
-    let comps2 = parse_components(&data2);
-    
-    let mut result: Vec<Component> = Vec::new();
-    
-    // Scale first distribution by (1-p)
-    for c in comps1 {
-        let scaled = scale_component(&c, 1.0 - p);
-        result.push(scaled);
+/// Validate a row-major n x n transition matrix and return its rows as
+/// normalized cumulative sums
+fn cumulative_transition(transition: &[f64], n: usize) -> Result<Vec<f64>, String> {
+    if transition.len() != n * n {
+        return Err(format!("transition matrix must have {} entries for {} regimes", n * n, n));
     }
-    
-    // Scale second distribution by p
-    for c in comps2 {
-        let scaled = scale_component(&c, p);
-        result.push(scaled);
+    if transition.iter().any(|&p| !(p >= 0.0 && p.is_finite())) {
+        return Err("transition probabilities must be finite and non-negative".to_string());
     }
-    
-    let serialized = serialize_components(&result);
-    Float64Array::from(serialized.as_slice())
+    let mut cumulative = Vec::with_capacity(n * n);
+    for row in transition.chunks(n) {
+        let total: f64 = row.iter().sum();
+        if total <= 0.0 {
+            return Err("each transition row needs positive total probability".to_string());
+        }
+        let mut acc = 0.0;
+        cumulative.extend(row.iter().map(|&p| {
+            acc += p;
+            acc / total
+        }));
+    }
+    Ok(cumulative)
 }
 
-/// Scale a component's probability
-fn scale_component(c: &Component, factor: f64) -> Component {
-    match c {
-        Component::Atom { x, p } => Component::Atom { x: *x, p: p * factor },
-        Component::Bin { a, b, p } => Component::Bin { a: *a, b: *b, p: p * factor },
-        Component::Tail { x0, mass, lambda, is_right } => Component::Tail {
-            x0: *x0,
-            mass: mass * factor,
-            lambda: *lambda,
-            is_right: *is_right,
-        },
-    }
+/// Claim-count model of the (a, b, 0) class, p_k = (a + b / k) p_{k-1}
+///
+/// Flat format: `[0, lambda]` Poisson, `[1, r, p]` negative binomial
+/// (failures before the r-th success, success probability p), `[2, n, p]`
+/// binomial.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Frequency {
+    Poisson { lambda: f64 },
+    NegativeBinomial { r: f64, p: f64 },
+    Binomial { n: u32, p: f64 },
 }
 
-/// Scale distribution values by k
-#[wasm_bindgen]
-pub fn dist_scale(components_data: Float64Array, k: f64) -> Float64Array {
-    let data: Vec<f64> = components_data.to_vec();
-    let components = parse_components(&data);
-    
-    let mut result: Vec<Component> = Vec::new();
-    
-    for c in components {
-        match c {
-            Component::Atom { x, p } => {
-                result.push(Component::Atom { x: x * k, p });
+impl Frequency {
+    fn parse(params: &[f64]) -> Result<Frequency, String> {
+        let frequency = match params {
+            [kind, lambda] if *kind == 0.0 => Frequency::Poisson { lambda: *lambda },
+            [kind, r, p] if *kind == 1.0 => Frequency::NegativeBinomial { r: *r, p: *p },
+            [kind, n, p] if *kind == 2.0 && *n >= 0.0 && n.fract() == 0.0 => Frequency::Binomial { n: *n as u32, p: *p },
+            _ => return Err("frequency must be [0, lambda], [1, r, p] or [2, n, p]".to_string()),
+        };
+        let valid = match frequency {
+            Frequency::Poisson { lambda } => lambda >= 0.0 && lambda.is_finite(),
+            Frequency::NegativeBinomial { r, p } => r > 0.0 && r.is_finite() && p > 0.0 && p <= 1.0,
+            Frequency::Binomial { p, .. } => (0.0..=1.0).contains(&p),
+        };
+        if valid {
+            Ok(frequency)
+        } else {
+            Err(format!("invalid frequency parameters {:?}", frequency))
+        }
+    }
+
+    /// Panjer coefficients (a, b)
+    fn panjer_ab(&self) -> (f64, f64) {
+        match *self {
+            Frequency::Poisson { lambda } => (0.0, lambda),
+            Frequency::NegativeBinomial { r, p } => (1.0 - p, (r - 1.0) * (1.0 - p)),
+            Frequency::Binomial { n, p } => (-p / (1.0 - p), (n as f64 + 1.0) * p / (1.0 - p)),
+        }
+    }
+
+    /// ln P(N = 0)
+    fn ln_p0(&self) -> f64 {
+        match *self {
+            Frequency::Poisson { lambda } => -lambda,
+            Frequency::NegativeBinomial { r, p } => r * p.ln(),
+            Frequency::Binomial { n, p } => n as f64 * (1.0 - p).ln(),
+        }
+    }
+
+    fn variance(&self) -> f64 {
+        match *self {
+            Frequency::Poisson { lambda } => lambda,
+            Frequency::NegativeBinomial { r, p } => r * (1.0 - p) / (p * p),
+            Frequency::Binomial { n, p } => n as f64 * p * (1.0 - p),
+        }
+    }
+
+    /// Probability generating function E[z^N] at a complex point (re, im)
+    fn pgf_complex(&self, z: (f64, f64)) -> (f64, f64) {
+        match *self {
+            Frequency::Poisson { lambda } => complex_exp((lambda * (z.0 - 1.0), lambda * z.1)),
+            Frequency::NegativeBinomial { r, p } => {
+                let base = complex_div((p, 0.0), (1.0 - (1.0 - p) * z.0, -(1.0 - p) * z.1));
+                complex_powf(base, r)
             }
-            Component::Bin { a, b, p } => {
-                if k >= 0.0 {
-                    result.push(Component::Bin { a: a * k, b: b * k, p });
-                } else {
-                    result.push(Component::Bin { a: b * k, b: a * k, p });
+            Frequency::Binomial { n, p } => complex_powf((1.0 - p + p * z.0, p * z.1), n as f64),
+        }
+    }
+
+    /// Probability generating function E[z^N]
+    fn pgf(&self, z: f64) -> f64 {
+        match *self {
+            Frequency::Poisson { lambda } => (lambda * (z - 1.0)).exp(),
+            Frequency::NegativeBinomial { r, p } => (p / (1.0 - (1.0 - p) * z)).powf(r),
+            Frequency::Binomial { n, p } => (1.0 - p + p * z).powi(n as i32),
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        match *self {
+            Frequency::Poisson { lambda } => lambda,
+            Frequency::NegativeBinomial { r, p } => r * (1.0 - p) / p,
+            Frequency::Binomial { n, p } => n as f64 * p,
+        }
+    }
+
+    /// pmf p_0, p_1, ... truncated once the remaining tail is negligible
+    ///
+    /// The recursion runs in log space so large means do not underflow p_0.
+    /// Errs when `FREQUENCY_MAX_COUNT` counts still leave more than
+    /// `FREQUENCY_TRUNCATION_TOLERANCE` of the mass untabulated.
+    fn pmf(&self) -> Result<Vec<f64>, String> {
+        if let Frequency::Binomial { n, p } = *self {
+            if p == 1.0 {
+                if n as usize > FREQUENCY_MAX_COUNT {
+                    return Err(format!("{:?} has more than {} claims", self, FREQUENCY_MAX_COUNT));
                 }
+                let mut pmf = vec![0.0; n as usize + 1];
+                pmf[n as usize] = 1.0;
+                return Ok(pmf);
             }
-            Component::Tail { x0, mass, lambda, is_right } => {
-                if k >= 0.0 {
-                    result.push(Component::Tail {
-                        x0: x0 * k,
-                        mass,
-                        lambda: lambda / k.abs(),
-                        is_right,
-                    });
-                } else {
-                    result.push(Component::Tail {
-                        x0: x0 * k,
-                        mass,
-                        lambda: lambda / k.abs(),
-                        is_right: !is_right,
-                    });
+        }
+        let (a, b) = self.panjer_ab();
+        let mean = self.mean();
+        let mut ln_p = self.ln_p0();
+        let mut pmf = vec![ln_p.exp()];
+        let mut total = pmf[0];
+        for k in 1.. {
+            let factor = a + b / k as f64;
+            if factor <= 0.0 || (k as f64 > mean && (1.0 - total) < FREQUENCY_TAIL_MASS) {
+                break;
+            }
+            if k > FREQUENCY_MAX_COUNT {
+                if 1.0 - total > FREQUENCY_TRUNCATION_TOLERANCE {
+                    return Err(format!(
+                        "{:?} leaves mass {:e} beyond {} claims",
+                        self, 1.0 - total, FREQUENCY_MAX_COUNT
+                    ));
                 }
+                break;
             }
+            ln_p += factor.ln();
+            let pk = ln_p.exp();
+            pmf.push(pk);
+            total += pk;
         }
+        Ok(pmf)
     }
-    
-    let serialized = serialize_components(&result);
-    Float64Array::from(serialized.as_slice())
+}
+
+/// Tail mass left untabulated once the count passes the mean
+const FREQUENCY_TAIL_MASS: f64 = 1e-15;
+
+/// Cap on tabulated claim counts (an 8 MB pmf table)
+const FREQUENCY_MAX_COUNT: usize = 1_000_000;
+
+/// Untabulated mass at the count cap beyond which the pmf is an error;
+/// below it the table is used renormalized
+const FREQUENCY_TRUNCATION_TOLERANCE: f64 = 1e-9;
+
+/// Inverse-CDF sampler of claim counts
+struct ClaimCounts {
+    cumulative: Vec<f64>,
+}
+
+impl ClaimCounts {
+    fn new(frequency: Frequency) -> Result<Self, String> {
+        let pmf = frequency.pmf()?;
+        let total: f64 = pmf.iter().sum();
+        let mut acc = 0.0;
+        let cumulative = pmf
+            .iter()
+            .map(|p| {
+                acc += p;
+                acc / total
+            })
+            .collect();
+        Ok(ClaimCounts { cumulative })
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> usize {
+        let u: f64 = rng.gen();
+        self.cumulative.partition_point(|&c| c <= u).min(self.cumulative.len() - 1)
+    }
+
+    /// Mean claim count of the tabulated (normalized) pmf
+    fn mean(&self) -> f64 {
+        let mut prev = 0.0;
+        self.cumulative
+            .iter()
+            .enumerate()
+            .map(|(k, &c)| {
+                let p = c - prev;
+                prev = c;
+                k as f64 * p
+            })
+            .sum()
+    }
+}
+
+/// Compound step: the increment is the sum of N draws from `severity`, with
+/// N from `counts` (a single draw when there is no frequency model)
+struct Compound<'a, S> {
+    severity: &'a S,
+    counts: Option<&'a ClaimCounts>,
+}
+
+impl<S: StepSource> StepSource for Compound<'_, S> {
+    fn initial_state(&self) -> SourceState {
+        self.severity.initial_state()
+    }
+
+    fn draw(&self, state: &mut SourceState, step: u32, rng: &mut impl Rng) -> f64 {
+        match self.counts {
+            None => self.severity.draw(state, step, rng),
+            Some(counts) => {
+                let n = counts.sample(rng);
+                (0..n).map(|_| self.severity.draw(state, step, rng)).sum()
+            }
+        }
+    }
+}
+
+/// Result of a single simulated trial
+#[derive(Default)]
+struct TrialOutcome {
+    /// 1-based step at which wealth first hit zero or below
+    ruin_step: Option<u32>,
+    /// 1-based step at which wealth first reached the target
+    target_step: Option<u32>,
+    /// Wealth after the last step, or when the trial stopped
+    final_wealth: f64,
+    /// Largest peak-to-trough fall in wealth (peaks include the start)
+    max_drawdown: f64,
+    /// Log likelihood ratio of the path under importance sampling (0 otherwise)
+    log_weight: f64,
+}
+
+/// How much is staked each step as a function of current wealth; the
+/// sampled increment is the outcome per unit stake
+#[derive(Clone)]
+enum StakeRule {
+    /// Unit stake: increments are absolute
+    Constant,
+    /// stake = min(fraction * wealth, cap)
+    Fraction { fraction: f64, cap: f64 },
+    /// stake = stakes[i] for the last threshold[i] <= wealth (stakes[0] below
+    /// the first threshold)
+    Table { thresholds: Vec<f64>, stakes: Vec<f64> },
+    /// stake = callback(wealth); a throw or non-number result stakes 0
+    Callback(js_sys::Function),
+}
+
+impl StakeRule {
+    fn stake(&self, wealth: f64) -> f64 {
+        match self {
+            StakeRule::Constant => 1.0,
+            StakeRule::Fraction { fraction, cap } => (fraction * wealth).min(*cap),
+            StakeRule::Table { thresholds, stakes } => {
+                let i = thresholds.partition_point(|&t| t <= wealth);
+                stakes[i.saturating_sub(1)]
+            }
+            StakeRule::Callback(f) => f
+                .call1(&JsValue::NULL, &JsValue::from_f64(wealth))
+                .ok()
+                .and_then(|v| v.as_f64())
+                .filter(|v| v.is_finite())
+                .unwrap_or(0.0),
+        }
+    }
+}
+
+/// Rules applied at every step of a trial
+#[derive(Clone)]
+struct StepRules {
+    /// Upper absorbing barrier: a trial reaching it stops as a success
+    target: f64,
+    /// Stake per step as a function of wealth
+    stake: StakeRule,
+    /// Ruin also when wealth falls to this fraction of its running peak
+    /// (0 keeps ruin at zero wealth only)
+    drawdown_floor: f64,
+    /// Per-step rate r: wealth *= 1 + r before the increment is added
+    interest: f64,
+    /// Deterministic income added every step alongside the increment
+    premium: f64,
+}
+
+impl Default for StepRules {
+    fn default() -> Self {
+        StepRules { target: f64::INFINITY, stake: StakeRule::Constant, drawdown_floor: 0.0, interest: 0.0, premium: 0.0 }
+    }
+}
+
+/// A trial in progress
+struct PathState {
+    wealth: f64,
+    peak: f64,
+    max_drawdown: f64,
+    ruin_step: Option<u32>,
+    target_step: Option<u32>,
+    source: SourceState,
+}
+
+impl PathState {
+    fn new(init_wealth: f64, source: SourceState) -> Self {
+        PathState {
+            wealth: init_wealth,
+            peak: init_wealth,
+            max_drawdown: 0.0,
+            ruin_step: None,
+            target_step: None,
+            source,
+        }
+    }
+
+    /// Whether the trial was stopped by ruin or by reaching the target
+    fn is_done(&self) -> bool {
+        self.ruin_step.is_some() || self.target_step.is_some()
+    }
+
+    /// Draw step `step`'s increment from `source` and apply it
+    fn step(&mut self, step: u32, source: &impl StepSource, rules: &StepRules, rng: &mut impl Rng) {
+        let increment = source.draw(&mut self.source, step, rng);
+        self.advance(step, increment, rules);
+    }
+
+    /// Apply step `step`'s increment (per unit stake) and check the barriers
+    fn advance(&mut self, step: u32, increment: f64, rules: &StepRules) {
+        if rules.interest != 0.0 {
+            self.wealth *= 1.0 + rules.interest;
+        }
+        self.wealth += rules.premium;
+        self.wealth += match rules.stake {
+            StakeRule::Constant => increment,
+            ref rule => increment * rule.stake(self.wealth),
+        };
+        self.peak = self.peak.max(self.wealth);
+        self.max_drawdown = self.max_drawdown.max(self.peak - self.wealth);
+        if self.wealth <= 0.0 || self.wealth <= rules.drawdown_floor * self.peak {
+            self.ruin_step = Some(step);
+        } else if self.wealth >= rules.target {
+            self.target_step = Some(step);
+        }
+    }
+
+    fn outcome(&self) -> TrialOutcome {
+        TrialOutcome {
+            ruin_step: self.ruin_step,
+            target_step: self.target_step,
+            final_wealth: self.wealth,
+            max_drawdown: self.max_drawdown,
+            log_weight: self.source.log_weight,
+        }
+    }
+}
+
+/// Simulate one trial as `simulate_trial` does, but keep drawing through all
+/// `steps` after it stops; returns the outcome and the sum of every draw, a
+/// control variate with known mean
+fn simulate_trial_with_control(
+    source: &impl StepSource,
+    init_wealth: f64,
+    steps: u32,
+    rules: &StepRules,
+    rng: &mut impl Rng,
+) -> (TrialOutcome, f64) {
+    let mut state = PathState::new(init_wealth, source.initial_state());
+    let mut control = 0.0;
+    for step in 1..=steps {
+        let increment = source.draw(&mut state.source, step, rng);
+        control += increment;
+        if !state.is_done() {
+            state.advance(step, increment, rules);
+        }
+    }
+    (state.outcome(), control)
+}
+
+/// Simulate one wealth path of up to `steps` steps, stopping at ruin or at
+/// the target
+fn simulate_trial(
+    source: &impl StepSource,
+    init_wealth: f64,
+    steps: u32,
+    rules: &StepRules,
+    rng: &mut impl Rng,
+) -> TrialOutcome {
+    let mut state = PathState::new(init_wealth, source.initial_state());
+    for step in 1..=steps {
+        state.step(step, source, rules, rng);
+        if state.is_done() {
+            break;
+        }
+    }
+    state.outcome()
+}
+
+/// Count trials whose wealth hits zero or below within `steps` steps
+fn count_ruins(
+    alias_table: &AliasTable,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    rng: &mut impl Rng,
+) -> u32 {
+    (0..num_trials)
+        .filter(|_| simulate_trial(alias_table, init_wealth, steps, &StepRules::default(), rng).ruin_step.is_some())
+        .count() as u32
+}
+
+/// Run Monte Carlo simulation
+/// 
+/// # Arguments
+/// * `components_data` - Flat array of component data
+/// * `init_wealth` - Initial wealth
+/// * `steps` - Number of steps per trial
+/// * `num_trials` - Number of simulation trials
+/// * `seed` - Seed for `StdRng`; omit it to draw from an unseeded generator
+/// 
+/// The same seed gives the same count on every run and platform with a
+/// given build. `StdRng`'s algorithm may change between rand releases; use
+/// `run_monte_carlo_with_rng` for a stream pinned to a named algorithm.
+/// 
+/// # Returns
+/// Number of trials that resulted in ruin
+#[wasm_bindgen]
+pub fn run_monte_carlo(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: Option<u64>,
+) -> u32 {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let alias_table = AliasTable::new(components);
+
+    match seed {
+        Some(seed) => count_ruins(&alias_table, init_wealth, steps, num_trials, &mut StdRng::seed_from_u64(seed)),
+        None => count_ruins(&alias_table, init_wealth, steps, num_trials, &mut rand::thread_rng()),
+    }
+}
+
+/// Run Monte Carlo simulation with a chosen RNG algorithm
+///
+/// Same as `run_monte_carlo`, but draws from `algorithm` seeded with `seed`.
+/// Results are reproducible for a fixed (algorithm, seed) pair.
+#[wasm_bindgen]
+pub fn run_monte_carlo_with_rng(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    algorithm: RngAlgorithm,
+) -> u32 {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let alias_table = AliasTable::new(components);
+
+    let mut rng = SimRng::new(algorithm, seed);
+    count_ruins(&alias_table, init_wealth, steps, num_trials, &mut rng)
+}
+
+/// Run one independent chunk of a Monte Carlo simulation
+///
+/// Intended to be called once per web worker with a distinct `chunk_seed`;
+/// the caller sums the returned ruin counts. Each chunk depends only on its
+/// own seed, so the total is the same however the chunks are scheduled.
+/// 
+/// # Returns
+/// Number of trials in this chunk that resulted in ruin
+#[wasm_bindgen]
+pub fn run_monte_carlo_chunk(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    trials_in_chunk: u32,
+    chunk_seed: u64,
+) -> u32 {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let alias_table = AliasTable::new(components);
+
+    let mut rng = StdRng::seed_from_u64(chunk_seed);
+    count_ruins(&alias_table, init_wealth, steps, trials_in_chunk, &mut rng)
+}
+
+/// Ruin probability within `steps` steps for each initial wealth
+///
+/// With `common_random_numbers` every wealth value is tested against the
+/// same increment paths: a path ruins wealth w exactly when w plus its
+/// lowest partial sum is at or below zero, so the curve is non-increasing
+/// in w. Otherwise each wealth value gets its own independent trials.
+fn ruin_curve_over_wealth(
+    alias_table: &AliasTable,
+    wealth_values: &[f64],
+    steps: u32,
+    trials_per_point: u32,
+    common_random_numbers: bool,
+    rng: &mut impl Rng,
+) -> Vec<f64> {
+    if trials_per_point == 0 {
+        return vec![f64::NAN; wealth_values.len()];
+    }
+    let n = trials_per_point as f64;
+    if !common_random_numbers {
+        return wealth_values
+            .iter()
+            .map(|&w| count_ruins(alias_table, w, steps, trials_per_point, rng) as f64 / n)
+            .collect();
+    }
+    // A path can stop early once its low point ruins every wealth value
+    let max_wealth = wealth_values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut ruins = vec![0u32; wealth_values.len()];
+    for _ in 0..trials_per_point {
+        let mut sum = 0.0;
+        let mut low = f64::INFINITY;
+        for _ in 0..steps {
+            sum += alias_table.sample(rng);
+            low = low.min(sum);
+            if max_wealth + low <= 0.0 {
+                break;
+            }
+        }
+        for (count, &w) in ruins.iter_mut().zip(wealth_values) {
+            if w + low <= 0.0 {
+                *count += 1;
+            }
+        }
+    }
+    ruins.iter().map(|&c| c as f64 / n).collect()
+}
+
+/// Ruin probability for each initial wealth in `wealth_values`, in one call
+///
+/// `common_random_numbers` (default true) reuses the same increment paths at
+/// every wealth value, which gives a smooth, monotone curve; false runs
+/// independent trials per point.
+///
+/// # Returns
+/// Ruin probability per wealth value, in input order (NaN when
+/// `trials_per_point` is 0)
+#[wasm_bindgen]
+pub fn run_monte_carlo_wealth_grid(
+    components_data: Float64Array,
+    wealth_values: Float64Array,
+    steps: u32,
+    trials_per_point: u32,
+    seed: u64,
+    common_random_numbers: Option<bool>,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let alias_table = AliasTable::new(parse_components(&data));
+    let wealth_values: Vec<f64> = wealth_values.to_vec();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let curve = ruin_curve_over_wealth(
+        &alias_table,
+        &wealth_values,
+        steps,
+        trials_per_point,
+        common_random_numbers.unwrap_or(true),
+        &mut rng,
+    );
+    Float64Array::from(&curve[..])
+}
+
+/// Draw n i.i.d. samples from an alias table
+fn sample_n(alias_table: &AliasTable, n: usize, rng: &mut impl Rng) -> Vec<f64> {
+    (0..n).map(|_| alias_table.sample(rng)).collect()
+}
+
+/// Draw `n` samples with component counts allocated exactly in proportion
+/// to the weights
+///
+/// Systematic allocation: positions (k + U) / n on the cumulative weights
+/// pick the components, so component i receives floor or ceil of n * w_i
+/// draws; values within components are random and the result is shuffled.
+fn sample_n_stratified(alias_table: &AliasTable, n: usize, rng: &mut impl Rng) -> Vec<f64> {
+    let components = &alias_table.components;
+    if components.is_empty() || n == 0 {
+        return vec![0.0; n];
+    }
+    let weights: Vec<f64> = components.iter().map(get_weight).collect();
+    let total: f64 = weights.iter().sum();
+    let weights = if total > 0.0 { weights } else { vec![1.0; components.len()] };
+    let total: f64 = weights.iter().sum();
+
+    let offset: f64 = rng.gen();
+    let mut samples = Vec::with_capacity(n);
+    let mut idx = 0;
+    let mut upper = weights[0];
+    for k in 0..n {
+        let position = (k as f64 + offset) / n as f64 * total;
+        while position >= upper && idx + 1 < weights.len() {
+            idx += 1;
+            upper += weights[idx];
+        }
+        samples.push(alias_table.sample_component(idx, rng));
+    }
+    samples.shuffle(rng);
+    samples
+}
+
+/// Draw `n` samples stratified across mixture components: each component
+/// gets its exact share of the draws (floor or ceil of n times its weight)
+/// instead of a multinomial count, which steadies estimates driven by small
+/// components such as tails. Reproducible from `seed`.
+#[wasm_bindgen]
+pub fn dist_sample_stratified(components_data: Float64Array, n: u32, seed: u64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let alias_table = AliasTable::new(parse_components(&data));
+    let mut rng = StdRng::seed_from_u64(seed);
+    let samples = sample_n_stratified(&alias_table, n as usize, &mut rng);
+    Float64Array::from(samples.as_slice())
+}
+
+/// Draw `n` i.i.d. samples from the distribution
+///
+/// The same `seed` always yields the same draws.
+#[wasm_bindgen]
+pub fn dist_sample_n(components_data: Float64Array, n: u32, seed: u64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let alias_table = AliasTable::new(components);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let samples = sample_n(&alias_table, n as usize, &mut rng);
+    Float64Array::from(samples.as_slice())
+}
+
+/// Draw `n` i.i.d. samples using the chosen RNG algorithm
+#[wasm_bindgen]
+pub fn dist_sample_n_with_rng(components_data: Float64Array, n: u32, seed: u64, algorithm: RngAlgorithm) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let alias_table = AliasTable::new(components);
+
+    let mut rng = SimRng::new(algorithm, seed);
+    let samples = sample_n(&alias_table, n as usize, &mut rng);
+    Float64Array::from(samples.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// +-1 walk stepping up with probability `p_up`
+    fn coin(p_up: f64) -> Vec<Component> {
+        parse_components(&[0.0, -1.0, 1.0 - p_up, 0.0, 1.0, p_up])
+    }
+
+    /// Engine on the +-1 walk `coin(p_up)`
+    fn coin_engine(p_up: f64, init_wealth: f64, steps: u32, seed: u64) -> MonteCarloEngine {
+        MonteCarloEngine::from_components(coin(p_up), init_wealth, steps, seed)
+    }
+
+    /// Exact probability that `coin(p_up)` is ruined within `steps` steps
+    fn coin_ruin(p_up: f64, init_wealth: f64, steps: u32) -> f64 {
+        lattice_ruin_probability(&coin(p_up), init_wealth, steps, f64::INFINITY)
+    }
+
+    /// Simulated probability from `trials` trials within four binomial
+    /// standard errors of the exact value
+    fn assert_near_exact(estimate: f64, exact: f64, trials: u32) {
+        let tolerance = 4.0 * (exact * (1.0 - exact) / trials as f64).sqrt();
+        assert!((estimate - exact).abs() <= tolerance, "estimate {} exact {} (+-{})", estimate, exact, tolerance);
+    }
+
+    #[test]
+    fn test_parse_components() {
+        // atom: type=0, x=10, p=0.5
+        let data = vec![0.0, 10.0, 0.5];
+        let comps = parse_components(&data);
+        assert_eq!(comps.len(), 1);
+    }
+
+    #[test]
+    fn test_min_variance_mix() {
+        let wide = parse_components(&[1.0, -10.0, 10.0, 0.5, 0.0, 3.0, 0.5]);
+        let narrow = parse_components(&[1.0, 1.0, 2.0, 0.7, 0.0, 4.0, 0.3]);
+
+        assert_eq!(min_variance_mix_weight(&wide, &wide), 0.5);
+
+        let p = min_variance_mix_weight(&wide, &narrow);
+        let best = mixture_variance(&mix_components(&wide, &narrow, p));
+        for i in 0..=20 {
+            let q = i as f64 / 20.0;
+            let v = mixture_variance(&mix_components(&wide, &narrow, q));
+            assert!(best <= v + 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_format_components() {
+        let data = vec![0.0, 10.0, 0.5, 1.0, 0.0, 5.0, 0.3, 2.0, 20.0, 0.2, 0.5, 1.0];
+        let text = format_components(&parse_components(&data));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "Atom(x=10, p=0.5)");
+        assert_eq!(lines[2], "Tail(x0=20, mass=0.2, lambda=0.5, right)");
+        assert!(lines[3].starts_with("Total mass: "));
+    }
+
+    #[test]
+    fn test_equal_mass_bins() {
+        // Bin [0, 10] with 0.6, atom at 4 with 0.2, right tail from 10 with 0.2
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.6, 0.0, 4.0, 0.2, 2.0, 10.0, 0.2, 0.5, 1.0]);
+        let n = 5;
+        let bins = equal_mass_bins(&comps, n);
+        assert_eq!(bins.len(), n);
+        for (i, bin) in bins.iter().enumerate() {
+            match bin {
+                Component::Bin { a, b, p } => {
+                    assert!((p - 0.2).abs() < 1e-12);
+                    assert!(a.is_finite() && b.is_finite() && a <= b);
+                    if i > 0 {
+                        let q = mixture_quantile(&comps, i as f64 / n as f64);
+                        assert!((a - q).abs() < 1e-9);
+                    }
+                }
+                _ => panic!("expected bins"),
+            }
+        }
+        // The last bin keeps the tail slice's mean (10 + 1/0.5)
+        assert!((mixture_mean(&bins[4..]) - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_refined_quantile() {
+        // Right tail overlapping a bin makes the CDF curved inside [0, 10]
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.5, 2.0, 0.0, 0.5, 0.5, 1.0]);
+        let q = 0.6;
+        let cdf_err = |x: f64| (mass_at_or_below(&comps, x) / total_mass(&comps) - q).abs();
+
+        let estimate = refined_quantile(&comps, q, 0);
+        let refined = refined_quantile(&comps, q, 4);
+        assert!(cdf_err(refined) < cdf_err(estimate));
+        assert!(cdf_err(refined) < 1e-9);
+
+        // Inside a lone tail the seed is already the exact inverse
+        let comps = parse_components(&[0.0, 0.0, 0.4, 2.0, 1.0, 0.6, 0.5, 1.0]);
+        let seeded = refined_quantile(&comps, 0.9, 0);
+        let expected = 1.0 + 2.0 * (0.6f64 / 0.1).ln();
+        assert!((seeded - expected).abs() < 1e-12);
+        let left = negate_components(&comps);
+        assert!((refined_quantile(&left, 0.1, 0) + expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_stratified_scenarios_ks() {
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.5, 0.0, 3.0, 0.2, 2.0, 10.0, 0.3, 0.5, 1.0]);
+        let n = 500;
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut xs = stratified_scenarios(&comps, n, &mut rng);
+        xs.sort_by(|a, b| a.total_cmp(b));
+
+        // Kolmogorov-Smirnov distance between empirical and true CDF
+        let mut ks: f64 = 0.0;
+        for (i, &x) in xs.iter().enumerate() {
+            let f = mass_at_or_below(&comps, x);
+            let f_left = mass_below(&comps, x);
+            ks = ks.max((i + 1) as f64 / n as f64 - f).max(f_left - i as f64 / n as f64);
+        }
+        assert!(ks <= 1.5 / n as f64, "ks = {}", ks);
+    }
+
+    #[test]
+    fn test_compound_growth() {
+        let value = parse_components(&[1.0, 90.0, 110.0, 0.8, 2.0, 110.0, 0.2, 0.1, 1.0]);
+        let factor = 1.05f64.powi(3);
+        let scaled = compound_growth(&value, 0.05, 3).unwrap();
+        assert!((mixture_mean(&scaled) - mixture_mean(&value) * factor).abs() < 1e-9);
+        assert!((mixture_variance(&scaled) - mixture_variance(&value) * factor * factor).abs() < 1e-6);
+        let unchanged = compound_growth(&value, 0.05, 0).unwrap();
+        assert_eq!(serialize_components(&unchanged), serialize_components(&value));
+        let wiped = compound_growth(&value, -1.0, 2).unwrap();
+        assert_eq!(serialize_components(&wiped), vec![0.0, 0.0, 1.0]);
+        assert!(compound_growth(&value, f64::NAN, 3).is_err());
+        assert!(compound_growth(&value, 1e10, 40).is_err());
+
+        // Zero or non-finite total weight leaves nothing to project
+        assert!(project_to_lattice(&[(1.0, 0.0), (2.0, 0.0)], 1).is_empty());
+        assert!(project_to_lattice(&[(1.0, f64::NAN)], 4).is_empty());
+
+        // A degenerate rate reduces the stochastic variant to the deterministic one
+        let rate = parse_components(&[0.0, 0.05, 1.0]);
+        let stochastic = compound_growth_stochastic(&value, &rate, 3, 16);
+        assert!((mixture_mean(&stochastic) - mixture_mean(&scaled)).abs() < 1e-9);
+        assert!((mixture_variance(&stochastic) - mixture_variance(&scaled)).abs() < 1e-6);
+
+        // Uncertain rates keep the mean at E[X] * E[1 + R]^periods
+        let rate = parse_components(&[0.0, -0.1, 0.5, 0.0, 0.2, 0.5]);
+        let stochastic = compound_growth_stochastic(&value, &rate, 3, 32);
+        let expected = mixture_mean(&value) * 1.05f64.powi(3);
+        assert!((mixture_mean(&stochastic) - expected).abs() / expected < 0.01);
+    }
+
+    #[test]
+    fn test_winsorized_mean() {
+        // Body on [0, 10] plus a heavy right tail far out
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.9, 2.0, 50.0, 0.1, 0.02, 1.0]);
+        let mean = mixture_mean(&comps);
+        assert!((winsorized_mean(&comps, 0.0) - mean).abs() < 1e-12);
+
+        let w = winsorized_mean(&comps, 0.15);
+        assert!(w < mean && w > 5.0);
+
+        // Symmetric body: winsorizing leaves the mean unchanged
+        let body = parse_components(&[1.0, -1.0, 1.0, 1.0]);
+        assert!(winsorized_mean(&body, 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fit_tail_from_body() {
+        // Crude bin-based tail as produced by convolution
+        let comps = parse_components(&[
+            1.0, 0.0, 10.0, 0.7, 1.0, 10.0, 14.0, 0.2, 1.0, 14.0, 20.0, 0.08, 0.0, 30.0, 0.02,
+        ]);
+        let t = 12.0;
+        let fitted = fit_tail_from_body(&comps, t, 1);
+
+        let beyond = |cs: &[Component], d: f64| total_mass(cs) - mass_at_or_below(cs, d);
+        assert!((beyond(&fitted, t) - beyond(&comps, t)).abs() < 1e-12);
+        assert!((total_mass(&fitted) - total_mass(&comps)).abs() < 1e-12);
+
+        let mean_excess = |cs: &[Component], d: f64| {
+            let m: f64 = cs.iter().map(|c| component_partial_expectation(c, d, f64::INFINITY)).sum();
+            m / beyond(cs, d) - d
+        };
+        let e = mean_excess(&fitted, t);
+        assert!((mean_excess(&fitted, t + 3.0) - e).abs() < 1e-9);
+        assert!((mean_excess(&fitted, t + 20.0) - e).abs() < 1e-9);
+        assert!((e - mean_excess(&comps, t)).abs() < 1e-9);
+
+        // Left side mirrors the right
+        let left = fit_tail_from_body(&comps, 2.0, -1);
+        assert!((mass_below(&left, 2.0) - mass_below(&comps, 2.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_monte_carlo_chunks_independent() {
+        let table = AliasTable::new(parse_components(&[0.0, 1.0, 0.48, 0.0, -1.0, 0.52]));
+        let seeds = [11u64, 12, 13, 14];
+        let chunk = |seed: u64| count_ruins(&table, 5.0, 200, 2500, &mut StdRng::seed_from_u64(seed));
+
+        let forward: u32 = seeds.iter().map(|&s| chunk(s)).sum();
+        let reverse: u32 = seeds.iter().rev().map(|&s| chunk(s)).sum();
+        assert_eq!(forward, reverse);
+
+        // The chunk total estimates the same probability as one full run of
+        // 10,000 trials, and both match the exact lattice value (sd ~0.005)
+        let exact = lattice_ruin_probability(&table.components, 5.0, 200, f64::INFINITY);
+        let single = count_ruins(&table, 5.0, 200, 10_000, &mut StdRng::seed_from_u64(11));
+        let (p_chunks, p_single) = (forward as f64 / 10_000.0, single as f64 / 10_000.0);
+        assert!((p_chunks - exact).abs() < 0.02, "chunks {} exact {}", p_chunks, exact);
+        assert!((p_single - exact).abs() < 0.02, "single {} exact {}", p_single, exact);
+        assert!((p_chunks - p_single).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_concat_components() {
+        let parts = vec![
+            parse_components(&[0.0, 1.0, 0.2, 1.0, 0.0, 2.0, 0.1]),
+            parse_components(&[2.0, 5.0, 0.3, 1.0, 1.0]),
+            parse_components(&[0.0, -1.0, 0.4, 9.0, 0.0]),
+        ];
+        let all = concat_components(&parts);
+        assert_eq!(all.len(), 4);
+        assert!((total_mass(&all) - 1.0).abs() < 1e-12);
+
+        // Round-tripping drops the malformed trailing component
+        let sanitized = serialize_components(&parse_components(&[0.0, 1.0, 0.5, 1.0, 2.0]));
+        assert_eq!(sanitized, vec![0.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_convert_tails() {
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.6, 2.0, 10.0, 0.3, 0.5, 1.0, 2.0, 0.0, 0.1, 2.0, 0.0]);
+        let mean = mixture_mean(&comps);
+
+        assert_eq!(convert_tails(&comps, 0, 0.9).len(), comps.len());
+
+        let binned = convert_tails(&comps, 1, 0.9);
+        assert!(binned.iter().all(|c| !matches!(c, Component::Tail { .. })));
+        assert!((total_mass(&binned) - total_mass(&comps)).abs() < 1e-12);
+        assert!((mixture_mean(&binned) - mean).abs() < 0.5);
+
+        let exact = convert_tails(&comps, 1, 1.0 - (-2.0f64).exp());
+        assert!((mixture_mean(&exact) - mean).abs() < 1e-9);
+
+        let atoms = convert_tails(&comps, 2, 0.0);
+        assert!((mixture_mean(&atoms) - mean).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_maxent_from_quantiles() {
+        let ps = [0.1, 0.5, 0.9];
+        let xs = [2.0, 5.0, 11.0];
+        let comps = maxent_from_quantiles(&ps, &xs).unwrap();
+        for (&p, &x) in ps.iter().zip(&xs) {
+            assert!((mixture_quantile(&comps, p) - x).abs() < 1e-9);
+        }
+        assert!((total_mass(&comps) - 1.0).abs() < 1e-12);
+
+        // Flat density between points, continuous at the tail junctions
+        let d = |x: f64| mass_density(&comps, x);
+        assert!((d(2.5) - d(4.5)).abs() < 1e-12);
+        assert!((d(5.5) - d(10.5)).abs() < 1e-12);
+        assert!((d(1.999999) - d(2.0)).abs() < 1e-6);
+        assert!((d(11.0) - d(10.999999)).abs() < 1e-6);
+
+        assert!(maxent_from_quantiles(&[0.5, 0.4], &[1.0, 2.0]).is_err());
+        assert!(maxent_from_quantiles(&[0.5], &[1.0]).is_err());
+    }
+
+    #[test]
+    fn test_scenario_mix() {
+        let conditionals = vec![
+            parse_components(&[1.0, 0.0, 10.0, 1.0]),
+            parse_components(&[0.0, -20.0, 0.5, 0.0, -10.0, 0.5]),
+            parse_components(&[2.0, 5.0, 1.0, 0.25, 1.0]),
+        ];
+        let probs = [0.6, 0.1, 0.3];
+        let mixed = scenario_mix(&conditionals, &probs).unwrap();
+
+        let expected: f64 = conditionals.iter().zip(&probs).map(|(c, p)| p * mixture_mean(c)).sum();
+        assert!((mixture_mean(&mixed) - expected).abs() < 1e-12);
+        assert!((total_mass(&mixed) - 1.0).abs() < 1e-12);
+
+        assert!(scenario_mix(&conditionals, &[0.6, 0.1, 0.2]).is_err());
+        assert!(scenario_mix(&conditionals, &[0.6, 0.4]).is_err());
+    }
+
+    #[test]
+    fn test_spectral_risk() {
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.7, 2.0, 0.0, 0.2, 0.3, 0.0, 0.0, 12.0, 0.1]);
+        let alpha = 0.1;
+
+        // phi uniform on [0, alpha] reproduces the lower-tail CVaR
+        let cvar = slice_expectation(&comps, 0.0, alpha) / alpha;
+        let w = 1.0 / alpha;
+        let risk = spectral_risk(&comps, &[0.0, alpha, alpha, 1.0], &[w, w, 0.0, 0.0]).unwrap();
+        assert!((risk - cvar).abs() < 1e-9);
+
+        // Constant phi gives the mean
+        let mean = spectral_risk(&comps, &[0.0, 1.0], &[1.0, 1.0]).unwrap();
+        assert!((mean - mixture_mean(&comps)).abs() < 1e-9);
+
+        // Linearly decreasing phi lies between the mean and the tail CVaR
+        let linear = spectral_risk(&comps, &[0.0, 1.0], &[2.0, 0.0]).unwrap();
+        assert!(linear < mixture_mean(&comps) && linear > cvar);
+
+        assert!(spectral_risk(&comps, &[0.0, 1.0], &[1.0, 2.0]).is_err());
+        assert!(spectral_risk(&comps, &[0.0, 0.5, 1.0], &[1.0, 0.0, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_modified_var() {
+        // Loss body on [0, 10] with a fat right tail
+        let comps = parse_components(&[1.0, 0.0, 10.0, 0.9, 2.0, 10.0, 0.1, 0.2, 1.0]);
+        let alpha = 0.99;
+        let exact = mixture_quantile(&comps, alpha);
+        let normal = mixture_mean(&comps) + mixture_variance(&comps).sqrt() * normal_quantile(alpha);
+        let modified = modified_var(&comps, alpha);
+        assert!((modified - exact).abs() < (normal - exact).abs());
+
+        assert!((normal_quantile(0.975) - 1.959963985).abs() < 1e-8);
+        assert!((mixture_central_moment(&comps, 2) - mixture_variance(&comps)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_running_sum() {
+        let dists = vec![
+            parse_components(&[0.0, 1.0, 0.5, 0.0, 3.0, 0.5]),
+            parse_components(&[1.0, 0.0, 2.0, 0.7, 0.0, 5.0, 0.3]),
+            parse_components(&[0.0, -1.0, 0.25, 1.0, 1.0, 4.0, 0.75]),
+        ];
+        let mut handle = RunningSum::new();
+        for d in &dists {
+            handle.add_components(d, 0);
+        }
+        let direct = dists[1..].iter().fold(dists[0].clone(), |acc, d| convolve_components(&acc, d));
+        assert_eq!(serialize_components(&handle.components), serialize_components(&direct));
+
+        // Compression keeps mass, mean and variance
+        let mut compressed = RunningSum::new();
+        for d in &dists {
+            compressed.add_components(d, 4);
+        }
+        assert!(compressed.components.len() <= 4);
+        assert!((total_mass(&compressed.components) - total_mass(&direct)).abs() < 1e-12);
+        assert!((mixture_mean(&compressed.components) - mixture_mean(&direct)).abs() < 1e-9);
+        assert!((mixture_variance(&compressed.components) - mixture_variance(&direct)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_quantile() {
+        // Left tail below 0, bin [0, 4], atom at 2, right tail above 4
+        let comps = parse_components(&[
+            2.0, 0.0, 0.1, 1.0, 0.0, 1.0, 0.0, 4.0, 0.5, 0.0, 2.0, 0.2, 2.0, 4.0, 0.2, 0.5, 1.0,
+        ]);
+        for &p in &[0.001, 0.05, 0.2, 0.3, 0.6, 0.75, 0.85, 0.95, 0.9999] {
+            let x = mixture_quantile(&comps, p);
+            assert!(mass_below(&comps, x) <= p + 1e-12 && mass_at_or_below(&comps, x) >= p - 1e-12);
+        }
+        // Levels inside the atom's jump [0.35, 0.55] return the atom
+        assert_eq!(mixture_quantile(&comps, 0.4), 2.0);
+        assert_eq!(mixture_quantile(&comps, 0.55), 2.0);
+        // Inside the bin only: linear interpolation is exact
+        assert!((mixture_quantile(&comps, 0.2) - 0.8).abs() < 1e-12);
+
+        assert_eq!(mixture_quantile(&comps, 0.0), f64::NEG_INFINITY);
+        assert_eq!(mixture_quantile(&comps, 1.0), f64::INFINITY);
+        assert!(mixture_quantile(&comps, 1.5).is_nan());
+    }
+
+    #[test]
+    fn test_cdf_tie_convention() {
+        let comps = parse_components(&[0.0, 5.0, 0.25, 1.0, 0.0, 10.0, 0.5, 2.0, 10.0, 0.25, 1.0, 1.0]);
+        for &x in &[-1.0, 0.0, 2.5, 5.0, 10.0, 12.0] {
+            assert!((mixture_cdf(&comps, x) + mixture_prob_gt(&comps, x) - 1.0).abs() < 1e-12);
+        }
+        // The atom at 5 is in the CDF but not in P(X < 5)
+        assert!((mixture_cdf(&comps, 5.0) - 0.5).abs() < 1e-12);
+        assert!((mixture_prob_lt(&comps, 5.0) - 0.25).abs() < 1e-12);
+        assert!((mixture_cdf(&comps, 11.0) - (0.75 + 0.25 * (1.0 - (-1.0f64).exp()))).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_prob_between() {
+        let comps = parse_components(&[
+            2.0, 0.0, 0.2, 2.0, 0.0, 1.0, 5.0, 10.0, 0.3, 0.0, 7.0, 0.2, 2.0, 10.0, 0.3, 0.5, 1.0,
+        ]);
+        for &(a, b) in &[(-3.0, -1.0), (-1.0, 6.0), (6.0, 7.0), (7.0, 12.0), (9.0, 20.0), (-1e9, 1e9)] {
+            let direct = mixture_prob_between(&comps, a, b);
+            let diff = mixture_cdf(&comps, b) - mixture_cdf(&comps, a);
+            assert!((direct - diff).abs() < 1e-12, "({}, {})", a, b);
+        }
+        // Tiny interval deep in the tail keeps its relative precision
+        let tiny = mixture_prob_between(&comps, 40.0, 40.0 + 1e-6);
+        let expected = 0.3 * 0.5 * (-15.0f64).exp() * 1e-6;
+        assert!((tiny - expected).abs() / expected < 1e-6);
+        assert_eq!(mixture_prob_between(&comps, 3.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_density_grid() {
+        let comps = parse_components(&[1.0, 0.0, 4.0, 0.4, 0.0, 2.0, 0.2, 2.0, 4.0, 0.4, 0.5, 1.0]);
+        let xs = linspace(0.0, 8.0, 5);
+        assert_eq!(xs, vec![0.0, 2.0, 4.0, 6.0, 8.0]);
+
+        let without_atoms = density_at(&comps, &xs, 0.0);
+        assert!((without_atoms[0] - 0.1).abs() < 1e-12);
+        assert!((without_atoms[1] - 0.1).abs() < 1e-12);
+        assert!((without_atoms[2] - 0.2).abs() < 1e-12);
+        assert!((without_atoms[3] - 0.2 * (-1.0f64).exp()).abs() < 1e-12);
+
+        let with_spikes = density_at(&comps, &xs, 0.5);
+        assert!((with_spikes[1] - (0.1 + 0.2 / 0.5)).abs() < 1e-12);
+
+        // Spikes integrate to the atom mass
+        let fine = linspace(-1.0, 30.0, 31001);
+        let h = 31.0 / 31000.0;
+        let area: f64 = density_at(&comps, &fine, 0.5).iter().sum::<f64>() * h;
+        assert!((area - 1.0).abs() < 2e-3);
+    }
+
+    #[test]
+    fn test_cdf_survival_grids() {
+        let comps = parse_components(&[1.0, 0.0, 4.0, 0.5, 0.0, 2.0, 0.25, 2.0, 4.0, 0.25, 1.0, 1.0]);
+        let xs = linspace(-1.0, 10.0, 12);
+        let cdf = cdf_at(&comps, &xs);
+        let surv = survival_at(&comps, &xs);
+        for i in 0..xs.len() {
+            assert!((cdf[i] + surv[i] - 1.0).abs() < 1e-12);
+            assert!((cdf[i] - mixture_cdf(&comps, xs[i])).abs() < 1e-15);
+            if i > 0 {
+                assert!(cdf[i] >= cdf[i - 1]);
+            }
+        }
+        assert_eq!(cdf[0], 0.0);
+        assert!((cdf[3] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_median_cases() {
+        // Inside a bin
+        let bin = parse_components(&[1.0, 0.0, 4.0, 1.0]);
+        assert!((mixture_quantile(&bin, 0.5) - 2.0).abs() < 1e-9);
+        // On an atom carrying the middle of the mass
+        let atoms = parse_components(&[0.0, 1.0, 0.3, 0.0, 2.0, 0.4, 0.0, 3.0, 0.3]);
+        assert_eq!(mixture_quantile(&atoms, 0.5), 2.0);
+        // Inside an exponential tail: 0.2 + 0.8 (1 - e^{-0.5 (m - 1)}) = 0.5
+        let tail = parse_components(&[0.0, 0.0, 0.2, 2.0, 1.0, 0.8, 0.5, 1.0]);
+        let target = 1.0 + (0.8f64 / 0.5).ln() / 0.5;
+        assert!((mixture_quantile(&tail, 0.5) - target).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_modes() {
+        // Two bins of different height with a gap, plus a right tail whose
+        // peak at its anchor sits inside the lower bin
+        let comps = parse_components(&[
+            1.0, 0.0, 1.0, 0.2, 1.0, 2.0, 4.0, 0.6, 1.0, 5.0, 6.0, 0.3, 2.0, 5.5, 0.2, 0.5, 1.0,
+        ]);
+        // Densities: [0,1) 0.2, [2,4) 0.3, [5,5.5) 0.3, 5.5+ 0.3 + 0.1 e^{-0.5 t}
+        let modes = mixture_modes(&comps);
+        assert_eq!(modes.len(), 3);
+        assert!((modes[0] - 0.5).abs() < 1e-12);
+        assert!((modes[1] - 3.0).abs() < 1e-12);
+        assert!((modes[2] - 5.5).abs() < 1e-12);
+        assert!((mixture_mode(&comps) - 5.5).abs() < 1e-12);
+
+        // Point masses dominate
+        let with_atom = parse_components(&[1.0, 0.0, 1.0, 0.9, 0.0, 3.0, 0.1]);
+        assert_eq!(mixture_mode(&with_atom), 3.0);
+        assert_eq!(mixture_modes(&with_atom), vec![0.5, 3.0]);
+        assert!(mixture_mode(&[]).is_nan());
+    }
+
+    #[test]
+    fn test_skewness_kurtosis() {
+        let uniform = parse_components(&[1.0, 3.0, 7.0, 2.0]);
+        assert!(mixture_skewness(&uniform).abs() < 1e-12);
+        assert!((mixture_excess_kurtosis(&uniform) + 1.2).abs() < 1e-12);
+
+        // Exponential: skewness 2, excess kurtosis 6; mirrored for a left tail
+        let right = parse_components(&[2.0, 10.0, 1.0, 0.25, 1.0]);
+        assert!((mixture_skewness(&right) - 2.0).abs() < 1e-9);
+        assert!((mixture_excess_kurtosis(&right) - 6.0).abs() < 1e-9);
+        let left = parse_components(&[2.0, -3.0, 1.0, 4.0, 0.0]);
+        assert!((mixture_skewness(&left) + 2.0).abs() < 1e-9);
+
+        // Bernoulli(0.25) on {0, 1}
+        let bern = parse_components(&[0.0, 0.0, 0.75, 0.0, 1.0, 0.25]);
+        let skew = (1.0 - 2.0 * 0.25) / (0.25f64 * 0.75).sqrt();
+        assert!((mixture_skewness(&bern) - skew).abs() < 1e-12);
+        assert!(mixture_skewness(&parse_components(&[0.0, 4.0, 1.0])).is_nan());
+    }
+
+    #[test]
+    fn test_moments_closed_form() {
+        // Uniform(1, 3): E[X^k] = (3^{k+1} - 1) / (2 (k + 1))
+        let uniform = parse_components(&[1.0, 1.0, 3.0, 0.5]);
+        for k in 0..7u32 {
+            let exact = (3f64.powi(k as i32 + 1) - 1.0) / (2.0 * (k + 1) as f64);
+            assert!((mixture_raw_moment(&uniform, k) - exact).abs() < 1e-9 * exact);
+        }
+        // Exponential(2) shifted to 1: central moments match Exp(2)
+        // (mu2 = 1/4, mu3 = 2/8, mu4 = 9/16)
+        let tail = parse_components(&[2.0, 1.0, 1.0, 2.0, 1.0]);
+        assert!((mixture_central_moment(&tail, 2) - 0.25).abs() < 1e-12);
+        assert!((mixture_central_moment(&tail, 3) - 0.25).abs() < 1e-12);
+        assert!((mixture_central_moment(&tail, 4) - 0.5625).abs() < 1e-12);
+        // Mixture: raw moments combine linearly by weight
+        let mix = parse_components(&[0.0, 2.0, 0.5, 1.0, 1.0, 3.0, 0.5]);
+        let expected = 0.5 * 8.0 + 0.5 * (81.0 - 1.0) / 8.0;
+        assert!((mixture_raw_moment(&mix, 3) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_cumulants() {
+        // Exp(2) shifted by 1: kappa_1 = 1.5, kappa_n = (n-1)! / 2^n
+        let tail = parse_components(&[2.0, 1.0, 1.0, 2.0, 1.0]);
+        let kappa = mixture_cumulants(&tail, 5);
+        let expected = [1.5, 0.25, 0.25, 0.375, 0.75];
+        for (k, e) in kappa.iter().zip(expected.iter()) {
+            assert!((k - e).abs() < 1e-9);
+        }
+        // Cumulants add under convolution (atom shift of a bin is exact)
+        let a = parse_components(&[0.0, 1.0, 0.5, 0.0, 3.0, 0.5]);
+        let b = parse_components(&[1.0, 0.0, 2.0, 1.0]);
+        let sum = mixture_cumulants(&convolve_components(&a, &b), 4);
+        let (ka, kb) = (mixture_cumulants(&a, 4), mixture_cumulants(&b, 4));
+        for i in 0..4 {
+            assert!((sum[i] - ka[i] - kb[i]).abs() < 1e-9);
+        }
+        assert!(mixture_cumulants(&tail, 0).is_empty());
+    }
+
+    #[test]
+    fn test_entropy() {
+        let ln2 = 2f64.ln();
+        let uniform = parse_components(&[1.0, 0.0, 4.0, 3.0]);
+        assert!((mixture_entropy(&uniform) - 2.0 * ln2).abs() < 1e-12);
+        // Exp(lambda): 1 - ln lambda
+        let tail = parse_components(&[2.0, 5.0, 1.0, 0.5, 0.0]);
+        assert!((mixture_entropy(&tail) - (1.0 + ln2)).abs() < 1e-8);
+        let coin = parse_components(&[0.0, 0.0, 0.5, 0.0, 1.0, 0.25, 0.0, 1.0, 0.25]);
+        assert!((mixture_entropy(&coin) - ln2).abs() < 1e-12);
+        // Half atom, half Uniform(0, 2): 0.5 ln 2 + 0.5 ln 4
+        let mixed = parse_components(&[0.0, 0.0, 0.5, 1.0, 0.0, 2.0, 0.5]);
+        assert!((mixture_entropy(&mixed) - 1.5 * ln2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mgf() {
+        let comps = parse_components(&[0.0, 1.0, 0.5, 1.0, 0.0, 2.0, 0.25, 2.0, 3.0, 0.25, 2.0, 1.0]);
+        let t: f64 = 0.7;
+        let expected = 0.5 * t.exp()
+            + 0.25 * ((2.0 * t).exp() - 1.0) / (2.0 * t)
+            + 0.25 * (3.0 * t).exp() * 2.0 / (2.0 - t);
+        assert!((mixture_mgf(&comps, t) - expected).abs() < 1e-12);
+        assert!((mixture_mgf(&comps, 0.0) - 1.0).abs() < 1e-15);
+        assert!(mixture_mgf(&comps, 2.0).is_nan());
+        // Derivative at 0 recovers the mean
+        let h = 1e-5;
+        let slope = (mixture_mgf(&comps, h) - mixture_mgf(&comps, -h)) / (2.0 * h);
+        assert!((slope - mixture_mean(&comps)).abs() < 1e-6);
+        let left = parse_components(&[2.0, 0.0, 1.0, 1.0, 0.0]);
+        assert!((mixture_mgf(&left, -0.5) - 2.0).abs() < 1e-12);
+        assert!(mixture_mgf(&left, -1.0).is_nan());
+    }
+
+    #[test]
+    fn test_characteristic_function() {
+        // Exp(2) at 1: 2 / (2 - i) = (4 + 2i) / 5
+        let tail = parse_components(&[2.0, 0.0, 1.0, 2.0, 1.0]);
+        let (re, im) = mixture_cf(&tail, 1.0);
+        assert!((re - 0.8).abs() < 1e-12 && (im - 0.4).abs() < 1e-12);
+        let (re, im) = mixture_cf(&parse_components(&[2.0, 0.0, 1.0, 2.0, 0.0]), 1.0);
+        assert!((re - 0.8).abs() < 1e-12 && (im + 0.4).abs() < 1e-12);
+
+        // Uniform(-1, 1) is real: sin t / t
+        let (re, im) = mixture_cf(&parse_components(&[1.0, -1.0, 1.0, 1.0]), 2.0);
+        assert!((re - 2f64.sin() / 2.0).abs() < 1e-12 && im.abs() < 1e-15);
+
+        // CF of a sum is the product of CFs
+        let a = parse_components(&[0.0, 1.0, 0.5, 0.0, 3.0, 0.5]);
+        let b = parse_components(&[1.0, 0.0, 2.0, 1.0, 0.0, 5.0, 0.5]);
+        for &t in &[0.3, 1.1, 4.0] {
+            let (ar, ai) = mixture_cf(&a, t);
+            let (br, bi) = mixture_cf(&b, t);
+            let (sr, si) = mixture_cf(&convolve_components(&a, &b), t);
+            assert!((sr - (ar * br - ai * bi)).abs() < 1e-12);
+            assert!((si - (ar * bi + ai * br)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_support_interval() {
+        let body = parse_components(&[0.0, -2.0, 0.1, 1.0, 0.0, 5.0, 0.9]);
+        assert_eq!(support_interval(&body, 0.01), (-2.0, 5.0));
+
+        // Right tail only: cut where the remaining tail mass is epsilon
+        let right = parse_components(&[1.0, 0.0, 1.0, 0.5, 2.0, 1.0, 0.5, 2.0, 1.0]);
+        let (lo, hi) = support_interval(&right, 0.01);
+        assert_eq!(lo, 0.0);
+        assert!((mixture_prob_gt(&right, hi) - 0.01).abs() < 1e-9);
+
+        // Both sides unbounded: epsilon is split
+        let both = parse_components(&[2.0, 0.0, 0.5, 1.0, 0.0, 2.0, 0.0, 0.5, 1.0, 1.0]);
+        let (lo, hi) = support_interval(&both, 0.02);
+        assert!((mixture_cdf(&both, lo) - 0.01).abs() < 1e-9);
+        assert!((mixture_prob_gt(&both, hi) - 0.01).abs() < 1e-9);
+        assert_eq!(support_interval(&both, 0.0), (f64::NEG_INFINITY, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_conditional_means() {
+        let comps = parse_components(&[0.0, 1.0, 0.4, 1.0, 0.0, 4.0, 0.4, 2.0, 4.0, 0.2, 0.5, 1.0]);
+        // Above 2: half the bin (mean 3, mass 0.2) and the whole tail (mean 6, mass 0.2)
+        assert!((cond_mean_gt(&comps, 2.0) - 4.5).abs() < 1e-12);
+        // Strictly above the atom: only the continuous part
+        let above_one = (0.3 * 2.5 + 0.2 * 6.0) / 0.5;
+        assert!((cond_mean_gt(&comps, 1.0) - above_one).abs() < 1e-12);
+        // Beyond the bin, a tail is memoryless
+        assert!((cond_mean_gt(&comps, 10.0) - 12.0).abs() < 1e-9);
+        // Below 2: the atom and the first half of the bin
+        assert!((cond_mean_lt(&comps, 2.0) - (0.4 * 1.0 + 0.2 * 1.0) / 0.6).abs() < 1e-12);
+        assert!(cond_mean_lt(&comps, 0.0).is_nan());
+        // Consistency: both sides recombine to the mean
+        let t = 3.0;
+        let p_gt = mixture_prob_gt(&comps, t);
+        let recombined = cond_mean_gt(&comps, t) * p_gt + cond_mean_lt(&comps, t) * (1.0 - p_gt);
+        assert!((recombined - mixture_mean(&comps)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mean_excess() {
+        let comps = parse_components(&[0.0, 1.0, 0.4, 1.0, 0.0, 4.0, 0.4, 2.0, 4.0, 0.2, 0.5, 1.0]);
+        for &d in &[-1.0, 0.5, 1.0, 2.5, 4.0] {
+            let expected = cond_mean_gt(&comps, d) - d;
+            assert!((mean_excess(&comps, d) - expected).abs() < 1e-12);
+        }
+        // Far out in the tail the closed form stays exact
+        assert!((mean_excess(&comps, 1e3) - 2.0).abs() < 1e-12);
+        assert!(mean_excess(&parse_components(&[1.0, 0.0, 1.0, 1.0]), 1.0).is_nan());
+
+        // Left tail: X = 2 - Exp(1), brute-force integral of (x - d) f(x) over (d, 2]
+        let left = parse_components(&[2.0, 2.0, 1.0, 1.0, 0.0]);
+        let d = 0.5;
+        let n = 100_000;
+        let h = (2.0 - d) / n as f64;
+        let numeric: f64 = (0..n)
+            .map(|i| {
+                let x = d + (i as f64 + 0.5) * h;
+                (x - d) * (-(2.0 - x)).exp() * h
+            })
+            .sum();
+        assert!((component_stop_loss(&left[0], d) - numeric).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stop_loss() {
+        let comps = parse_components(&[
+            0.0, 1.0, 0.3, 1.0, 0.0, 4.0, 0.3, 2.0, 4.0, 0.2, 0.5, 1.0, 2.0, 0.0, 0.2, 2.0, 0.0,
+        ]);
+        // Put-call parity: E[(X - d)+] - E[(d - X)+] = mean - d, with the put
+        // taken as the stop-loss of -X at -d
+        let d = 1.5;
+        let put = stop_loss(&scale_components(&comps, -1.0), -d);
+        assert!((stop_loss(&comps, d) - put - (mixture_mean(&comps) - d)).abs() < 1e-12);
+        // Premium is decreasing and convex in d
+        let xs = linspace(-3.0, 8.0, 23);
+        let values: Vec<f64> = xs.iter().map(|&x| stop_loss(&comps, x)).collect();
+        for w in values.windows(3) {
+            assert!(w[1] <= w[0] && w[0] - 2.0 * w[1] + w[2] >= -1e-12);
+        }
+        assert!((stop_loss(&comps, 6.0) - 0.2 * 2.0 * (-1.0f64).exp()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_layer_mean() {
+        // Atom at 1 (0.5) and Uniform(0, 4) (0.5); layer 2 xs 1 pays min((X - 1)+, 2)
+        let comps = parse_components(&[0.0, 1.0, 0.5, 1.0, 0.0, 4.0, 0.5]);
+        // Only the uniform part pays: integral over (1, 3) of (x - 1) / 8 plus 2 * P(X > 3) = 0.25 + 0.25
+        assert!((layer_mean(&comps, 1.0, 2.0) - 0.5).abs() < 1e-12);
+        assert!((layer_mean(&comps, 0.0, f64::INFINITY) - stop_loss(&comps, 0.0)).abs() < 1e-15);
+        assert_eq!(layer_mean(&comps, 0.0, 0.0), 0.0);
+        // Layers stack: 0-1 plus 1-3 equals 0-3
+        let stacked = layer_mean(&comps, 0.0, 1.0) + layer_mean(&comps, 1.0, 2.0);
+        assert!((stacked - layer_mean(&comps, 0.0, 3.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_value_at_risk() {
+        // 90% no loss, Uniform(0, 100) severity otherwise, plus an Exp tail past 100
+        let comps = parse_components(&[0.0, 0.0, 0.9, 1.0, 0.0, 100.0, 0.08, 2.0, 100.0, 0.02, 0.1, 1.0]);
+        // Inside the atom's jump
+        assert_eq!(value_at_risk(&comps, 0.5), 0.0);
+        assert_eq!(value_at_risk(&comps, 0.9), 0.0);
+        // Inside the bin: 0.9 + 0.08 * x / 100 = 0.95
+        assert!((value_at_risk(&comps, 0.95) - 62.5).abs() < 1e-9);
+        // Inside the tail: 0.98 + 0.02 (1 - e^{-0.1 (x - 100)}) = 0.995
+        let tail_var = 100.0 + (0.02f64 / 0.005).ln() / 0.1;
+        assert!((value_at_risk(&comps, 0.995) - tail_var).abs() < 1e-7);
+        assert!((mixture_prob_gt(&comps, value_at_risk(&comps, 0.995)) - 0.005).abs() < 1e-12);
+        assert!(value_at_risk(&comps, 1.0).is_nan() && value_at_risk(&comps, 0.0).is_nan());
+        // P&L view: VaR of the negated distribution
+        let pnl = parse_components(&[1.0, -50.0, 50.0, 1.0]);
+        assert!((value_at_risk(&scale_components(&pnl, -1.0), 0.99) - 49.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_conditional_var() {
+        let comps = parse_components(&[0.0, 0.0, 0.9, 1.0, 0.0, 100.0, 0.08, 2.0, 100.0, 0.02, 0.1, 1.0]);
+        // Beyond a continuous quantile CVaR is E[X | X > VaR]
+        for &alpha in &[0.95, 0.99, 0.995] {
+            let var = value_at_risk(&comps, alpha);
+            assert!((conditional_var(&comps, alpha) - cond_mean_gt(&comps, var)).abs() < 1e-6);
+        }
+        // Deep in the exponential tail: VaR + 1 / lambda
+        let cvar = conditional_var(&comps, 0.995);
+        assert!((cvar - value_at_risk(&comps, 0.995) - 10.0).abs() < 1e-6);
+        // Alpha inside the atom's jump: the slice [0.5, 0.9] sits on the atom at 0
+        let expected = (0.08 * 50.0 + 0.02 * 110.0) / 0.5;
+        assert!((conditional_var(&comps, 0.5) - expected).abs() < 1e-9);
+        assert!(conditional_var(&comps, 1.0).is_nan());
+    }
+
+    #[test]
+    fn test_exceedance_curve() {
+        let comps = parse_components(&[
+            0.0, 1.0, 0.2, 1.0, 0.0, 4.0, 0.3, 1.0, 2.0, 2.0, 0.1, 2.0, 4.0, 0.2, 0.5, 1.0, 2.0, 0.0, 0.2, 1.0, 0.0,
+        ]);
+        // Unsorted, with duplicates and points on every breakpoint
+        let ts = [3.0, -1.0, 1.0, 4.0, 2.0, 0.0, 10.0, 2.0, -5.0, 0.5, f64::INFINITY];
+        let curve = exceedance_curve(&comps, &ts);
+        for (t, v) in ts.iter().zip(curve.iter()) {
+            assert!((v - mixture_prob_gt(&comps, *t)).abs() < 1e-14);
+        }
+        assert!(exceedance_curve(&comps, &[f64::NAN])[0].is_nan());
+        assert!(exceedance_curve(&comps, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_sample_n() {
+        let comps = parse_components(&[0.0, -1.0, 0.25, 1.0, 0.0, 2.0, 0.5, 2.0, 2.0, 0.25, 1.0, 1.0]);
+        let table = AliasTable::new(comps.clone());
+        let a = sample_n(&table, 1000, &mut StdRng::seed_from_u64(7));
+        let b = sample_n(&table, 1000, &mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+        assert_ne!(a, sample_n(&table, 1000, &mut StdRng::seed_from_u64(8)));
+
+        let draws = sample_n(&table, 200_000, &mut StdRng::seed_from_u64(1));
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        assert!((mean - mixture_mean(&comps)).abs() < 0.02);
+        let atoms = draws.iter().filter(|&&x| x == -1.0).count() as f64 / draws.len() as f64;
+        assert!((atoms - 0.25).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_rng_backends() {
+        // Reference output of xoshiro256++ from state [1, 2, 3, 4]
+        let mut x = Xoshiro256PlusPlus { s: [1, 2, 3, 4] };
+        assert_eq!(x.next_u64(), 41943041);
+        assert_eq!(x.next_u64(), 58720359);
+        // Reference PCG64 XSL RR output for state 42, stream 54 (the pcg-c
+        // test vector, also checked by rand_pcg)
+        let mut pcg = Pcg64::new(42, 54);
+        let expected: [u64; 6] =
+            [0x86b1da1d72062b68, 0x1304aa46c9853d39, 0xa3670e9e0dd50358, 0xf9090e529a7dae00, 0xc85b9fd837996f2c, 0x606121f8e3919196];
+        for e in expected {
+            assert_eq!(pcg.next_u64(), e);
+        }
+
+        let algorithms = [RngAlgorithm::Xoshiro256PlusPlus, RngAlgorithm::Pcg64, RngAlgorithm::ChaCha20];
+        let comps = parse_components(&[1.0, 0.0, 1.0, 1.0]);
+        let table = AliasTable::new(comps);
+        let mut firsts = Vec::new();
+        for &alg in &algorithms {
+            let a = sample_n(&table, 100, &mut SimRng::new(alg, 42));
+            assert_eq!(a, sample_n(&table, 100, &mut SimRng::new(alg, 42)));
+            firsts.push(a[0]);
+
+            let draws = sample_n(&table, 100_000, &mut SimRng::new(alg, 3));
+            let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+            let var = draws.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / draws.len() as f64;
+            assert!((mean - 0.5).abs() < 0.005);
+            assert!((var - 1.0 / 12.0).abs() < 0.002);
+        }
+        assert!(firsts[0] != firsts[1] && firsts[1] != firsts[2]);
+    }
+
+    #[test]
+    fn test_distribution_handle() {
+        let comps = parse_components(&[0.0, 0.0, 0.5, 1.0, 0.0, 2.0, 0.3, 2.0, 2.0, 0.2, 1.0, 1.0]);
+        let dist = Distribution::from_components(comps.clone());
+        assert_eq!(dist.num_components(), 3);
+        assert_eq!(dist.mean(), mixture_mean(&comps));
+        assert_eq!(dist.variance(), mixture_variance(&comps));
+        assert_eq!(dist.prob_gt(1.0), mixture_prob_gt(&comps, 1.0));
+        assert_eq!(dist.quantile(0.9), mixture_quantile(&comps, 0.9));
+        assert_eq!(dist.cvar(0.95), conditional_var(&comps, 0.95));
+        let cached = sample_n(&dist.alias_table, 50, &mut StdRng::seed_from_u64(5));
+        let fresh = sample_n(&AliasTable::new(comps), 50, &mut StdRng::seed_from_u64(5));
+        assert_eq!(cached, fresh);
+    }
+
+    #[test]
+    fn test_monte_carlo_engine() {
+        let mut engine = coin_engine(0.45, 5.0, 100, 9);
+        let table = AliasTable::new(coin(0.45));
+        // Same stream as run_monte_carlo with the same seed
+        let direct = count_ruins(&table, 5.0, 100, 2000, &mut StdRng::seed_from_u64(21));
+        assert_eq!(engine.run_seeded(2000, 21), direct);
+        assert_near_exact(direct as f64 / 2000.0, coin_ruin(0.45, 5.0, 100), 2000);
+        // Consecutive runs continue one stream
+        engine.reseed(21);
+        let split = engine.run(700) + engine.run(1300);
+        assert_eq!(split, direct);
+        // Other backends are reproducible too
+        engine.set_rng(RngAlgorithm::Pcg64, 4);
+        let first = engine.run(2000);
+        assert_eq!(engine.run_seeded(2000, 4), first);
+        engine.set_steps(0);
+        assert_eq!(engine.run(100), 0);
+    }
+
+    #[test]
+    fn test_monte_carlo_result() {
+        let outcomes = vec![
+            TrialOutcome { ruin_step: Some(3), final_wealth: -0.5, ..Default::default() },
+            TrialOutcome { ruin_step: None, final_wealth: 4.0, ..Default::default() },
+            TrialOutcome { ruin_step: Some(7), final_wealth: -1.0, ..Default::default() },
+            TrialOutcome { ruin_step: None, final_wealth: 2.0, ..Default::default() },
+            TrialOutcome { ruin_step: None, final_wealth: 9.0, ..Default::default() },
+        ];
+        let mut summary = RunSummary::default();
+        summary.add(&outcomes);
+        let r = summary.result(DEFAULT_CONFIDENCE_LEVEL);
+        assert_eq!(r.ruin_count(), 2);
+        assert!((r.ruin_probability() - 0.4).abs() < 1e-15);
+        assert!((r.standard_error() - (0.4f64 * 0.6 / 5.0).sqrt()).abs() < 1e-15);
+        assert!((r.mean_final_wealth() - 5.0).abs() < 1e-15);
+        assert_eq!(r.median_final_wealth(), 4.0);
+        assert_eq!(r.mean_time_to_ruin(), 5.0);
+
+        // Detailed runs see the same trials as the plain ruin count
+        let mut engine = coin_engine(0.45, 5.0, 100, 3);
+        let plain = engine.run_seeded(1000, 3);
+        engine.reseed(3);
+        let detailed = engine.run_detailed(1000);
+        assert_eq!(detailed.ruin_count(), plain);
+        assert_near_exact(detailed.ruin_probability(), coin_ruin(0.45, 5.0, 100), 1000);
+        assert!(detailed.mean_time_to_ruin() >= 5.0);
+    }
+
+    #[test]
+    fn test_p2_quantile() {
+        let mut m = P2Quantile::new(0.5);
+        assert!(m.value().is_nan());
+        [3.0, 1.0, 4.0, 2.0].iter().for_each(|&x| m.add(x));
+        assert_eq!(m.value(), 2.5);
+
+        // A shuffled uniform stream: the streaming median tracks the exact one
+        let mut rng = SimRng::std(11);
+        let mut xs: Vec<f64> = (0..20_000).map(|_| rng.gen::<f64>()).collect();
+        let (mut mid, mut hi) = (P2Quantile::new(0.5), P2Quantile::new(0.9));
+        xs.iter().for_each(|&x| {
+            mid.add(x);
+            hi.add(x);
+        });
+        xs.sort_by(|a, b| a.total_cmp(b));
+        assert!((mid.value() - xs[10_000]).abs() < 0.01);
+        assert!((hi.value() - xs[18_000]).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_wilson_interval() {
+        // Reference: 8 ruins in 100 trials at 95% gives [0.0411, 0.1500]
+        let (lo, hi) = wilson_interval(8, 100, 0.95);
+        assert!((lo - 0.0411).abs() < 1e-4 && (hi - 0.1500).abs() < 1e-4);
+        // No ruins still gives a non-trivial upper bound
+        let (lo, hi) = wilson_interval(0, 1000, 0.95);
+        assert_eq!(lo, 0.0);
+        assert!(hi > 0.003 && hi < 0.004);
+        // Higher confidence widens the interval
+        let (lo99, hi99) = wilson_interval(8, 100, 0.99);
+        assert!(lo99 < 0.0411 && hi99 > 0.1500);
+        assert!(wilson_interval(1, 0, 0.95).0.is_nan());
+        assert!(wilson_interval(1, 10, 1.0).1.is_nan());
+    }
+
+    #[test]
+    fn test_ruin_time_histogram() {
+        let times = [1, 2, 5, 6, 10, 10, 3];
+        assert_eq!(ruin_time_histogram(&times, 10, 5), vec![2.0, 1.0, 2.0, 0.0, 2.0]);
+        assert_eq!(ruin_time_histogram(&times, 10, 3), vec![3.0, 2.0, 2.0]);
+        let per_step = ruin_time_histogram(&times, 10, 0);
+        assert_eq!(per_step.len(), 10);
+        assert_eq!(per_step[9], 2.0);
+        assert!(ruin_time_histogram(&times, 0, 4).is_empty());
+
+        // Ruin steps from the engine match the detailed summary
+        let mut engine = coin_engine(0.45, 3.0, 50, 8);
+        let steps: Vec<u32> = engine.simulate(500).iter().filter_map(|o| o.ruin_step).collect();
+        engine.reseed(8);
+        let summary = engine.run_detailed(500);
+        assert_eq!(steps.len() as u32, summary.ruin_count());
+        assert!(steps.iter().all(|&t| (3..=50).contains(&t)));
+        let hist = ruin_time_histogram(&steps, 50, 10);
+        assert_eq!(hist.iter().sum::<f64>() as usize, steps.len());
+    }
+
+    #[test]
+    fn test_survival_curve() {
+        let curve = survival_curve(&[2, 2, 4], 5, 10);
+        assert_eq!(curve, vec![1.0, 0.8, 0.8, 0.7, 0.7]);
+        assert!(survival_curve(&[], 3, 0).iter().all(|v| v.is_nan()));
+
+        // The last point matches the simulated ruin probability
+        let mut engine = coin_engine(0.45, 3.0, 60, 2);
+        let steps: Vec<u32> = engine.simulate(800).iter().filter_map(|o| o.ruin_step).collect();
+        let curve = survival_curve(&steps, 60, 800);
+        assert_eq!(curve.len(), 60);
+        assert_near_exact(1.0 - curve[59], coin_ruin(0.45, 3.0, 60), 800);
+        assert!(curve.windows(2).all(|w| w[1] <= w[0]));
+        assert_eq!(curve[1], 1.0);
+        engine.reseed(2);
+        assert!((curve[59] - (1.0 - engine.run_detailed(800).ruin_probability())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let values = [0.0, 0.5, 1.0, 1.5, 2.0, 2.0, 3.5, -1.0];
+        let h = Histogram::build(&values, vec![0.0, 1.0, 2.0], 0);
+        // [0, 1): 0, 0.5; [1, 2]: 1, 1.5, 2, 2; 3.5 and -1 fall outside
+        assert_eq!(h.counts, vec![2.0, 4.0]);
+
+        let auto = Histogram::build(&values, vec![], 3);
+        assert_eq!(auto.edges, linspace(-1.0, 3.5, 4));
+        assert_eq!(auto.counts.iter().sum::<f64>(), values.len() as f64);
+        // Sturges: ceil(log2 8) + 1 = 4 bins
+        assert_eq!(Histogram::build(&values, vec![], 0).counts.len(), 4);
+        let constant = Histogram::build(&[2.0, 2.0], vec![], 2);
+        assert_eq!(constant.counts, vec![0.0, 2.0]);
+        assert!(Histogram::build(&[], vec![], 5).counts.is_empty());
+
+        assert!(check_edges(&[]).is_ok() && check_edges(&[0.0, 1.0, 2.0]).is_ok());
+        assert!(check_edges(&[1.0]).is_err());
+        assert!(check_edges(&[0.0, 2.0, 1.0]).is_err());
+        assert!(check_edges(&[0.0, 1.0, 1.0]).is_err());
+        assert!(check_edges(&[0.0, f64::NAN]).is_err());
+        assert!(check_edges(&[f64::NEG_INFINITY, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_wealth_percentiles() {
+        assert_eq!(sorted_percentile(&[1.0, 2.0, 3.0, 4.0], 0.5), 2.5);
+        assert_eq!(sorted_percentile(&[1.0, 2.0, 3.0, 4.0], 1.0), 4.0);
+        assert!(sorted_percentile(&[], 0.5).is_nan());
+
+        // Symmetric +-1 walk from 100: median stays near 100, bands widen like sqrt(t)
+        let table = AliasTable::new(coin(0.5));
+        let pcts = [5.0, 50.0, 95.0];
+        let m = wealth_percentiles(&table, 100.0, 200, 2000, &pcts, &StepRules::default(), &mut StdRng::seed_from_u64(6));
+        assert_eq!(m.len(), 200 * 3);
+        for row in m.chunks(3) {
+            assert!(row[0] <= row[1] && row[1] <= row[2]);
+        }
+        let last = &m[199 * 3..];
+        assert!((last[1] - 100.0).abs() <= 2.0);
+        // 1.645 * sqrt(200) = 23.3
+        assert!((last[2] - last[1] - 23.3).abs() < 3.0);
+        // Ruined trials are absorbed at zero wealth
+        let m = wealth_percentiles(&table, 1.0, 200, 1000, &[5.0], &StepRules::default(), &mut StdRng::seed_from_u64(6));
+        assert_eq!(m[199], 0.0);
+    }
+
+    #[test]
+    fn test_sample_paths() {
+        let table = AliasTable::new(coin(0.5));
+        let paths = sample_paths(&table, 2.0, 30, 50, &StepRules::default(), &mut StdRng::seed_from_u64(12));
+        assert_eq!(paths.stride(), 31);
+        assert_eq!(paths.num_paths(), 50);
+        let mut ruined = 0;
+        for path in paths.values.chunks(31) {
+            assert_eq!(path[0], 2.0);
+            match path.iter().position(|w| *w <= 0.0) {
+                Some(i) => {
+                    ruined += 1;
+                    assert!(path[i + 1..].iter().all(|w| w.is_nan()));
+                    assert!(path[..i].iter().all(|w| *w > 0.0));
+                }
+                None => assert!(path.iter().all(|w| w.is_finite())),
+            }
+        }
+        // Paths replay the same trials as the ruin count
+        let count = count_ruins(&table, 2.0, 30, 50, &mut StdRng::seed_from_u64(12));
+        assert_eq!(ruined, count);
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        // A rising path has no drawdown; a falling one loses everything down to ruin
+        let up = AliasTable::new(parse_components(&[0.0, 2.0, 1.0]));
+        assert_eq!(simulate_trial(&up, 1.0, 10, &StepRules::default(), &mut StdRng::seed_from_u64(0)).max_drawdown, 0.0);
+        let down = AliasTable::new(parse_components(&[0.0, -1.5, 1.0]));
+        // 4 -> 2.5 -> 1 -> -0.5
+        assert_eq!(simulate_trial(&down, 4.0, 10, &StepRules::default(), &mut StdRng::seed_from_u64(0)).max_drawdown, 4.5);
+
+        let mut engine = coin_engine(0.5, 50.0, 100, 1);
+        let outcomes = engine.simulate(1000);
+        let summary = drawdown_summary(&outcomes, &[0.0, 50.0, 100.0]);
+        assert!(summary[1] >= 0.0 && summary[1] <= summary[2] && summary[2] <= summary[3]);
+        assert!(summary[0] > 0.0 && summary[0] <= summary[3]);
+    }
+
+    #[test]
+    fn test_target_barrier() {
+        // Fair +-1 walk from 3 with target 10: P(success) = 3 / 10 (gambler's ruin)
+        let mut engine = coin_engine(0.5, 3.0, 10_000, 4);
+        engine.set_target(10.0);
+        let r = engine.run_detailed(20_000);
+        assert_eq!(r.ruin_count() + r.success_count() + r.timeout_count(), 20_000);
+        assert_eq!(r.timeout_count(), 0);
+        let p_success = r.success_count() as f64 / 20_000.0;
+        assert_near_exact(p_success, 1.0 - gamblers_ruin_probability(&coin(0.5), 3.0, 10.0), 20_000);
+
+        // Short horizon leaves timeouts; no barrier means no successes
+        engine.set_steps(5);
+        let r = engine.run_detailed(1000);
+        assert!(r.timeout_count() > 0);
+        engine.set_target(f64::NAN);
+        assert_eq!(engine.run_detailed(1000).success_count(), 0);
+
+        // Stopped paths end with NaN padding at a success too
+        let rules = StepRules { target: 5.0, ..Default::default() };
+        let up = AliasTable::new(parse_components(&[0.0, 1.0, 1.0]));
+        let paths = sample_paths(&up, 3.0, 6, 1, &rules, &mut StdRng::seed_from_u64(0));
+        assert_eq!(&paths.values[..3], &[3.0, 4.0, 5.0]);
+        assert!(paths.values[3..].iter().all(|v| v.is_nan()));
+    }
+
+    #[test]
+    fn test_stake_rules() {
+        // A sure +1 per unit stake makes every step's gain equal to the stake
+        let up = AliasTable::new(parse_components(&[0.0, 1.0, 1.0]));
+        let run = |stake: StakeRule| {
+            let rules = StepRules { stake, ..Default::default() };
+            sample_paths(&up, 10.0, 4, 1, &rules, &mut StdRng::seed_from_u64(0)).values
+        };
+        assert_eq!(run(StakeRule::Constant), vec![10.0, 11.0, 12.0, 13.0, 14.0]);
+        let fraction = run(StakeRule::Fraction { fraction: 0.5, cap: f64::INFINITY });
+        assert_eq!(fraction, vec![10.0, 15.0, 22.5, 33.75, 50.625]);
+        let capped = run(StakeRule::Fraction { fraction: 0.5, cap: 6.0 });
+        assert_eq!(capped, vec![10.0, 15.0, 21.0, 27.0, 33.0]);
+        let table = StakeRule::Table { thresholds: vec![0.0, 12.0, 20.0], stakes: vec![1.0, 4.0, 10.0] };
+        assert_eq!(run(table), vec![10.0, 11.0, 12.0, 16.0, 20.0]);
+        let below = StakeRule::Table { thresholds: vec![50.0], stakes: vec![2.0] };
+        assert_eq!(below.stake(1.0), 2.0);
+    }
+
+    #[test]
+    fn test_multiplicative_dynamics() {
+        let down = AliasTable::new(parse_components(&[0.0, -1.0, 1.0]));
+        let mut engine = coin_engine(0.5, 10.0, 4, 3);
+        engine.set_multiplicative(0.5, 0.25);
+        // 10 -> 5 -> 2.5: a 75% drawdown ruins at step 2
+        let o = simulate_trial(&down, 10.0, 10, &engine.rules, &mut StdRng::seed_from_u64(0));
+        assert_eq!(o.ruin_step, Some(2));
+        assert_eq!(o.final_wealth, 2.5);
+        // Staking everything on a loss wipes wealth out even without a floor
+        let all_in = StepRules { stake: StakeRule::Fraction { fraction: 1.0, cap: f64::INFINITY }, ..Default::default() };
+        assert_eq!(simulate_trial(&down, 10.0, 10, &all_in, &mut StdRng::seed_from_u64(0)).ruin_step, Some(1));
+
+        // Two losses in a row from any peak ruin; longer horizons ruin more
+        let short = engine.run_detailed(2000).ruin_probability();
+        engine.set_steps(20);
+        let long = engine.run_detailed(2000).ruin_probability();
+        assert!(short > 0.0 && long > short);
+        // Without a floor, multiplicative wealth never reaches zero at f < 1
+        engine.set_multiplicative(0.5, 0.0);
+        assert_eq!(engine.run(500), 0);
+    }
+
+    #[test]
+    fn test_step_schedule() {
+        let up = parse_components(&[0.0, 1.0, 1.0]);
+        let down = parse_components(&[0.0, -2.0, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(up.clone(), 10.0, 7, 0);
+        // Blocks of 2: up, up, down, down, then up for the rest
+        engine.set_schedule(vec![up.clone(), down.clone(), up.clone()], 2).unwrap();
+        let paths = engine.sample_paths(1);
+        assert_eq!(paths.values, vec![10.0, 11.0, 12.0, 10.0, 8.0, 9.0, 10.0, 11.0]);
+        // Per-step schedule, last entry repeating
+        engine.set_schedule(vec![up.clone(), down.clone()], 1).unwrap();
+        let paths = engine.sample_paths(1);
+        assert_eq!(paths.values, vec![10.0, 11.0, 9.0, 7.0, 5.0, 3.0, 1.0, -1.0]);
+        assert_eq!(engine.run_detailed(10).mean_time_to_ruin(), 7.0);
+        assert!(engine.set_schedule(vec![], 1).is_err());
+        assert!(engine.set_schedule(vec![up], 0).is_err());
+    }
+
+    #[test]
+    fn test_regime_switching() {
+        let up = parse_components(&[0.0, 1.0, 1.0]);
+        let down = parse_components(&[0.0, -1.0, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(up.clone(), 5.0, 4, 0);
+        // Deterministic alternation between the two regimes
+        engine.set_regime_tables(vec![up.clone(), down.clone()], &[0.0, 1.0, 2.0, 0.0], 1).unwrap();
+        assert_eq!(engine.sample_paths(1).values, vec![5.0, 4.0, 5.0, 4.0, 5.0]);
+        // Sticky regimes: a trial that starts down mostly stays down, so ruin
+        // is far more likely than with i.i.d. draws from the 50/50 mixture
+        engine.set_steps(50);
+        engine.set_regime_tables(vec![up.clone(), down.clone()], &[0.95, 0.05, 0.05, 0.95], 1).unwrap();
+        let sticky = engine.run(2000) as f64 / 2000.0;
+        engine.increments = Increments::Iid(AliasTable::new(coin(0.5)));
+        let iid = engine.run(2000) as f64 / 2000.0;
+        assert!(sticky > iid + 0.2, "sticky {} iid {}", sticky, iid);
+        assert!(engine.set_regime_tables(vec![up.clone(), down.clone()], &[1.0, 0.0, 0.0], 0).is_err());
+        assert!(engine.set_regime_tables(vec![up.clone(), down.clone()], &[1.0, 0.0, 0.0, 0.0], 0).is_err());
+        assert!(engine.set_regime_tables(vec![up, down], &[1.0, 0.0, 0.0, 1.0], 2).is_err());
+    }
+
+    #[test]
+    fn test_autocorrelated_increments() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.959964) - 0.975).abs() < 1e-6);
+        assert!((normal_cdf(normal_quantile(0.01)) - 0.01).abs() < 1e-8);
+
+        let mut engine = coin_engine(0.5, 1000.0, 2000, 3);
+        engine.set_rho(0.9).unwrap();
+        let paths = engine.sample_paths(1);
+        let inc: Vec<f64> = paths.values.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(inc.iter().all(|&x| x == 1.0 || x == -1.0));
+        let ups = inc.iter().filter(|&&x| x == 1.0).count() as f64 / inc.len() as f64;
+        assert!((ups - 0.5).abs() < 0.1, "ups {}", ups);
+        let lag1 = inc.windows(2).map(|w| w[0] * w[1]).sum::<f64>() / (inc.len() - 1) as f64;
+        assert!(lag1 > 0.5, "lag-1 correlation {}", lag1);
+
+        // Streaks make ruin from a small bankroll more likely
+        engine.set_init_wealth(5.0);
+        engine.set_steps(50);
+        let correlated = engine.run(2000);
+        engine.set_rho(0.0).unwrap();
+        let iid = engine.run(2000);
+        assert!(correlated > iid + 200, "correlated {} iid {}", correlated, iid);
+        assert!(engine.set_rho(1.5).is_err());
+    }
+
+    #[test]
+    fn test_interest_rate() {
+        // Surplus of 100 earning 10% per step against a fixed claim of 12:
+        // 100 -> 98 -> 95.8 -> 93.38
+        let claim = parse_components(&[0.0, -12.0, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(claim, 100.0, 3, 0);
+        engine.set_interest_rate(0.1);
+        let paths = engine.sample_paths(1);
+        let expected = [100.0, 98.0, 95.8, 93.38];
+        for (v, e) in paths.values.iter().zip(expected) {
+            assert!((v - e).abs() < 1e-9, "{} vs {}", v, e);
+        }
+        // Interest above claims / surplus keeps the surplus growing forever
+        engine.set_steps(200);
+        engine.set_interest_rate(0.125);
+        assert_eq!(engine.run(10), 0);
+        engine.set_interest_rate(0.0);
+        assert_eq!(engine.run(10), 10);
+    }
+
+    #[test]
+    fn test_premium_income() {
+        // Claims of 3 every step against a premium of 2: u = 5 lasts 5 steps
+        let claim = parse_components(&[0.0, -3.0, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(claim, 5.0, 10, 0);
+        engine.set_premium(2.0);
+        assert_eq!(engine.sample_paths(1).values[..6], [5.0, 4.0, 3.0, 2.0, 1.0, 0.0]);
+        assert!(engine.simulate(5).iter().all(|o| o.ruin_step == Some(5)));
+        // Premium applies on top of interest
+        engine.set_interest_rate(0.5);
+        assert_eq!(engine.sample_paths(1).values[..3], [5.0, 6.5, 8.75]);
+    }
+
+    #[test]
+    fn test_frequency_models() {
+        let poisson = Frequency::parse(&[0.0, 3.0]).unwrap().pmf().unwrap();
+        assert!((poisson[2] - 4.5 * (-3.0f64).exp()).abs() < 1e-15);
+        assert!((poisson.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        // Large means must not underflow p_0
+        let large = Frequency::parse(&[0.0, 2000.0]).unwrap().pmf().unwrap();
+        assert!((large.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        let nb = Frequency::parse(&[1.0, 2.0, 0.5]).unwrap().pmf().unwrap();
+        assert!((nb[1] - 0.25).abs() < 1e-15 && (nb[2] - 0.1875).abs() < 1e-15);
+        assert_eq!(Frequency::parse(&[2.0, 2.0, 0.5]).unwrap().pmf().unwrap(), vec![0.25, 0.5, 0.25]);
+        assert!(Frequency::parse(&[0.0, -1.0]).is_err());
+        assert!(Frequency::parse(&[1.0, 2.0, 0.0]).is_err());
+        assert!(Frequency::parse(&[3.0, 1.0]).is_err());
+        // A count table that cannot hold the mass is an error, not a silent cut
+        assert!(Frequency::parse(&[1.0, 1.0, 1e-7]).unwrap().pmf().is_err());
+        assert!(Frequency::parse(&[2.0, 2e6, 1.0]).unwrap().pmf().is_err());
+
+        // Unit claims with Poisson(2) counts: mean aggregate per step is -2
+        let claim = parse_components(&[0.0, -1.0, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(claim, 1e6, 1, 5);
+        engine.set_frequency_params(&[0.0, 2.0]).unwrap();
+        let mean = engine.run_detailed(20000).mean_final_wealth() - 1e6;
+        assert!((mean + 2.0).abs() < 0.05, "mean {}", mean);
+        engine.set_frequency_params(&[]).unwrap();
+        assert_eq!(engine.run_detailed(10).mean_final_wealth(), 1e6 - 1.0);
+    }
+
+    #[test]
+    fn test_panjer_recursion() {
+        // Unit severity: the aggregate is the claim-count distribution itself
+        let unit = parse_components(&[0.0, 1.0, 1.0]);
+        let f = discretize_severity(&unit, 1.0).unwrap();
+        assert_eq!(f, vec![0.0, 1.0]);
+        let poisson = Frequency::Poisson { lambda: 2.0 };
+        let g = panjer_recursion(poisson, &f).unwrap();
+        for (k, gk) in g.iter().enumerate().take(10) {
+            assert!((gk - poisson.pmf().unwrap()[k]).abs() < 1e-14);
+        }
+        // Binomial counts with bounded severity stop at the maximum aggregate
+        let g = panjer_recursion(Frequency::Binomial { n: 2, p: 0.5 }, &f).unwrap();
+        assert_eq!(g, vec![0.25, 0.5, 0.25]);
+        // p = 1 means exactly n claims: the n-fold convolution of f
+        let sure = Frequency::parse(&[2.0, 3.0, 1.0]).unwrap();
+        assert_eq!(panjer_recursion(sure, &f).unwrap(), vec![0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(panjer_recursion(sure, &[0.25, 0.75]).unwrap(), vec![1.0 / 64.0, 9.0 / 64.0, 27.0 / 64.0, 27.0 / 64.0]);
+        // Gaps in the lattice support do not stop the recursion
+        let g = panjer_recursion(poisson, &[0.0, 0.0, 1.0]).unwrap();
+        assert_eq!(g[3], 0.0);
+        assert!((g[4] - poisson.pmf().unwrap()[2]).abs() < 1e-15);
+
+        // Compound negative binomial: E[S] = E[N] E[X] and Var via the usual
+        // formula Var = E[N] Var[X] + Var[N] E[X]^2
+        let severity = parse_components(&[0.0, 1.0, 0.5, 1.0, 1.5, 2.5, 0.5]);
+        let f = discretize_severity(&severity, 0.5).unwrap();
+        assert!((f.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        let g = panjer_recursion(Frequency::NegativeBinomial { r: 2.0, p: 0.5 }, &f).unwrap();
+        let atoms = lattice_atoms(&g, 0.5);
+        let f_atoms = lattice_atoms(&f, 0.5);
+        let (ex, vx) = (mixture_mean(&f_atoms), mixture_variance(&f_atoms));
+        assert!((mixture_mean(&atoms) - 2.0 * ex).abs() < 1e-9);
+        assert!((mixture_variance(&atoms) - (2.0 * vx + 4.0 * ex * ex)).abs() < 1e-8);
+
+        assert!(discretize_severity(&parse_components(&[0.0, -1.0, 1.0]), 1.0).is_err());
+        assert!(discretize_severity(&unit, 0.0).is_err());
+        assert!(panjer_recursion(Frequency::Poisson { lambda: 1e4 }, &f).is_err());
+    }
+
+    #[test]
+    fn test_fft_aggregate() {
+        let severity = parse_components(&[0.0, 1.0, 0.5, 1.0, 1.5, 2.5, 0.5]);
+        let f = discretize_severity(&severity, 0.5).unwrap();
+        for frequency in [
+            Frequency::Poisson { lambda: 3.0 },
+            Frequency::NegativeBinomial { r: 2.0, p: 0.5 },
+            Frequency::Binomial { n: 4, p: 0.3 },
+        ] {
+            let panjer = panjer_recursion(frequency, &f).unwrap();
+            let fft = fft_aggregate(frequency, &f, 0).unwrap();
+            for (k, p) in panjer.iter().enumerate() {
+                assert!((p - fft[k]).abs() < 1e-12, "{:?} k={} {} vs {}", frequency, k, p, fft[k]);
+            }
+        }
+        // Large claim counts where Panjer underflows
+        let frequency = Frequency::Poisson { lambda: 5000.0 };
+        let g = fft_aggregate(frequency, &f, 0).unwrap();
+        let atoms = lattice_atoms(&g, 0.5);
+        let ex = mixture_mean(&lattice_atoms(&f, 0.5));
+        assert!((total_mass(&atoms) - 1.0).abs() < 1e-9);
+        assert!((mixture_mean(&atoms) / (5000.0 * ex) - 1.0).abs() < 1e-9);
+        assert!(fft_aggregate(frequency, &f, LATTICE_MAX_POINTS + 1).is_err());
+        // A lattice shorter than the severity would fold its tail onto one point
+        assert!(f.len() > 4 && fft_aggregate(frequency, &f, 4).is_err());
+    }
+
+    #[test]
+    fn test_adjustment_coefficient() {
+        // Increments {0, -2} with c = 1.5: 0.5 y^-3 + 0.5 y = 1 for
+        // y = e^{R/2}, so y is the tribonacci constant
+        let two_point = parse_components(&[0.0, 0.0, 0.5, 0.0, -2.0, 0.5]);
+        let r = lundberg_coefficient(&two_point, 0.5);
+        assert!((r - 2.0 * 1.839286755214161f64.ln()).abs() < 1e-12, "R = {}", r);
+
+        // Exponential losses: the root satisfies the closed-form MGF equation
+        let exp_claims = parse_components(&[2.0, 0.0, 1.0, 0.5, 0.0]);
+        let r = lundberg_coefficient(&exp_claims, 0.25);
+        assert!(r > 0.0 && r < 0.5);
+        assert!((0.5 / (0.5 - r) * (-r * 2.5).exp() - 1.0).abs() < 1e-10);
+
+        // Monte Carlo ruin from surplus 5 on the same data stays below the bound
+        let mut engine = MonteCarloEngine::from_components(two_point.clone(), 5.0, 500, 1);
+        engine.set_premium(1.5);
+        let ruin = engine.run(4000) as f64 / 4000.0;
+        let bound = (-lundberg_coefficient(&two_point, 0.5) * 5.0).exp();
+        assert!(ruin <= bound, "ruin {} bound {}", ruin, bound);
+
+        assert_eq!(lundberg_coefficient(&parse_components(&[0.0, -1.0, 1.0]), 0.1), f64::INFINITY);
+        assert!(lundberg_coefficient(&two_point, 0.0).is_nan());
+        assert!(lundberg_coefficient(&scale_components(&two_point, -1.0), 0.5).is_nan());
+    }
+
+    #[test]
+    fn test_gamblers_ruin() {
+        // Fair game from 3 with target 10: 1 - 3/10
+        assert!((gamblers_ruin_probability(&coin(0.5), 3.0, 10.0) - 0.7).abs() < 1e-14);
+        // Favourable game, no target: (q/p)^u
+        assert!((gamblers_ruin_probability(&coin(0.6), 3.0, f64::INFINITY) - (2.0f64 / 3.0).powi(3)).abs() < 1e-14);
+        assert_eq!(gamblers_ruin_probability(&coin(0.4), 3.0, f64::INFINITY), 1.0);
+        // Unfavourable game with target: textbook formula in r = q/p
+        let r: f64 = 1.5;
+        let expected = (r.powi(10) - r.powi(3)) / (r.powi(10) - 1.0);
+        assert!((gamblers_ruin_probability(&coin(0.4), 3.0, 10.0) - expected).abs() < 1e-14);
+        // From 5 in steps of 2: ruin after 3 net downs, success after 8 net ups
+        let scaled = scale_components(&coin(0.5), 2.0);
+        assert!((gamblers_ruin_probability(&scaled, 5.0, 20.0) - 8.0 / 11.0).abs() < 1e-14);
+
+        // Agrees with the simulator on a long horizon
+        let mut engine = coin_engine(0.5, 3.0, 100_000, 9);
+        engine.set_target(10.0);
+        assert_near_exact(engine.run(4000) as f64 / 4000.0, 0.7, 4000);
+
+        // Unequal steps +2 / -1: down-steps never overshoot, so with no target
+        // psi(u) = rho^u where rho = q + p rho^3; for p = 1/2, rho = (sqrt 5 - 1) / 2
+        let skewed = parse_components(&[0.0, 2.0, 0.5, 0.0, -1.0, 0.5]);
+        let rho = (5f64.sqrt() - 1.0) / 2.0;
+        assert!((gamblers_ruin_probability(&skewed, 3.0, f64::INFINITY) - rho.powi(3)).abs() < 1e-12);
+        // With a target it matches the long-horizon lattice DP
+        let psi = gamblers_ruin_probability(&skewed, 3.0, 10.0);
+        assert!((psi - lattice_ruin_probability(&skewed, 3.0, 2000, 10.0)).abs() < 1e-12);
+        // and the solver agrees with the closed form for equal steps
+        assert!((lattice_walk_ruin(1.0, 1.0, 0.4, 1.0, 3.0, 10.0) - expected).abs() < 1e-12);
+        assert!((lattice_walk_ruin(1.0, 1.0, 0.6, 1.0, 3.0, f64::INFINITY) - (2.0f64 / 3.0).powi(3)).abs() < 1e-12);
+        // Overshooting down-steps: -3 / +1 with upward drift
+        let big_down = parse_components(&[0.0, 1.0, 0.9, 0.0, -3.0, 0.1]);
+        let psi = gamblers_ruin_probability(&big_down, 4.5, 12.0);
+        assert!((psi - lattice_ruin_probability(&big_down, 4.5, 4000, 12.0)).abs() < 1e-12);
+
+        assert!(gamblers_ruin_probability(&parse_components(&[1.0, -1.0, 1.0, 1.0]), 3.0, 10.0).is_nan());
+    }
+
+    #[test]
+    fn test_lattice_ruin() {
+        let coin = coin(0.5);
+        assert_eq!(lattice_span(&coin), Some(1.0));
+        assert_eq!(lattice_span(&parse_components(&[0.0, 1.5, 0.5, 0.0, -0.5, 0.5])), Some(0.5));
+        assert_eq!(lattice_span(&parse_components(&[1.0, -1.0, 1.0, 1.0])), None);
+        // Ruin paths from 1 within 3 steps: D, then UDD
+        assert_eq!(lattice_ruin_probability(&coin, 1.0, 1, f64::INFINITY), 0.5);
+        assert_eq!(lattice_ruin_probability(&coin, 1.0, 3, f64::INFINITY), 0.625);
+        // Long horizons converge to the closed form
+        let dp = lattice_ruin_probability(&coin, 3.0, 5000, 10.0);
+        assert!((dp - 0.7).abs() < 1e-9, "dp {}", dp);
+
+        // Skewed lattice walk against the simulator
+        let skewed = parse_components(&[0.0, 2.0, 0.4, 0.0, -1.0, 0.6]);
+        let exact = lattice_ruin_probability(&skewed, 4.0, 30, f64::INFINITY);
+        let mut engine = MonteCarloEngine::from_components(skewed.clone(), 4.0, 30, 2);
+        assert_near_exact(engine.run(20000) as f64 / 20000.0, exact, 20000);
+        // Wealth between lattice points: 3.5 behaves like 4 for down-steps of 1
+        assert_eq!(
+            lattice_ruin_probability(&skewed, 3.5, 30, f64::INFINITY),
+            lattice_ruin_probability(&skewed, 4.0, 30, f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_importance_sampling() {
+        // Coin with p = 0.6: the optimal tilt swaps the probabilities and
+        // every ruined path has weight (2/3)^20
+        let coin = coin(0.6);
+        assert!((optimal_tilt(&coin).unwrap() - 1.5f64.ln()).abs() < 1e-12);
+        let exact = lattice_ruin_probability(&coin, 20.0, 400, f64::INFINITY);
+        let mut engine = coin_engine(0.6, 20.0, 400, 4);
+        engine.set_tilt(f64::NAN).unwrap();
+        let est = engine.run_importance(2000);
+        assert!(exact < 1e-3);
+        assert!((est.value() / exact - 1.0).abs() < 2e-3, "{} vs {}", est.value(), exact);
+        assert!(est.relative_error() < 0.05);
+
+        // Tilted bins sample inside their bounds with the tilted mean
+        let mut rng = StdRng::seed_from_u64(0);
+        let xs: Vec<f64> = (0..20000).map(|_| tilted_bin_sample(0.0, 1.0, 3.0, rng.gen())).collect();
+        assert!(xs.iter().all(|&x| (0.0..=1.0).contains(&x)));
+        let tilted_mean = 1.0 / (1.0 - (-3.0f64).exp()) - 1.0 / 3.0;
+        assert!((xs.iter().sum::<f64>() / 20000.0 - tilted_mean).abs() < 0.01);
+
+        // Continuous increments: unbiased against plain Monte Carlo
+        let bin = parse_components(&[1.0, -1.0, 1.5, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(bin, 2.0, 40, 6);
+        let plain = engine.run(20000) as f64 / 20000.0;
+        engine.set_tilt(f64::NAN).unwrap();
+        let est = engine.run_importance(5000);
+        assert!((est.value() - plain).abs() < 0.01, "{} vs {}", est.value(), plain);
+        engine.set_tilt(0.0).unwrap();
+        assert!(matches!(engine.increments, Increments::Iid(_)));
+        assert!(engine.set_tilt(f64::NAN).is_ok());
+        assert!(optimal_tilt(&parse_components(&[0.0, 1.0, 1.0])).is_err());
+    }
+
+    #[test]
+    fn test_antithetic_pairs() {
+        // One step of a symmetric bin: each pair mirrors around the start
+        let bin = parse_components(&[1.0, -1.0, 1.0, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(bin.clone(), 10.0, 1, 8);
+        engine.set_antithetic(true);
+        let outcomes = engine.simulate(5);
+        assert_eq!(outcomes.len(), 6);
+        for pair in outcomes.chunks(2) {
+            assert!((pair[0].final_wealth + pair[1].final_wealth - 20.0).abs() < 1e-9);
+        }
+
+        // Pairing cuts the variance of a smooth ruin estimate
+        let drift = parse_components(&[1.0, -1.0, 1.4, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(drift, 2.0, 20, 8);
+        let plain = engine.run_importance(20000);
+        engine.set_antithetic(true);
+        let paired = engine.run_importance(20000);
+        assert_eq!(paired.num_trials(), 20000);
+        assert!((paired.value() - plain.value()).abs() < 4.0 * plain.standard_error());
+        assert!(paired.variance() < plain.variance(), "{} vs {}", paired.variance(), plain.variance());
+
+        // Halton overrides pairing: odd counts run unpaired
+        engine.set_tilt(f64::NAN).unwrap();
+        engine.set_sampling_scheme(SamplingScheme::Halton);
+        let quasi = engine.run_importance(101);
+        assert_eq!(quasi.num_trials(), 101);
+        assert!(quasi.value().is_finite());
+    }
+
+    #[test]
+    fn test_control_variate() {
+        // Far from ruin, final wealth is exactly u + sum of draws, so the
+        // control removes all the noise
+        let bin = parse_components(&[1.0, -1.0, 1.5, 1.0]);
+        let mut engine = MonteCarloEngine::from_components(bin.clone(), 1000.0, 50, 2);
+        let est = engine.control_variate(500, ControlTarget::FinalWealth).unwrap();
+        assert!((est.value() - 1012.5).abs() < 1e-9 && est.variance() < 1e-20);
+
+        // Survival: agrees with plain Monte Carlo at lower variance
+        engine.set_init_wealth(3.0);
+        let plain_ruin = engine.run_importance(20000);
+        let est = engine.control_variate(20000, ControlTarget::SurvivalProbability).unwrap();
+        assert!((est.value() - (1.0 - plain_ruin.value())).abs() < 4.0 * plain_ruin.standard_error());
+        assert!(est.variance() < plain_ruin.variance());
+
+        engine.set_schedule(vec![bin.clone(), bin], 5).unwrap();
+        assert!(engine.control_variate(10, ControlTarget::FinalWealth).is_err());
+    }
+
+    #[test]
+    fn test_stratified_component_sampling() {
+        // A 0.1% tail gets exactly its share every time
+        let comps = parse_components(&[1.0, 0.0, 1.0, 0.999, 2.0, 10.0, 0.001, 1.0, 1.0]);
+        let table = AliasTable::new(comps);
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let xs = sample_n_stratified(&table, 10000, &mut rng);
+            assert_eq!(xs.len(), 10000);
+            assert_eq!(xs.iter().filter(|&&x| x >= 10.0).count(), 10);
+        }
+        // Fractional shares round to a neighbouring count
+        let thirds = AliasTable::new(parse_components(&[0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 2.0, 1.0]));
+        let mut rng = StdRng::seed_from_u64(1);
+        let xs = sample_n_stratified(&thirds, 10, &mut rng);
+        for v in [0.0, 1.0, 2.0] {
+            let count = xs.iter().filter(|&&x| x == v).count();
+            assert!(count == 3 || count == 4, "{} drawn {} times", v, count);
+        }
+    }
+
+    #[test]
+    fn test_halton_sampling() {
+        let sequence = HaltonSequence::new(0);
+        assert_eq!(sequence.bases[..3], [2, 3, 5]);
+        assert_eq!(sequence.bases.len(), HALTON_MAX_DIMENSIONS);
+        // Scrambling keeps the net property: b^m consecutive points put one
+        // in each of the b^m equal cells, in every dimension
+        for dim in [0, 1, HALTON_MAX_DIMENSIONS - 1] {
+            let base = sequence.bases[dim];
+            let cells = if base < 10 { base * base } else { base };
+            let mut hit = vec![false; cells as usize];
+            for i in cells..2 * cells {
+                let u = sequence.coordinate(i, dim);
+                assert!((0.0..1.0).contains(&u));
+                hit[(u * cells as f64) as usize] = true;
+            }
+            assert!(hit.iter().all(|&h| h), "dimension {}", dim);
+        }
+        // Different seeds scramble differently
+        assert_ne!(sequence.coordinate(5, 0), HaltonSequence::new(1).coordinate(5, 0));
+
+        // Expected final wealth far from ruin: the QMC error is well below
+        // the pseudo-random standard error sd / sqrt(n)
+        let bin = parse_components(&[1.0, -1.0, 1.5, 1.0]);
+        let exact = 1000.0 + 10.0 * 0.25;
+        let mc_se = (10.0 * 2.5f64.powi(2) / 12.0).sqrt() / 32.0;
+        let mut engine = MonteCarloEngine::from_components(bin, 1000.0, 10, 3);
+        engine.set_sampling_scheme(SamplingScheme::Halton);
+        let qmc = (engine.run_detailed(1024).mean_final_wealth() - exact).abs();
+        assert!(qmc < 0.2 * mc_se, "qmc error {} vs MC standard error {}", qmc, mc_se);
+    }
+
+    #[test]
+    fn test_run_until_target_precision() {
+        let mut engine = coin_engine(0.5, 3.0, 20, 1);
+        let result = engine.run_until(0.01, 1_000_000);
+        assert!(result.standard_error() < 0.0101);
+        assert_near_exact(result.ruin_probability(), coin_ruin(0.5, 3.0, 20), result.num_trials());
+        assert_eq!(result.num_trials() % ADAPTIVE_BATCH_TRIALS, 0);
+        assert!(result.num_trials() < 5000);
+        // Respects the cap, including a partial last batch
+        assert_eq!(engine.run_until(1e-6, 2500).num_trials(), 2500);
+        // No ruin possible: stops once the Laplace bound is small enough
+        let mut safe = coin_engine(0.5, 100.0, 20, 1);
+        let result = safe.run_until(0.01, 1_000_000);
+        assert_eq!(result.ruin_count(), 0);
+        assert!(result.num_trials() <= 10_000);
+    }
+
+    #[test]
+    fn test_run_chunks() {
+        let mut whole = coin_engine(0.5, 3.0, 30, 12);
+        let expected = whole.run_detailed(3000);
+        assert_near_exact(expected.ruin_probability(), coin_ruin(0.5, 3.0, 30), 3000);
+        let mut chunked = coin_engine(0.5, 3.0, 30, 12);
+        chunked.run_chunk(1000);
+        chunked.run_chunk(500);
+        let result = chunked.run_chunk(1500);
+        assert_eq!(result.num_trials(), 3000);
+        assert_eq!(result.ruin_count(), expected.ruin_count());
+        assert!((result.mean_final_wealth() - expected.mean_final_wealth()).abs() < 1e-9);
+        assert_eq!(result.mean_time_to_ruin(), expected.mean_time_to_ruin());
+        assert!(result.median_final_wealth().is_nan());
+        chunked.reset_chunks();
+        assert_eq!(chunked.run_chunk(10).num_trials(), 10);
+    }
+
+    #[test]
+    fn test_progress_reporting() {
+        let mut plain = coin_engine(0.5, 3.0, 30, 4);
+        let expected = plain.simulate(2500);
+        let mut engine = coin_engine(0.5, 3.0, 30, 4);
+        let mut reports = Vec::new();
+        let outcomes = engine.simulate_with_progress(2500, 1000, |done, ruins| {
+            reports.push((done, ruins));
+            true
+        });
+        let ruins = expected.iter().filter(|o| o.ruin_step.is_some()).count() as u32;
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports.iter().map(|r| r.0).collect::<Vec<_>>(), vec![1000, 2000, 2500]);
+        assert_eq!(reports[2].1, ruins);
+        let steps = |os: &[TrialOutcome]| os.iter().map(|o| o.ruin_step).collect::<Vec<_>>();
+        assert_eq!(steps(&outcomes), steps(&expected));
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let mut engine = coin_engine(0.5, 3.0, 30, 4);
+        // Cancel from the progress report
+        let outcomes = engine.simulate_with_progress(10_000, 1000, |done, _| done < 3000);
+        assert_eq!(outcomes.len(), 3000);
+
+        // Cancel through a shared handle
+        let handle = AbortHandle::new();
+        engine.set_abort_handle(&handle);
+        let flag = handle.flag.clone();
+        let outcomes = engine.simulate_with_progress(10_000, 500, |done, _| {
+            if done == 1500 {
+                flag.set(true);
+            }
+            true
+        });
+        assert_eq!(outcomes.len(), 1500);
+        assert!(handle.is_aborted());
+        assert_eq!(engine.run_detailed(100).num_trials(), 0);
+        handle.reset();
+        assert_eq!(engine.run_detailed(2500).num_trials(), 2500);
+    }
+
+    #[test]
+    fn test_parallel_blocks() {
+        let coin = AliasTable::new(coin(0.5));
+        let totals = parallel_totals(&coin, 3.0, 30, 25_000, 7);
+        assert_eq!(totals.num_trials, 25_000);
+        // Agrees with the unblocked serial engine and the exact value
+        let exact = coin_ruin(0.5, 3.0, 30);
+        let serial = coin_engine(0.5, 3.0, 30, 7).run(25_000) as f64 / 25_000.0;
+        assert_near_exact(totals.ruin_count as f64 / 25_000.0, exact, 25_000);
+        assert_near_exact(serial, exact, 25_000);
+        assert_ne!(parallel_totals(&coin, 3.0, 30, 25_000, 8).ruin_count, totals.ruin_count);
+
+        // The thread count does not change the result
+        #[cfg(feature = "parallel")]
+        {
+            let pool = |threads: usize| rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            let one = pool(1).install(|| parallel_totals(&coin, 3.0, 30, 25_000, 7));
+            let four = pool(4).install(|| parallel_totals(&coin, 3.0, 30, 25_000, 7));
+            assert_eq!(one.ruin_count, four.ruin_count);
+            assert_eq!(one.survivor_wealth_sum, four.survivor_wealth_sum);
+            assert_eq!(one.ruin_count, totals.ruin_count);
+        }
+    }
+
+    #[test]
+    fn test_lane_ruin_counts() {
+        let mut wealth = [1.0, 2.0];
+        assert_eq!(lanes_update(&mut wealth, [-1.0, 1.0]), 0b01);
+        assert_eq!(wealth, [0.0, 3.0]);
+
+        // Both kernels pick the same components and give the same counts
+        let skewed = AliasTable::new(parse_components(&[0.0, -2.0, 0.1, 1.0, 0.0, 1.0, 0.6, 0.0, 3.0, 0.3]));
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..1000 {
+            let uniforms = [rng.gen::<f64>(), rng.gen::<f64>()];
+            assert_eq!(lanes_pick(&skewed, uniforms), lanes_pick_portable(&skewed, uniforms));
+        }
+        let portable = count_ruins_with_lanes(&skewed, 2.0, 20, 5001, &mut StdRng::seed_from_u64(5), lanes_pick_portable, lanes_update_portable);
+        assert_eq!(count_ruins_lanes(&skewed, 2.0, 20, 5001, &mut StdRng::seed_from_u64(5)), portable);
+
+        // 10,001 trials exercises a partial final lane group
+        let coin = AliasTable::new(coin(0.5));
+        let ruins = count_ruins_lanes(&coin, 3.0, 30, 10_001, &mut rng);
+        assert_near_exact(ruins as f64 / 10_001.0, coin_ruin(0.5, 3.0, 30), 10_001);
+        assert_eq!(count_ruins_lanes(&coin, 0.0, 30, 7, &mut rng), 7);
+    }
+
+    #[test]
+    fn test_wealth_grid_curve() {
+        let coin = AliasTable::new(coin(0.5));
+        let wealths = [1.0, 2.0, 3.0, 5.0, 8.0];
+        let mut rng = StdRng::seed_from_u64(4);
+        let common = ruin_curve_over_wealth(&coin, &wealths, 30, 20_000, true, &mut rng);
+        let independent = ruin_curve_over_wealth(&coin, &wealths, 30, 20_000, false, &mut rng);
+        assert!(common.windows(2).all(|w| w[0] >= w[1]));
+        for ((&w, &p), &q) in wealths.iter().zip(&common).zip(&independent) {
+            let exact = coin_ruin(0.5, w, 30);
+            assert_near_exact(p, exact, 20_000);
+            assert_near_exact(q, exact, 20_000);
+        }
+    }
+
+    #[test]
+    fn test_ruin_at_horizons() {
+        assert_eq!(ruin_at_horizons(&[4, 2, 9], &[10, 1, 4], 10), vec![0.3, 0.0, 0.2]);
+        assert!(ruin_at_horizons(&[], &[5], 0)[0].is_nan());
+
+        // The largest horizon reproduces a full run; the others match exact values
+        let mut engine = coin_engine(0.45, 3.0, 7, 5);
+        let curve = engine.horizon_curve(2000, &[10, 40, 25]);
+        assert_eq!(engine.steps, 7);
+        let mut full = coin_engine(0.45, 3.0, 40, 5);
+        assert!((curve[1] - full.run_detailed(2000).ruin_probability()).abs() < 1e-12);
+        for (&h, &p) in [10, 40, 25].iter().zip(&curve) {
+            assert_near_exact(p, coin_ruin(0.45, 3.0, h), 2000);
+        }
+    }
+
+    #[test]
+    fn test_ruin_sensitivities() {
+        let data = [0.0, 1.0, 0.5, 1.0, -1.5, -0.5, 0.5];
+        assert_eq!(perturbable_slots(&data), vec![false, true, false, false, true, true, false]);
+        assert!(ruin_sensitivities(&data, &[0.0; 6], 3.0, 30, 100, 1).is_err());
+        assert!(ruin_sensitivities(&data, &[0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.0], 3.0, 30, 100, 1).is_err());
+
+        // Raising the up step or the bin can only help each common-number path
+        let deltas = [0.0, 0.05, 0.0, 0.0, 0.05, 0.05, 0.0];
+        let s = ruin_sensitivities(&data, &deltas, 3.0, 30, 5000, 2).unwrap();
+        assert!(s.changes[1] < 0.0 && s.changes[4] < 0.0 && s.changes[5] < 0.0);
+        assert_eq!(s.changes[0], 0.0);
+        // Paired differences are far less noisy than two independent runs
+        let independent_se = (2.0 * s.base_probability * (1.0 - s.base_probability) / 5000.0).sqrt();
+        assert!(s.standard_errors[1] < 0.5 * independent_se);
+    }
+
+    #[test]
+    fn test_bin_convolution_trapezoid() {
+        let a = parse_components(&[1.0, 0.0, 1.0, 0.5]);
+        let b = parse_components(&[1.0, 2.0, 5.0, 0.8]);
+        let sum = convolve_components(&a, &b);
+        assert_eq!(sum.len(), 2 * TRAPEZOID_RAMP_BINS + 1);
+        assert!((total_mass(&sum) - 0.4).abs() < 1e-12);
+        assert!((mixture_mean(&sum) - (0.5 + 3.5)).abs() < 1e-12);
+        // Ramp bins sit on their cell's mean, so the variance comes out a little low
+        assert!((mixture_variance(&sum) - (1.0 + 9.0) / 12.0).abs() < 1e-3);
+        let (lo, hi) = support_bounds(&sum);
+        assert!(lo >= 2.0 && hi <= 6.0);
+        // Ramps of width 1 on [2, 3] and [5, 6] hold s^2 / 6 within s of
+        // their foot: exact at every ramp sub-edge and on the flat middle
+        let k = TRAPEZOID_RAMP_BINS as f64;
+        for j in 0..=TRAPEZOID_RAMP_BINS {
+            let s = j as f64 / k;
+            assert!((mixture_cdf(&sum, 2.0 + s) - s * s / 6.0).abs() < 1e-12);
+            assert!((mixture_cdf(&sum, 6.0 - s) - (1.0 - s * s / 6.0)).abs() < 1e-12);
+        }
+        assert!((mixture_cdf(&sum, 4.0) - 0.5).abs() < 1e-12);
+        // and close between them
+        for j in 0..=40 {
+            let s = j as f64 / 40.0;
+            assert!((mixture_cdf(&sum, 2.0 + s) - s * s / 6.0).abs() < 0.01);
+        }
+
+        // Equal widths give a triangle; a zero-width bin is a plain shift
+        let tri = convolve_components(&a, &a);
+        assert_eq!(tri.len(), 2 * TRAPEZOID_RAMP_BINS);
+        assert!((mixture_variance(&tri) - 2.0 / 12.0).abs() < 1e-3);
+        assert!((mixture_cdf(&tri, 1.0) - 0.5).abs() < 1e-12 && (mixture_cdf(&tri, 0.5) - 0.125).abs() < 1e-12);
+        let point = parse_components(&[1.0, 3.0, 3.0, 1.0]);
+        assert_eq!(serialize_components(&convolve_components(&a, &point)), vec![1.0, 3.0, 4.0, 0.5]);
+    }
+
+    #[test]
+    fn test_tail_convolution() {
+        let tail = parse_components(&[2.0, 1.0, 0.5, 2.0, 1.0]);
+        let shifted = convolve_components(&parse_components(&[0.0, 3.0, 0.4]), &tail);
+        assert_eq!(serialize_components(&shifted), vec![2.0, 4.0, 0.2, 2.0, 1.0]);
+
+        // Mass always adds up, and mean and variance add for exact pairings
+        let left = parse_components(&[2.0, -1.0, 1.0, 4.0, 0.0]);
+        let bin = parse_components(&[1.0, 0.0, 3.0, 1.0]);
+        let full = parse_components(&[2.0, 1.0, 1.0, 2.0, 1.0]);
+        // (bin + tail keeps the mean exactly and the variance to within 1e-3)
+        for (x, y, tol) in [(&full, &left, 1e-12), (&bin, &full, 1e-3), (&bin, &left, 1e-3)] {
+            let sum = convolve_components(x, y);
+            assert!((total_mass(&sum) - 1.0).abs() < 1e-12);
+            assert!((mixture_mean(&sum) - mixture_mean(x) - mixture_mean(y)).abs() < 1e-12);
+            assert!((mixture_variance(&sum) - mixture_variance(x) - mixture_variance(y)).abs() < tol);
+        }
+
+        // Bin + tail: U(0, 3) + 1 + Exp(2) has the closed-form CDF
+        // G(s) / 3 on the rise, G(s) = s - (1 - e^{-2s}) / 2, then an
+        // exponential of the same rate past 4
+        let sum = convolve_components(&bin, &full);
+        assert_eq!(sum.len(), BIN_TAIL_RAMP_BINS + 1);
+        let g = |s: f64| s - (1.0 - (-2.0 * s).exp()) / 2.0;
+        let beyond = (1.0 - (-6.0f64).exp()) / 6.0;
+        for j in 0..=BIN_TAIL_RAMP_BINS {
+            let s = 3.0 * j as f64 / BIN_TAIL_RAMP_BINS as f64;
+            assert!((mixture_cdf(&sum, 1.0 + s) - g(s) / 3.0).abs() < 1e-12);
+        }
+        for &t in &[4.0, 4.5, 7.0] {
+            assert!((mixture_prob_gt(&sum, t) - beyond * (-2.0 * (t - 4.0)).exp()).abs() < 1e-12);
+        }
+        // and mirrored for a left tail: U(0, 3) - 1 - Exp(4)
+        let sum = convolve_components(&bin, &left);
+        let g = |s: f64| s - (1.0 - (-4.0 * s).exp()) / 4.0;
+        for j in 0..=BIN_TAIL_RAMP_BINS {
+            let s = 3.0 * j as f64 / BIN_TAIL_RAMP_BINS as f64;
+            assert!((mixture_prob_gt(&sum, 2.0 - s) - g(s) / 3.0).abs() < 1e-12);
+        }
+        let beyond = (1.0 - (-12.0f64).exp()) / 12.0;
+        assert!((mixture_cdf(&sum, -2.0) - beyond * (-4.0f64).exp()).abs() < 1e-12);
+
+        // Same-side tails: Exp(2) + Exp(1) has P(S > t) = 2e^-t - e^-2t
+        for rates in [(2.0, 1.0), (1.0, 1.0)] {
+            let x = parse_components(&[2.0, 1.0, 1.0, rates.0, 1.0]);
+            let sum = convolve_components(&x, &parse_components(&[2.0, 0.0, 1.0, rates.1, 1.0]));
+            assert!((total_mass(&sum) - 1.0).abs() < 1e-12);
+            assert!((mixture_mean(&sum) - (1.0 + 1.0 / rates.0 + 1.0 / rates.1)).abs() < 0.01);
+            for &t in &[0.5, 2.0, 6.0, 12.0] {
+                let exact = hypoexponential_survival(rates.0, rates.1, t).0;
+                assert!((mixture_prob_gt(&sum, 1.0 + t) - exact).abs() < 0.005);
+            }
+        }
+        assert!((hypoexponential_survival(2.0, 1.0, 1.0).0 - (2.0 * (-1f64).exp() - (-2f64).exp())).abs() < 1e-12);
+
+        // Mirrored for left tails; mixtures with tails keep all their mass
+        let l = convolve_components(&parse_components(&[2.0, 0.0, 1.0, 2.0, 0.0]), &parse_components(&[2.0, 0.0, 1.0, 1.0, 0.0]));
+        assert!((mixture_prob_lt(&l, -2.0) - hypoexponential_survival(2.0, 1.0, 2.0).0).abs() < 0.005);
+        let mix = parse_components(&[0.0, 0.0, 0.3, 1.0, 0.0, 1.0, 0.4, 2.0, 1.0, 0.3, 0.5, 1.0]);
+        assert!((total_mass(&convolve_components(&mix, &mix)) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_convolution_mass_lost() {
+        let mix = parse_components(&[0.0, 0.0, 0.3, 1.0, 0.0, 1.0, 0.4, 2.0, 1.0, 0.3, 0.5, 1.0]);
+        assert_eq!(convolve_with_loss(&mix, &mix).1, 0.0);
+
+        // A zero-rate tail cannot be convolved: its pairings' mass is reported
+        let bad = parse_components(&[0.0, 1.0, 0.8, 2.0, 0.0, 0.2, 0.0, 1.0]);
+        let (sum, lost) = convolve_with_loss(&mix, &bad);
+        assert!((lost - 0.2).abs() < 1e-12);
+        assert!((total_mass(&sum) + lost - 1.0).abs() < 1e-12);
+
+        let mut handle = RunningSum::new();
+        handle.add_components(&bad, 0);
+        handle.add_components(&bad, 0);
+        assert!((handle.mass_lost - (0.2 + 0.8 * 0.2)).abs() < 1e-12);
+        assert!((total_mass(&handle.components) + handle.mass_lost - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_iterated_convolution_compression() {
+        let risk = parse_components(&[0.0, 0.0, 0.5, 1.0, 0.0, 2.0, 0.3, 2.0, 2.0, 0.2, 1.0, 1.0]);
+        let mut total = risk.clone();
+        for _ in 0..9 {
+            total = compress_components(&convolve_components(&total, &risk), 12);
+            assert!(total.len() <= 12);
+        }
+        // Same-side tail sums drift in mean; bin ramps also leave the variance low
+        // Only same-side tail sums are not moment-exact
+        assert!((mixture_mean(&total) / (10.0 * mixture_mean(&risk)) - 1.0).abs() < 0.01);
+        assert!((mixture_variance(&total) / (10.0 * mixture_variance(&risk)) - 1.0).abs() < 0.02);
+
+        // Many same-side tails merge into one with matched moments
+        let tails = parse_components(&[2.0, 0.0, 0.5, 1.0, 1.0, 2.0, 1.0, 0.5, 2.0, 1.0, 2.0, -1.0, 0.2, 1.0, 0.0]);
+        let merged = compress_components(&tails, 2);
+        assert_eq!(merged.len(), 2);
+        assert!((mixture_mean(&merged) - mixture_mean(&tails)).abs() < 1e-12);
+        assert!((mixture_variance(&merged) - mixture_variance(&tails)).abs() < 1e-9);
+
+        // Tails that fill the budget absorb the body rather than exceed the cap
+        let two_sided = parse_components(&[2.0, 1.0, 0.3, 1.0, 1.0, 0.0, 0.5, 0.4, 2.0, -1.0, 0.3, 2.0, 0.0]);
+        for cap in [1, 2] {
+            let capped = compress_components(&two_sided, cap);
+            assert_eq!(capped.len(), cap);
+            assert!((total_mass(&capped) - 1.0).abs() < 1e-12);
+            assert!((mixture_mean(&capped) - mixture_mean(&two_sided)).abs() < 1e-12);
+            assert!((mixture_variance(&capped) - mixture_variance(&two_sided)).abs() < 1e-9);
+        }
+        let tails_only = parse_components(&[2.0, 1.0, 0.5, 1.0, 1.0, 2.0, -1.0, 0.5, 2.0, 0.0]);
+        assert_eq!(compress_components(&tails_only, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_wasserstein_distance() {
+        // Shifting by d moves every quantile by d
+        let a = parse_components(&[0.0, 0.0, 0.25, 1.0, 1.0, 3.0, 0.75]);
+        let shifted = parse_components(&[0.0, 0.5, 0.25, 1.0, 1.5, 3.5, 0.75]);
+        assert!((wasserstein_distance(&a, &shifted) - 0.5).abs() < 1e-12);
+        assert!(wasserstein_distance(&a, &a).abs() < 1e-15);
+        // An atom at 0 against U(0, 1): integral of (1 - x) = 1/2
+        let atom = parse_components(&[0.0, 0.0, 1.0]);
+        assert!((wasserstein_distance(&atom, &parse_components(&[1.0, 0.0, 1.0, 1.0])) - 0.5).abs() < 1e-12);
+        // Exp(2) from 0 against its mean: 2 / (e lambda)
+        let tail = parse_components(&[2.0, 0.0, 1.0, 2.0, 1.0]);
+        let mean = parse_components(&[0.0, 0.5, 1.0]);
+        assert!((wasserstein_distance(&tail, &mean) - 1.0 / std::f64::consts::E).abs() < 1e-3);
+
+        // Finer compression is closer to the original
+        let fine: Vec<f64> = (0..40).flat_map(|i| [0.0, (i as f64).sqrt(), 1.0 / 40.0]).collect();
+        let fine = parse_components(&fine);
+        let errors: Vec<f64> = [2, 5, 20].iter().map(|&k| wasserstein_distance(&fine, &compress_components(&fine, k))).collect();
+        assert!(errors[0] > errors[1] && errors[1] > errors[2] && errors[2] > 0.0);
+    }
+
+    #[test]
+    fn test_greedy_wasserstein_merge() {
+        // Two clusters of unequal mass: equal-mass cuts straddle the gap
+        let mut data = Vec::new();
+        for i in 0..10 {
+            data.extend([0.0, i as f64 * 0.1, 0.08, 0.0, 10.0 + i as f64 * 0.1, 0.02]);
+        }
+        let comps = parse_components(&data);
+        let greedy = greedy_wasserstein_merge(&comps, 2, f64::INFINITY);
+        assert_eq!(greedy.len(), 2);
+        assert!((total_mass(&greedy) - 1.0).abs() < 1e-12);
+        assert!((mixture_mean(&greedy) - mixture_mean(&comps)).abs() < 1e-9);
+        assert!((mixture_variance(&greedy) - mixture_variance(&comps)).abs() < 1e-9);
+        let greedy_error = wasserstein_distance(&comps, &greedy);
+        assert!(greedy_error < 0.05);
+        assert!(greedy_error < 0.2 * wasserstein_distance(&comps, &compress_components(&comps, 2)));
+
+        // The error budget stops merging before the clusters are joined
+        let budgeted = greedy_wasserstein_merge(&comps, 1, 0.05);
+        assert_eq!(budgeted.len(), 2);
+        assert!(wasserstein_distance(&comps, &budgeted) <= 0.05);
+    }
+
+    #[test]
+    fn test_simplify_components() {
+        let comps = parse_components(&[
+            0.0, 1.0, 0.1, 1.0, 0.0, 2.0, 0.2, 0.0, 1.0 + 1e-12, 0.1, 0.0, 3.0, 0.2,
+            1.0, 0.0, 2.0 + 1e-13, 0.1, 2.0, 5.0, 0.1, 1.0, 1.0, 0.0, 1.0 - 1e-12, 0.1,
+        ]);
+        let simple = simplify_components(&comps, 1e-9);
+        assert_eq!(simple.len(), 4);
+        assert!(matches!(simple[0], Component::Atom { x, p } if (x - 1.0).abs() < 1e-12 && (p - 0.3).abs() < 1e-15));
+        assert!(matches!(simple[2], Component::Bin { a, b, p } if a == 0.0 && b == 2.0 && (p - 0.3).abs() < 1e-15));
+        assert!(matches!(simple[3], Component::Tail { .. }));
+        assert!((mixture_mean(&simple) - mixture_mean(&comps)).abs() < 1e-12);
+
+        // Convolving discrete distributions collapses onto the lattice
+        let coin = parse_components(&[0.0, 0.1, 0.5, 0.0, 0.2, 0.5]);
+        let sum = (0..5).fold(coin.clone(), |acc, _| convolve_components(&acc, &coin));
+        assert_eq!(sum.len(), 64);
+        assert_eq!(simplify_components(&sum, 1e-9).len(), 7);
+        // Zero tolerance only merges exact duplicates
+        assert_eq!(simplify_components(&comps, 0.0).len(), 7);
+    }
+
+    #[test]
+    fn test_prune_components() {
+        let comps = parse_components(&[0.0, 1.0, 0.5, 1.0, 0.0, 2.0, 0.3, 0.0, 9.0, 1e-9, 2.0, 4.0, 0.2 - 1e-9, 1.0, 1.0]);
+        let (kept, pruned) = prune_components(&comps, 1e-6, false);
+        assert_eq!(kept.len(), 3);
+        assert!((pruned - 1e-9).abs() < 1e-20);
+        assert!((total_mass(&kept) + pruned - 1.0).abs() < 1e-15);
+
+        let (kept, pruned) = prune_components(&comps, 0.25, true);
+        assert_eq!(kept.len(), 2);
+        assert!((pruned - 0.2).abs() < 1e-15);
+        assert!((total_mass(&kept) - 1.0).abs() < 1e-15);
+        assert!(matches!(kept[0], Component::Atom { p, .. } if (p - 0.625).abs() < 1e-12));
+
+        // Nothing kept: nothing to redistribute onto
+        let (kept, pruned) = prune_components(&comps, 1.0, true);
+        assert!(kept.is_empty() && (pruned - 1.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_rebin() {
+        let comps = parse_components(&[0.0, 1.0, 0.2, 1.0, 0.0, 4.0, 0.4, 2.0, 4.0, 0.4, 1.0, 1.0]);
+        assert!(rebin(&comps, 1.0, 1.0, 4, 0).is_err());
+        assert!(rebin(&comps, 0.0, 4.0, 0, 0).is_err());
+        assert!(rebin(&comps, 0.0, 4.0, 4, 3).is_err());
+
+        // The atom on an inner edge goes to the bin on its left
+        let dropped = rebin(&comps, 0.0, 4.0, 4, 0).unwrap();
+        let masses: Vec<f64> = dropped.iter().map(get_weight).collect();
+        for (m, e) in masses.iter().zip([0.3, 0.1, 0.1, 0.1]) {
+            assert!((m - e).abs() < 1e-12);
+        }
+        assert!(matches!(dropped[3], Component::Bin { a, b, .. } if a == 3.0 && b == 4.0));
+
+        let folded = rebin(&comps, 0.0, 4.0, 4, 1).unwrap();
+        assert_eq!(folded.len(), 4);
+        assert!((total_mass(&folded) - 1.0).abs() < 1e-12);
+        assert!((get_weight(&folded[3]) - 0.5).abs() < 1e-12);
+
+        // Preserved tails keep the mass and shape beyond the range
+        let kept = rebin(&comps, 0.0, 4.0, 4, 2).unwrap();
+        assert_eq!(kept.len(), 5);
+        assert!((total_mass(&kept) - 1.0).abs() < 1e-12);
+        assert!((mixture_prob_gt(&kept, 5.0) - mixture_prob_gt(&comps, 5.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_convolve_power() {
+        // A cap above every intermediate size (4 x 256 pieces) keeps powers
+        // of a discrete distribution exact: they match the binomial
+        let coin = parse_components(&[0.0, 0.0, 0.5, 0.0, 1.0, 0.5]);
+        let sum = simplify_components(&convolve_power(&coin, 10, 1024).unwrap(), 1e-9);
+        assert_eq!(sum.len(), 11);
+        assert!(matches!(sum[3], Component::Atom { x, p } if x == 3.0 && (p - 120.0 / 1024.0).abs() < 1e-15));
+        assert_eq!(serialize_components(&convolve_power(&coin, 0, 4).unwrap()), vec![0.0, 0.0, 1.0]);
+        assert!(convolve_power(&coin, 10, 0).is_err());
+
+        // Compressed powers keep mass and mean; bin sums leave the variance
+        // a little low
+        let risk = parse_components(&[0.0, 0.0, 0.6, 1.0, 1.0, 3.0, 0.4]);
+        let power = convolve_power(&risk, 37, 16).unwrap();
+        assert!(power.len() <= 16);
+        assert!((total_mass(&power) - 1.0).abs() < 1e-9);
+        assert!((mixture_mean(&power) - 37.0 * mixture_mean(&risk)).abs() < 1e-9);
+        assert!((mixture_variance(&power) / (37.0 * mixture_variance(&risk)) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_convolve_lattice_fft() {
+        let a = parse_components(&[1.0, 0.0, 0.5, 0.2, 1.0, 1.0, 1.5, 0.5, 1.0, 0.5, 1.0, 0.3]);
+        let b = parse_components(&[1.0, 2.0, 2.5, 0.6, 1.0, 3.0, 3.5, 0.4]);
+        assert_eq!(lattice_bins(&b), Some((2.0, 0.5, vec![0.6, 0.0, 0.4])));
+        assert!(lattice_bins(&parse_components(&[1.0, 0.0, 0.5, 0.5, 1.0, 0.7, 1.2, 0.5])).is_none());
+        assert!(lattice_bins(&parse_components(&[0.0, 1.0, 1.0])).is_none());
+
+        // Matches the pairwise convolution of `a` with `b` at its bin centers
+        let sum = convolve_lattice_fft(&a, &b).unwrap();
+        let centers = parse_components(&[0.0, 2.25, 0.6, 0.0, 3.25, 0.4]);
+        let pairwise = simplify_components(&convolve_components(&a, &centers), 1e-9);
+        assert_eq!(sum.len(), pairwise.len());
+        for (x, y) in sum.iter().zip(&pairwise) {
+            let (Component::Bin { a: a1, p: p1, .. }, Component::Bin { a: a2, p: p2, .. }) = (x, y) else { panic!() };
+            assert!((a1 - a2).abs() < 1e-12 && (p1 - p2).abs() < 1e-12);
+        }
+        assert!((mixture_mean(&sum) - mixture_mean(&a) - mixture_mean(&b)).abs() < 1e-12);
+        // The output is a lattice again
+        assert!(convolve_lattice_fft(&sum, &a).is_ok());
+        assert!(convolve_lattice_fft(&a, &parse_components(&[1.0, 0.0, 1.0, 1.0])).is_err());
+    }
+
+    #[test]
+    fn test_clt_approx() {
+        let risk = parse_components(&[0.0, 0.0, 0.6, 1.0, 1.0, 3.0, 0.4]);
+        let sum = clt_approx(&risk, 2000);
+        assert_eq!(sum.len(), CLT_BINS);
+        assert!((total_mass(&sum) - 1.0).abs() < 1e-12);
+        assert!((mixture_mean(&sum) - 2000.0 * mixture_mean(&risk)).abs() < 1e-9);
+        assert!((mixture_variance(&sum) / (2000.0 * mixture_variance(&risk)) - 1.0).abs() < 1e-12);
+        // Quantiles match the normal
+        let (mean, sd) = (mixture_mean(&sum), mixture_variance(&sum).sqrt());
+        assert!((mixture_quantile(&sum, 0.975) - (mean + 1.959964 * sd)).abs() < 0.01 * sd);
+
+        assert!(matches!(clt_approx(&risk, 0)[..], [Component::Atom { x, p }] if x == 0.0 && p == 1.0));
+        let point = parse_components(&[0.0, 2.0, 1.0]);
+        assert!(matches!(clt_approx(&point, 5)[..], [Component::Atom { x, .. }] if x == 10.0));
+    }
+
+    #[test]
+    fn test_sum_quantile_cornish_fisher() {
+        // Sum of 10 Exp(1) is Gamma(10, 1), whose 99% quantile is 18.7831
+        let exp = parse_components(&[2.0, 0.0, 1.0, 1.0, 1.0]);
+        let cf = sum_quantile_cornish_fisher(&exp, 10, 0.99);
+        let clt = mixture_quantile(&clt_approx(&exp, 10), 0.99);
+        assert!((cf - 18.7831).abs() < 0.15);
+        assert!((cf - 18.7831).abs() < 0.25 * (clt - 18.7831).abs());
+        // Symmetric sums have no skew correction at the median
+        let coin = coin(0.5);
+        assert!(sum_quantile_cornish_fisher(&coin, 50, 0.5).abs() < 1e-12);
+        assert!(sum_quantile_cornish_fisher(&coin, 50, 1.0).is_nan());
+        assert_eq!(sum_quantile_cornish_fisher(&coin, 0, 0.9), 0.0);
+    }
+
+    #[test]
+    fn test_saddlepoint_tail() {
+        // Tilted moments: Exp(1) tilted by t is Exp(1 - t)
+        let exp = parse_components(&[2.0, 0.0, 1.0, 1.0, 1.0]);
+        let (k, m, v) = tilted_moments(&exp, 0.5).unwrap();
+        assert!((k - 2f64.ln()).abs() < 1e-12 && (m - 2.0).abs() < 1e-12 && (v - 4.0).abs() < 1e-12);
+        assert!(tilted_moments(&exp, 1.0).is_none());
+        let bin = parse_components(&[1.0, 0.0, 1.0, 1.0]);
+        for &t in &[1e-6, 0.3, -2.0, 40.0] {
+            let (k, m, v) = tilted_moments(&bin, t).unwrap();
+            assert!((k - mixture_mgf(&bin, t).ln()).abs() < 1e-9);
+            let h = 1e-4;
+            let (kp, km) = (tilted_moments(&bin, t + h).unwrap().0, tilted_moments(&bin, t - h).unwrap().0);
+            assert!((m - (kp - km) / (2.0 * h)).abs() < 1e-6);
+            assert!((v - (kp - 2.0 * k + km) / (h * h)).abs() < 1e-4);
+        }
+
+        // Gamma(10, 1) tail: e^-x sum_{k<10} x^k / k!
+        let gamma_tail = |x: f64| (0..10).map(|k| x.powi(k) / (1..=k).map(|j| j as f64).product::<f64>()).sum::<f64>() * (-x).exp();
+        for &x in &[15.0, 25.0, 40.0] {
+            let p = saddlepoint_tail(&exp, 10, x);
+            assert!((p / gamma_tail(x) - 1.0).abs() < 0.01);
+        }
+        // Far tail, where 1 - CDF(w) would cancel to 0
+        for &x in &[60.0, 70.0, 80.0, 100.0] {
+            let p = saddlepoint_tail(&exp, 10, x);
+            assert!(p > 0.0 && (p / gamma_tail(x) - 1.0).abs() < 0.01, "x = {x}: {p} vs {}", gamma_tail(x));
+        }
+        assert!((saddlepoint_tail(&exp, 10, 5.0) - (1.0 - 0.0318281)).abs() < 0.003);
+        // Irwin-Hall: P(U1 + U2 + U3 > 2.5) = 1/48
+        assert!((saddlepoint_tail(&bin, 3, 2.5) * 48.0 - 1.0).abs() < 0.05);
+        assert_eq!(saddlepoint_tail(&bin, 3, 3.0), 0.0);
+        assert_eq!(saddlepoint_tail(&bin, 3, -0.1), 1.0);
+    }
+
+    #[test]
+    fn test_subtract_components() {
+        // Exp(2) income minus Exp(1) loss: asymmetric Laplace about 0
+        let income = parse_components(&[2.0, 0.0, 1.0, 2.0, 1.0]);
+        let loss = parse_components(&[2.0, 0.0, 1.0, 1.0, 1.0]);
+        let net = subtract_components(&income, &loss);
+        assert_eq!(net.len(), 2);
+        assert!((mixture_prob_lt(&net, 0.0) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((mixture_prob_lt(&net, -3.0) - 2.0 / 3.0 * (-3f64).exp()).abs() < 1e-12);
+        assert!((mixture_mean(&net) - (0.5 - 1.0)).abs() < 1e-12);
+        assert!((mixture_variance(&net) - (0.25 + 1.0)).abs() < 1e-12);
+
+        // Bins and atoms in either operand
+        let x = parse_components(&[0.0, 10.0, 0.5, 1.0, 8.0, 12.0, 0.5]);
+        let y = parse_components(&[0.0, 1.0, 0.7, 2.0, 3.0, 0.3, 0.5, 1.0]);
+        let d = subtract_components(&x, &y);
+        assert!((total_mass(&d) - 1.0).abs() < 1e-12);
+        assert!((mixture_mean(&d) - mixture_mean(&x) + mixture_mean(&y)).abs() < 1e-12);
+        assert!((mixture_variance(&d) - mixture_variance(&x) - mixture_variance(&y)).abs() < 1e-3);
+        assert!(support_bounds(&d).0 == f64::NEG_INFINITY && support_bounds(&d).1 == 11.0);
+    }
+
+    #[test]
+    fn test_negate_components() {
+        let data = [0.0, 2.0, 0.2, 1.0, -1.0, 3.0, 0.5, 2.0, 4.0, 0.3, 0.5, 1.0];
+        let comps = parse_components(&data);
+        let neg = negate_components(&comps);
+        assert_eq!(serialize_components(&neg), vec![0.0, -2.0, 0.2, 1.0, -3.0, 1.0, 0.5, 2.0, -4.0, 0.3, 0.5, 0.0]);
+        assert_eq!(serialize_components(&negate_components(&neg)), data.to_vec());
+        for &x in &[-5.0, -1.0, 0.0, 2.5] {
+            assert!((mixture_prob_lt(&neg, x) - mixture_prob_gt(&comps, -x)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_shift_matches_atom_convolution() {
+        let comps = parse_components(&[0.0, 2.0, 0.2, 1.0, -1.0, 3.0, 0.5, 2.0, 4.0, 0.3, 0.5, 0.0]);
+        let shifted: Vec<Component> = comps.iter().map(|c| shift_component(c, -1.5)).collect();
+        let convolved = convolve_components(&comps, &parse_components(&[0.0, -1.5, 1.0]));
+        assert_eq!(serialize_components(&shifted), serialize_components(&convolved));
+    }
+
+    #[test]
+    fn test_extreme_of_pair() {
+        // Max of two U(0, 1) has CDF x^2
+        let u = parse_components(&[1.0, 0.0, 1.0, 1.0]);
+        let max = extreme_of_pair(&u, &u, 200, true);
+        assert!((total_mass(&max) - 1.0).abs() < 1e-12);
+        for &x in &[0.1, 0.5, 0.9] {
+            assert!((mixture_cdf(&max, x) - x * x).abs() < 1e-12);
+        }
+        assert!((mixture_mean(&max) - 2.0 / 3.0).abs() < 1e-5);
+
+        // Max of Exp(1) and the constant 1: an atom at 1, then the tail
+        let exp = parse_components(&[2.0, 0.0, 1.0, 1.0, 1.0]);
+        let one = parse_components(&[0.0, 1.0, 1.0]);
+        let max = extreme_of_pair(&exp, &one, 100, true);
+        assert!(max.iter().any(|c| matches!(c, Component::Atom { x, p } if *x == 1.0 && (p - (1.0 - (-1f64).exp())).abs() < 1e-12)));
+        assert!((mixture_prob_gt(&max, 3.0) / (-3f64).exp() - 1.0).abs() < 0.01);
+        assert!((total_mass(&max) - 1.0).abs() < 1e-12);
+
+        // Min of two Exp(1) is Exp(2)
+        let min = extreme_of_pair(&exp, &exp, 400, false);
+        for &x in &[0.5, 2.0, 12.0] {
+            assert!((mixture_prob_gt(&min, x) / (-2.0 * x).exp() - 1.0).abs() < 2e-3);
+        }
+        assert!((total_mass(&min) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_multiply_components() {
+        // Atom pairings are exact
+        let atoms = parse_components(&[0.0, 2.0, 0.5, 0.0, -1.0, 0.5]);
+        let mixed = parse_components(&[0.0, 0.0, 0.2, 1.0, 1.0, 3.0, 0.5, 2.0, 1.0, 0.3, 2.0, 1.0]);
+        let product = multiply_components(&atoms, &mixed, 0);
+        assert_eq!(product.len(), 6);
+        assert!((mixture_prob_lt(&product, -4.0) - 0.5 * 0.3 * (-6f64).exp()).abs() < 1e-12);
+        assert!((mixture_prob_gt(&product, 4.0) - 0.5 * (0.5 * 0.5 + 0.3 * (-2f64).exp())).abs() < 1e-12);
+
+        // U(1, 2)^2: P(XY <= 2) = 2 ln 2 - 1, and E[XY] = E[X] E[Y]
+        let u = parse_components(&[1.0, 1.0, 2.0, 1.0]);
+        let square = multiply_components(&u, &u, 0);
+        assert!((mixture_cdf(&square, 2.0) - (2.0 * 2f64.ln() - 1.0)).abs() < 2e-3);
+        assert!((mixture_mean(&square) - 2.25).abs() < 1e-12);
+        let both = multiply_components(&mixed, &u, 12);
+        assert!(both.len() <= 12);
+        assert!((total_mass(&both) - 1.0).abs() < 1e-12);
+        assert!((mixture_mean(&both) - 1.5 * mixture_mean(&mixed)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reciprocal_components() {
+        // An atom at 0 is dropped; other atoms invert exactly
+        let data = parse_components(&[0.0, 2.0, 0.25, 0.0, 0.0, 0.25, 1.0, 1.0, 2.0, 0.5]);
+        let (inverse, dropped) = reciprocal_components(&data, 0.0);
+        assert!((dropped - 0.25).abs() < 1e-12);
+        assert!(inverse.iter().any(|c| matches!(c, Component::Atom { x, p } if *x == 0.5 && *p == 0.25)));
+
+        // 1 / U(1, 2): P(Y <= y) = 2 - 1 / y on [1/2, 1], E[Y] = ln 2
+        let u = parse_components(&[1.0, 1.0, 2.0, 1.0]);
+        let (inv_u, _) = reciprocal_components(&u, 0.0);
+        assert!((mixture_cdf(&inv_u, 0.7) - (2.0 - 1.0 / 0.7)).abs() < 5e-4);
+        assert!((mixture_mean(&inv_u) - 2f64.ln()).abs() < 1e-4);
+
+        // 1 / (1 + Exp(1)): P(Y <= 1/2) = P(X >= 2) = e^-1
+        let tail = parse_components(&[2.0, 1.0, 1.0, 1.0, 1.0]);
+        let (inv_tail, _) = reciprocal_components(&tail, 0.0);
+        assert!((mixture_cdf(&inv_tail, 0.5) - (-1f64).exp()).abs() < 2e-3);
+        assert!(mixture_prob_gt(&inv_tail, 1.0) < 1e-12);
+
+        // U(-1, 1) with a 0.1 band around zero dropped
+        let sym = parse_components(&[1.0, -1.0, 1.0, 1.0]);
+        let (inv_sym, dropped) = reciprocal_components(&sym, 0.1);
+        assert!((dropped - 0.1).abs() < 1e-12);
+        assert!(support_bounds(&inv_sym).0 >= -10.0 - 1e-9 && support_bounds(&inv_sym).1 <= 10.0 + 1e-9);
+        assert!(mixture_mean(&inv_sym).abs() < 1e-12);
+
+        // 3 / U(1, 2) has mean 3 ln 2
+        let (ratio, dropped) = divide_components(&parse_components(&[0.0, 3.0, 1.0]), &u, 0, 0.0);
+        assert_eq!(dropped, 0.0);
+        assert!((mixture_mean(&ratio) - 3.0 * 2f64.ln()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_abs_components() {
+        // Atoms at +-1 combine, the atom at 0 stays, U(-1, 2) splits at 0
+        let data = parse_components(&[0.0, -1.0, 0.2, 0.0, 1.0, 0.1, 0.0, 0.0, 0.1, 1.0, -1.0, 2.0, 0.6]);
+        let folded = abs_components(&data);
+        assert!((total_mass(&folded) - 1.0).abs() < 1e-12);
+        assert!(folded.iter().any(|c| matches!(c, Component::Atom { x, p } if *x == 1.0 && (*p - 0.3).abs() < 1e-12)));
+        assert!(folded.iter().any(|c| matches!(c, Component::Atom { x, p } if *x == 0.0 && *p == 0.1)));
+        assert!((mass_at_or_below(&folded, 0.5) - (0.1 + 0.6 * 1.0 / 3.0)).abs() < 1e-12);
+        assert!(support_bounds(&folded).0 >= 0.0);
+
+        // Laplace(0, 1) folds to Exp(1); a left tail anchored at 1 leaves a
+        // bin on (0, 1] and a right tail from 0
+        let laplace = parse_components(&[2.0, 0.0, 0.5, 1.0, 1.0, 2.0, 0.0, 0.5, 1.0, 0.0]);
+        let exp = abs_components(&laplace);
+        for &x in &[0.3, 1.0, 4.0] {
+            assert!((mixture_prob_gt(&exp, x) - (-x).exp()).abs() < 1e-12);
+        }
+        let shifted = abs_components(&parse_components(&[2.0, 1.0, 1.0, 1.0, 0.0]));
+        assert!((mixture_prob_gt(&shifted, 1.0) - (-2f64).exp()).abs() < 1e-12);
+        assert!((mixture_cdf(&shifted, 1.0) - (1.0 - (-2f64).exp())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_log_exp_components() {
+        // e^U(0, 1): P(Y <= 2) = ln 2, E[Y] = e - 1; atoms map exactly
+        let data = parse_components(&[0.0, 0.0, 0.5, 1.0, 0.0, 1.0, 0.5]);
+        let grown = exp_components(&data, 32);
+        assert!(grown.iter().any(|c| matches!(c, Component::Atom { x, p } if *x == 1.0 && *p == 0.5)));
+        assert!((mass_at_or_below(&grown, 2.0) - (0.5 + 0.5 * 2f64.ln())).abs() < 1e-4);
+        assert!((mixture_mean(&grown) - (0.5 + 0.5 * (1f64.exp() - 1.0))).abs() < 1e-4);
+
+        // A left tail of rate 2 below 0 maps to P(Y <= y) = y^2 on (0, 1]
+        let below = exp_components(&parse_components(&[2.0, 0.0, 1.0, 2.0, 0.0]), 32);
+        assert!((mixture_cdf(&below, 0.5) - 0.25).abs() < 2e-3);
+        assert!(support_bounds(&below).0 >= 0.0 && support_bounds(&below).1 <= 1.0);
+
+        // ln U(0, 1) is -Exp(1); an atom below zero is dropped
+        let (logged, dropped) = log_components(&parse_components(&[1.0, 0.0, 1.0, 0.8, 0.0, -1.0, 0.2]), 32);
+        assert!((dropped - 0.2).abs() < 1e-12);
+        for &y in &[-3.0, -1.0, -0.2] {
+            assert!((mixture_cdf(&logged, y) - y.exp()).abs() < 1e-3);
+        }
+        assert!((mixture_mean(&logged) + 1.0).abs() < 2e-3);
+
+        // ln(1 + Exp(1)): P(Y > ln 2) = P(X > 2) = e^-1
+        let (log_tail, _) = log_components(&parse_components(&[2.0, 1.0, 1.0, 1.0, 1.0]), 32);
+        assert!((mixture_prob_gt(&log_tail, 2f64.ln()) - (-1f64).exp()).abs() < 2e-3);
+
+        // Round trip through exp and back
+        let (back, _) = log_components(&exp_components(&parse_components(&[1.0, -1.0, 1.0, 1.0]), 32), 32);
+        assert!((mixture_cdf(&back, 0.3) - 0.65).abs() < 1e-3);
+        assert!(mixture_mean(&back).abs() < 1e-3);
+    }
+}
+
+/// Serialize components back to flat array format
+fn serialize_components(components: &[Component]) -> Vec<f64> {
+    let mut result = Vec::new();
+    for c in components {
+        match c {
+            Component::Atom { x, p } => {
+                result.push(0.0);
+                result.push(*x);
+                result.push(*p);
+            }
+            Component::Bin { a, b, p } => {
+                result.push(1.0);
+                result.push(*a);
+                result.push(*b);
+                result.push(*p);
+            }
+            Component::Tail { x0, mass, lambda, is_right } => {
+                result.push(2.0);
+                result.push(*x0);
+                result.push(*mass);
+                result.push(*lambda);
+                result.push(if *is_right { 1.0 } else { 0.0 });
+            }
+        }
+    }
+    result
+}
+
+/// Human-readable dump of components, one per line, followed by the total mass
+fn format_components(components: &[Component]) -> String {
+    let mut lines: Vec<String> = components.iter().map(|c| match c {
+        Component::Atom { x, p } => format!("Atom(x={}, p={})", x, p),
+        Component::Bin { a, b, p } => format!("Bin(a={}, b={}, p={})", a, b, p),
+        Component::Tail { x0, mass, lambda, is_right } => format!(
+            "Tail(x0={}, mass={}, lambda={}, {})",
+            x0, mass, lambda, if *is_right { "right" } else { "left" }
+        ),
+    }).collect();
+    let total: f64 = components.iter().map(get_weight).sum();
+    lines.push(format!("Total mass: {}", total));
+    lines.join("\n")
+}
+
+/// Debug string for logging, e.g. "Atom(x=10, p=0.5)\nTotal mass: 0.5"
+#[wasm_bindgen]
+pub fn dist_debug_string(components_data: Float64Array) -> String {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    format_components(&components)
+}
+
+/// Bins each linear ramp of a bin + bin trapezoid is split into
+const TRAPEZOID_RAMP_BINS: usize = 4;
+
+/// Bins the rising part of a bin + tail sum is split into
+const BIN_TAIL_RAMP_BINS: usize = 8;
+
+/// Bins, past the start, that a same-side tail + tail sum is split into
+const TAIL_SUM_BINS: usize = 16;
+
+/// A same-side tail + tail sum keeps bins out to this many means past its
+/// start; the remainder becomes a single tail
+const TAIL_SUM_SPAN_MEANS: f64 = 3.0;
+
+/// Bin holding a cell's `mass` on [u, v], centered on the cell's `mean` and
+/// as wide as the cell allows
+fn cell_bin(u: f64, v: f64, mass: f64, mean: f64) -> Component {
+    let mean = mean.clamp(u, v);
+    let half = (mean - u).min(v - mean);
+    Component::Bin { a: mean - half, b: mean + half, p: mass }
+}
+
+/// Sum of two independent uniform bins: a trapezoid on [a1 + a2, b1 + b2]
+///
+/// Taking the first bin as the wider one, the density rises linearly over
+/// the narrow width, is flat on [a1 + b2, b1 + a2] and falls back the same
+/// way. The flat middle is one exact bin and each ramp is cut into
+/// `TRAPEZOID_RAMP_BINS` equal-width cells, each holding the ramp's exact
+/// mass over it in a `cell_bin` on its exact mean. Mass, mean and the CDF
+/// at every cell edge are exact. In between the CDF error is largest for
+/// equal widths, about 0.009 of the pair's mass with 4 cells, and falls
+/// like 1 / k^2 in the cell count; the variance comes out low by the
+/// within-cell spread the centered bins give up.
+fn convolve_bins(bin1: (f64, f64), bin2: (f64, f64), p: f64, out: &mut Vec<Component>) {
+    let ((wide_a, wide_b), (narrow_a, narrow_b)) =
+        if bin1.1 - bin1.0 >= bin2.1 - bin2.0 { (bin1, bin2) } else { (bin2, bin1) };
+    let (wide, narrow) = (wide_b - wide_a, narrow_b - narrow_a);
+    let (lo, hi) = (wide_a + narrow_a, wide_b + narrow_b);
+    if narrow <= 0.0 {
+        out.push(Component::Bin { a: lo, b: hi, p });
+        return;
+    }
+    // Mass and first moment of one ramp from its foot out to distance s
+    let ramp = |s: f64| p * s * s / (2.0 * wide * narrow);
+    let moment = |s: f64| p * s * s * s / (3.0 * wide * narrow);
+    let h = narrow / TRAPEZOID_RAMP_BINS as f64;
+    let cells: Vec<(f64, f64, f64, f64)> = (0..TRAPEZOID_RAMP_BINS)
+        .map(|j| {
+            let (s0, s1) = (j as f64 * h, if j + 1 == TRAPEZOID_RAMP_BINS { narrow } else { (j + 1) as f64 * h });
+            let mass = ramp(s1) - ramp(s0);
+            (s0, s1, mass, (moment(s1) - moment(s0)) / mass)
+        })
+        .collect();
+    for &(s0, s1, mass, mean) in &cells {
+        out.push(cell_bin(lo + s0, lo + s1, mass, lo + mean));
+    }
+    if wide > narrow {
+        out.push(Component::Bin { a: lo + narrow, b: hi - narrow, p: p * (wide - narrow) / wide });
+    }
+    for &(s0, s1, mass, mean) in cells.iter().rev() {
+        out.push(cell_bin(hi - s1, hi - s0, mass, hi - mean));
+    }
+}
+
+/// Sum of a uniform bin on [a, b] and a tail from `x0`, with mass `p`
+///
+/// For a right tail the density rises as (1 - e^{-lambda (t - x0 - a)}) / d
+/// on [x0 + a, x0 + b], d = b - a, and beyond x0 + b is an exponential of
+/// the same rate: a tail from x0 + b with mass p (1 - e^{-lambda d}) /
+/// (lambda d). The rising part is cut into `BIN_TAIL_RAMP_BINS` equal-width
+/// cells, each holding its exact mass in a `cell_bin` on its exact mean, so
+/// mass, mean and the CDF at every cell edge and along the tail are exact.
+/// Left tails are the mirror image.
+fn convolve_bin_tail(bin: (f64, f64), x0: f64, lambda: f64, is_right: bool, p: f64, out: &mut Vec<Component>) {
+    let (a, b) = bin;
+    let d = b - a;
+    if d <= 0.0 {
+        out.push(Component::Tail { x0: x0 + a, mass: p, lambda, is_right });
+        return;
+    }
+    // Mass and first moment of the rising part from its foot out to distance s
+    let ramp = |s: f64| p / d * (s + (-lambda * s).exp_m1() / lambda);
+    let moment = |s: f64| p / d * (s * s / 2.0 - (1.0 - (1.0 + lambda * s) * (-lambda * s).exp()) / (lambda * lambda));
+    let (foot, sign) = if is_right { (x0 + a, 1.0) } else { (x0 + b, -1.0) };
+    let h = d / BIN_TAIL_RAMP_BINS as f64;
+    for j in 0..BIN_TAIL_RAMP_BINS {
+        let (s0, s1) = (j as f64 * h, if j + 1 == BIN_TAIL_RAMP_BINS { d } else { (j + 1) as f64 * h });
+        let mass = ramp(s1) - ramp(s0);
+        let mean = foot + sign * (moment(s1) - moment(s0)) / mass;
+        let (u, v) = (foot + sign * s0, foot + sign * s1);
+        out.push(cell_bin(u.min(v), u.max(v), mass, mean));
+    }
+    let mass = -p * (-lambda * d).exp_m1() / (lambda * d);
+    out.push(Component::Tail { x0: foot + sign * d, mass, lambda, is_right });
+}
+
+/// P(E1 + E2 > t) and its integral over [t, inf) for independent
+/// exponentials with rates `l1` and `l2` (hypoexponential; Gamma(2) when the
+/// rates coincide)
+fn hypoexponential_survival(l1: f64, l2: f64, t: f64) -> (f64, f64) {
+    if (l1 - l2).abs() <= 1e-6 * l1.max(l2) {
+        let l = (l1 + l2) / 2.0;
+        let e = (-l * t).exp();
+        return ((1.0 + l * t) * e, (2.0 + l * t) * e / l);
+    }
+    let (e1, e2) = ((-l1 * t).exp(), (-l2 * t).exp());
+    ((l2 * e1 - l1 * e2) / (l2 - l1), (l2 / l1 * e1 - l1 / l2 * e2) / (l2 - l1))
+}
+
+/// Sum of two tails on the same side of `x0`: `TAIL_SUM_BINS` bins with the
+/// exact hypoexponential masses, then a tail whose rate matches the mean
+/// excess beyond the last bin
+fn convolve_same_side_tails(x0: f64, l1: f64, l2: f64, is_right: bool, p: f64, out: &mut Vec<Component>) {
+    let sign = if is_right { 1.0 } else { -1.0 };
+    let span = TAIL_SUM_SPAN_MEANS * (1.0 / l1 + 1.0 / l2);
+    let width = span / TAIL_SUM_BINS as f64;
+    let mut survival = 1.0;
+    for i in 0..TAIL_SUM_BINS {
+        let next = hypoexponential_survival(l1, l2, (i + 1) as f64 * width).0;
+        let (u, v) = (x0 + sign * i as f64 * width, x0 + sign * (i + 1) as f64 * width);
+        out.push(Component::Bin { a: u.min(v), b: u.max(v), p: p * (survival - next) });
+        survival = next;
+    }
+    let (rest, integral) = hypoexponential_survival(l1, l2, span);
+    if rest > 0.0 && integral > 0.0 {
+        out.push(Component::Tail { x0: x0 + sign * span, mass: p * rest, lambda: rest / integral, is_right });
+    }
+}
+
+/// Convolve two components, appending the result to `out`; returns false
+/// for pairings that are not supported (their mass is dropped)
+fn convolve_pair(c1: &Component, c2: &Component, out: &mut Vec<Component>) -> bool {
+    match (c1, c2) {
+        // Atom + Atom = Atom
+        (Component::Atom { x: x1, p: p1 }, Component::Atom { x: x2, p: p2 }) => {
+            out.push(Component::Atom { x: x1 + x2, p: p1 * p2 });
+        }
+        // Atom + Bin = shifted Bin
+        (Component::Atom { x, p: p1 }, Component::Bin { a, b, p: p2 }) |
+        (Component::Bin { a, b, p: p2 }, Component::Atom { x, p: p1 }) => {
+            out.push(Component::Bin { a: a + x, b: b + x, p: p1 * p2 });
+        }
+        // Bin + Bin = trapezoid: exact flat middle, ramps in bins of exact mass
+        (Component::Bin { a: a1, b: b1, p: p1 }, Component::Bin { a: a2, b: b2, p: p2 }) => {
+            convolve_bins((*a1, *b1), (*a2, *b2), p1 * p2, out);
+        }
+        // A tail with a non-positive or non-finite rate is not a distribution
+        (Component::Tail { lambda, .. }, _) | (_, Component::Tail { lambda, .. })
+            if !(lambda.is_finite() && *lambda > 0.0) =>
+        {
+            return false;
+        }
+        // Atom + Tail = shifted Tail
+        (Component::Atom { x, p }, Component::Tail { x0, mass, lambda, is_right }) |
+        (Component::Tail { x0, mass, lambda, is_right }, Component::Atom { x, p }) => {
+            out.push(Component::Tail { x0: x0 + x, mass: p * mass, lambda: *lambda, is_right: *is_right });
+        }
+        // Bin + Tail = rising part in bins of exact mass, then the same tail
+        (Component::Bin { a, b, p }, Component::Tail { x0, mass, lambda, is_right }) |
+        (Component::Tail { x0, mass, lambda, is_right }, Component::Bin { a, b, p }) => {
+            convolve_bin_tail((*a, *b), *x0, *lambda, *is_right, p * mass, out);
+        }
+        // Opposite tails: x0 + E1 - E2 is an asymmetric Laplace, exactly two
+        // tails from the same start
+        (
+            Component::Tail { x0: x1, mass: m1, lambda: l1, is_right: r1 },
+            Component::Tail { x0: x2, mass: m2, lambda: l2, is_right: r2 },
+        ) if r1 != r2 => {
+            let (x0, p) = (x1 + x2, m1 * m2);
+            out.push(Component::Tail { x0, mass: p * l2 / (l1 + l2), lambda: *l1, is_right: *r1 });
+            out.push(Component::Tail { x0, mass: p * l1 / (l1 + l2), lambda: *l2, is_right: *r2 });
+        }
+        // Same-side tails: hypoexponential
+        (
+            Component::Tail { x0: x1, mass: m1, lambda: l1, is_right },
+            Component::Tail { x0: x2, mass: m2, lambda: l2, .. },
+        ) => {
+            convolve_same_side_tails(x1 + x2, *l1, *l2, *is_right, m1 * m2, out);
+        }
+    }
+    true
+}
+
+/// Pairwise convolution of two component lists
+fn convolve_components(comps1: &[Component], comps2: &[Component]) -> Vec<Component> {
+    convolve_with_loss(comps1, comps2).0
+}
+
+/// Pairwise convolution that also returns the total mass of the pairings
+/// `convolve_pair` could not convolve
+fn convolve_with_loss(comps1: &[Component], comps2: &[Component]) -> (Vec<Component>, f64) {
+    let mut result: Vec<Component> = Vec::with_capacity(comps1.len() * comps2.len());
+    let mut lost = 0.0;
+
+    for c1 in comps1 {
+        for c2 in comps2 {
+            if !convolve_pair(c1, c2, &mut result) {
+                lost += get_weight(c1) * get_weight(c2);
+            }
+        }
+    }
+
+    (result, lost)
+}
+
+/// Convolve two distributions
+/// Returns flat array of result components, compressed to at most
+/// `max_components` components (mass, mean and variance preserved) when
+/// given and non-zero
+#[wasm_bindgen]
+pub fn convolve_distributions(
+    dist1_data: Float64Array,
+    dist2_data: Float64Array,
+    max_components: Option<u32>,
+) -> Float64Array {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    
+    let comps1 = parse_components(&data1);
+    let comps2 = parse_components(&data2);
+    
+    let mut result = convolve_components(&comps1, &comps2);
+    if let Some(max_components) = max_components.filter(|&m| m > 0) {
+        result = compress_components(&result, max_components as usize);
+    }
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Bins of a distribution laid on a lattice of equal-width bins: the first
+/// bin's left edge, the width and the mass at each lattice offset
+///
+/// None unless every component is a bin of the same width whose left edge
+/// sits on the lattice (to within 1e-9 of the width).
+fn lattice_bins(components: &[Component]) -> Option<(f64, f64, Vec<f64>)> {
+    let mut edges = Vec::with_capacity(components.len());
+    for c in components {
+        match *c {
+            Component::Bin { a, b, p } if b > a => edges.push((a, b - a, p)),
+            _ => return None,
+        }
+    }
+    let (_, h, _) = *edges.first()?;
+    let origin = edges.iter().map(|e| e.0).fold(f64::INFINITY, f64::min);
+    let tol = 1e-9 * h;
+    let mut masses = Vec::new();
+    for (a, w, p) in edges {
+        let k = ((a - origin) / h).round();
+        if (w - h).abs() > tol || (a - origin - k * h).abs() > tol * k.max(1.0) || k >= LATTICE_MAX_POINTS as f64 {
+            return None;
+        }
+        let k = k as usize;
+        if masses.len() <= k {
+            masses.resize(k + 1, 0.0);
+        }
+        masses[k] += p;
+    }
+    Some((origin, h, masses))
+}
+
+/// FFT convolution of two distributions on lattices of the same bin width
+///
+/// The lattice masses are convolved in O(m log m) and each result sits in a
+/// bin of that width centered on the sum of the two bin centers: the exact
+/// pairwise result of convolving the first histogram with the second
+/// collapsed to its bin centers. Mass and mean are exact; the variance is
+/// short by h^2 / 12. The output is again a lattice, so calls compose.
+fn convolve_lattice_fft(comps1: &[Component], comps2: &[Component]) -> Result<Vec<Component>, String> {
+    let (o1, h, m1) = lattice_bins(comps1).ok_or("first distribution is not a lattice of equal-width bins")?;
+    let (o2, h2, m2) = lattice_bins(comps2).ok_or("second distribution is not a lattice of equal-width bins")?;
+    if (h - h2).abs() > 1e-9 * h {
+        return Err(format!("bin widths differ ({h} vs {h2})"));
+    }
+    let len = m1.len() + m2.len() - 1;
+    let n = len.next_power_of_two();
+    if n > LATTICE_MAX_POINTS {
+        return Err(format!("FFT lattice of {} points exceeds the limit of {}", n, LATTICE_MAX_POINTS));
+    }
+    let mut x: Vec<(f64, f64)> = m1.iter().map(|&p| (p, 0.0)).collect();
+    let mut y: Vec<(f64, f64)> = m2.iter().map(|&p| (p, 0.0)).collect();
+    x.resize(n, (0.0, 0.0));
+    y.resize(n, (0.0, 0.0));
+    fft(&mut x, false);
+    fft(&mut y, false);
+    for (u, v) in x.iter_mut().zip(&y) {
+        *u = complex_mul(*u, *v);
+    }
+    fft(&mut x, true);
+    let scale = total_mass(comps1) * total_mass(comps2);
+    let start = o1 + o2 + h / 2.0;
+    Ok(x[..len]
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| v.0 > FFT_ROUNDOFF * scale)
+        .map(|(k, v)| {
+            let a = start + k as f64 * h;
+            Component::Bin { a, b: a + h, p: v.0 }
+        })
+        .collect())
+}
+
+/// Convolve two distributions that are lattices of equal-width bins (as
+/// produced by `dist_rebin`) with an FFT instead of the pairwise loop
+///
+/// Results are bins of the same width; see `convolve_distributions` for
+/// the general case.
+#[wasm_bindgen]
+pub fn convolve_distributions_fft(dist1_data: Float64Array, dist2_data: Float64Array) -> Result<Float64Array, JsValue> {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    let result = convolve_lattice_fft(&parse_components(&data1), &parse_components(&data2))
+        .map_err(|e| JsValue::from_str(&e))?;
+    let serialized = serialize_components(&result);
+    Ok(Float64Array::from(serialized.as_slice()))
+}
+
+/// Distribution of the sum of `n` i.i.d. copies, by repeated squaring
+///
+/// Every intermediate product is compressed to `max_components`, so only
+/// O(log n) convolutions are needed and each stays small. Without a cap the
+/// component count would grow like |A|^n, so 0 is an error. n = 0 gives an
+/// atom at 0.
+fn convolve_power(components: &[Component], n: u32, max_components: usize) -> Result<Vec<Component>, String> {
+    if max_components == 0 {
+        return Err("max_components must be positive".to_string());
+    }
+    let mut result = vec![Component::Atom { x: 0.0, p: 1.0 }];
+    let mut power = components.to_vec();
+    let mut n = n;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = compress_components(&convolve_components(&result, &power), max_components);
+        }
+        n >>= 1;
+        if n > 0 {
+            power = compress_components(&convolve_components(&power, &power), max_components);
+        }
+    }
+    Ok(result)
+}
+
+/// Distribution of the sum of `n` i.i.d. copies of a distribution,
+/// compressed to at most `max_components` (positive) components along the
+/// way
+#[wasm_bindgen]
+pub fn dist_convolve_n(components_data: Float64Array, n: u32, max_components: u32) -> Result<Float64Array, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let result = convolve_power(&parse_components(&data), n, max_components as usize).map_err(|e| JsValue::from_str(&e))?;
+    let serialized = serialize_components(&result);
+    Ok(Float64Array::from(serialized.as_slice()))
+}
+
+/// Distribution of X - Y for independent X and Y: Y is mirrored (its right
+/// tails become left tails) and convolved with X
+fn subtract_components(comps1: &[Component], comps2: &[Component]) -> Vec<Component> {
+    convolve_components(comps1, &scale_components(comps2, -1.0))
+}
+
+/// Difference of two independent distributions, X - Y
+/// Returns flat array of result components
+#[wasm_bindgen]
+pub fn dist_subtract(dist1_data: Float64Array, dist2_data: Float64Array) -> Float64Array {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    let result = subtract_components(&parse_components(&data1), &parse_components(&data2));
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Probability mass `convolve_distributions` drops for the same inputs
+/// (pairings it cannot convolve, such as tails with a non-positive rate)
+#[wasm_bindgen]
+pub fn convolution_mass_lost(dist1_data: Float64Array, dist2_data: Float64Array) -> f64 {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    convolve_with_loss(&parse_components(&data1), &parse_components(&data2)).1
+}
+
+// ===========================================
+// Dist Operations - Phase 1 Full Rust Implementation
+// ===========================================
+
+/// Get weight of a component
+fn get_weight(c: &Component) -> f64 {
+    match c {
+        Component::Atom { p, .. } => *p,
+        Component::Bin { p, .. } => *p,
+        Component::Tail { mass, .. } => *mass,
+    }
+}
+
+/// Mean of parsed components, normalized by total mass
+fn mixture_mean(components: &[Component]) -> f64 {
+    let total_p: f64 = components.iter().map(get_weight).sum();
+    if total_p == 0.0 {
+        return 0.0;
+    }
+    
+    let mut sum = 0.0;
+    for c in components {
+        match c {
+            Component::Atom { x, p } => {
+                sum += x * p;
+            }
+            Component::Bin { a, b, p } => {
+                let center = (a + b) / 2.0;
+                sum += center * p;
+            }
+            Component::Tail { x0, mass, lambda, is_right } => {
+                // Mean of exponential part: x0 ± 1/lambda
+                let exp_mean = if *is_right { x0 + 1.0 / lambda } else { x0 - 1.0 / lambda };
+                sum += exp_mean * mass;
+            }
+        }
+    }
+    sum / total_p
+}
+
+/// Variance of parsed components, normalized by total mass
+fn mixture_variance(components: &[Component]) -> f64 {
+    let total_p: f64 = components.iter().map(get_weight).sum();
+    if total_p == 0.0 {
+        return 0.0;
+    }
+    
+    let mean = mixture_mean(components);
+    
+    let mut sum_sq = 0.0;
+    for c in components {
+        match c {
+            Component::Atom { x, p } => {
+                sum_sq += (x - mean).powi(2) * p;
+            }
+            Component::Bin { a, b, p } => {
+                let center = (a + b) / 2.0;
+                let width = b - a;
+                // Variance = (diff from mean)^2 + internal variance
+                let internal_var = width * width / 12.0;
+                sum_sq += ((center - mean).powi(2) + internal_var) * p;
+            }
+            Component::Tail { x0, mass, lambda, is_right } => {
+                let exp_mean = if *is_right { x0 + 1.0 / lambda } else { x0 - 1.0 / lambda };
+                let exp_var = 1.0 / (lambda * lambda);
+                sum_sq += ((exp_mean - mean).powi(2) + exp_var) * mass;
+            }
+        }
+    }
+    sum_sq / total_p
+}
+
+/// Calculate mean of distribution
+#[wasm_bindgen]
+pub fn dist_mean(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_mean(&components)
+}
+
+/// Calculate variance of distribution
+#[wasm_bindgen]
+pub fn dist_variance(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_variance(&components)
+}
+
+/// Calculate standard deviation
+#[wasm_bindgen]
+pub fn dist_std(components_data: Float64Array) -> f64 {
+    dist_variance(components_data).sqrt()
+}
+
+/// P(X > x) of parsed components, normalized by total mass
+fn mixture_prob_gt(components: &[Component], x: f64) -> f64 {
+    let total_p: f64 = components.iter().map(get_weight).sum();
+    if total_p == 0.0 {
+        return 0.0;
+    }
+    
+    let mut prob = 0.0;
+    for c in components {
+        match c {
+            Component::Atom { x: ax, p } => {
+                if *ax > x {
+                    prob += p;
+                }
+            }
+            Component::Bin { a, b, p } => {
+                if *a > x {
+                    prob += p;
+                } else if *b > x {
+                    // Partial overlap
+                    let fraction = (b - x) / (b - a);
+                    prob += p * fraction;
+                }
+            }
+            Component::Tail { x0, mass, lambda, is_right } => {
+                if *is_right {
+                    // Right tail: P(X > x) where X ~ x0 + Exp(lambda)
+                    if x < *x0 {
+                        prob += mass;
+                    } else {
+                        prob += mass * (-(x - x0) * lambda).exp();
+                    }
+                } else {
+                    // Left tail: P(X > x) where X ~ x0 - Exp(lambda)
+                    if x >= *x0 {
+                        // All mass is <= x0, so P(X > x) = 0
+                    } else {
+                        // P(x0 - Exp > x) = P(Exp < x0 - x) = 1 - exp(-lambda*(x0-x))
+                        prob += mass * (1.0 - (-(x0 - x) * lambda).exp());
+                    }
+                }
+            }
+        }
+    }
+    prob / total_p
+}
+
+/// Calculate P(X > x) - probability of exceeding x
+///
+/// Strict inequality: an atom sitting exactly at x is not counted.
+#[wasm_bindgen]
+pub fn dist_prob_gt(components_data: Float64Array, x: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_prob_gt(&components, x)
+}
+
+/// Mix two component lists: (1-p)*comps1 + p*comps2
+fn mix_components(comps1: &[Component], comps2: &[Component], p: f64) -> Vec<Component> {
+    let mut result: Vec<Component> = Vec::with_capacity(comps1.len() + comps2.len());
+    
+    // Scale first distribution by (1-p)
+    for c in comps1 {
+        result.push(scale_component(c, 1.0 - p));
+    }
+    
+    // Scale second distribution by p
+    for c in comps2 {
+        result.push(scale_component(c, p));
+    }
+    
+    result
+}
+
+/// Mix two distributions: result = (1-p)*dist1 + p*dist2
+#[wasm_bindgen]
+pub fn dist_mix(
+    dist1_data: Float64Array,
+    dist2_data: Float64Array,
+    p: f64,
+) -> Float64Array {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    
+    let comps1 = parse_components(&data1);
+    let comps2 = parse_components(&data2);
+    
+    let result = mix_components(&comps1, &comps2, p);
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Scale a component's probability
+fn scale_component(c: &Component, factor: f64) -> Component {
+    match c {
+        Component::Atom { x, p } => Component::Atom { x: *x, p: p * factor },
+        Component::Bin { a, b, p } => Component::Bin { a: *a, b: *b, p: p * factor },
+        Component::Tail { x0, mass, lambda, is_right } => Component::Tail {
+            x0: *x0,
+            mass: mass * factor,
+            lambda: *lambda,
+            is_right: *is_right,
+        },
+    }
+}
+
+/// Scale component values by k (negative k mirrors bins and flips tails)
+fn scale_components(components: &[Component], k: f64) -> Vec<Component> {
+    let mut result: Vec<Component> = Vec::with_capacity(components.len());
+    
+    for c in components.iter().cloned() {
+        match c {
+            Component::Atom { x, p } => {
+                result.push(Component::Atom { x: x * k, p });
+            }
+            Component::Bin { a, b, p } => {
+                if k >= 0.0 {
+                    result.push(Component::Bin { a: a * k, b: b * k, p });
+                } else {
+                    result.push(Component::Bin { a: b * k, b: a * k, p });
+                }
+            }
+            Component::Tail { x0, mass, lambda, is_right } => {
+                if k >= 0.0 {
+                    result.push(Component::Tail {
+                        x0: x0 * k,
+                        mass,
+                        lambda: lambda / k.abs(),
+                        is_right,
+                    });
+                } else {
+                    result.push(Component::Tail {
+                        x0: x0 * k,
+                        mass,
+                        lambda: lambda / k.abs(),
+                        is_right: !is_right,
+                    });
+                }
+            }
+        }
+    }
+    
+    result
+}
+
+/// Shift a component's values by c
+fn shift_component(c: &Component, shift: f64) -> Component {
+    match c {
+        Component::Atom { x, p } => Component::Atom { x: x + shift, p: *p },
+        Component::Bin { a, b, p } => Component::Bin { a: a + shift, b: b + shift, p: *p },
+        Component::Tail { x0, mass, lambda, is_right } => Component::Tail {
+            x0: x0 + shift,
+            mass: *mass,
+            lambda: *lambda,
+            is_right: *is_right,
+        },
+    }
+}
+
+/// Scale distribution values by k
+#[wasm_bindgen]
+pub fn dist_scale(components_data: Float64Array, k: f64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let result = scale_components(&components, k);
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Distribution of X + c: atoms, bin endpoints and tail anchors move by c
+#[wasm_bindgen]
+pub fn dist_shift(components_data: Float64Array, c: f64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let result: Vec<Component> = parse_components(&data).iter().map(|comp| shift_component(comp, c)).collect();
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Reflection about zero, -X: bins swap endpoints and tails change side
+fn negate_components(components: &[Component]) -> Vec<Component> {
+    scale_components(components, -1.0)
+}
+
+/// Distribution of -X
+#[wasm_bindgen]
+pub fn dist_negate(components_data: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let result = negate_components(&parse_components(&data));
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Distribution of |X|
+///
+/// The negative side is reflected onto the positive axis: bins straddling
+/// zero are split there, a left tail anchored below zero becomes a right
+/// tail, and one anchored above zero becomes bins on (0, x0] plus a right
+/// tail from 0. Atoms at zero stay put, and pieces that land on top of each
+/// other (atoms at +-x, mirrored bins) are combined.
+fn abs_components(components: &[Component]) -> Vec<Component> {
+    let mut folded: Vec<Component> = components
+        .iter()
+        .filter(|c| match c {
+            Component::Atom { x, .. } => *x == 0.0,
+            Component::Bin { a, b, .. } => *a == 0.0 && *b == 0.0,
+            Component::Tail { .. } => false,
+        })
+        .cloned()
+        .collect();
+    folded.extend(restrict(components, 0.0, f64::INFINITY));
+    folded.extend(restrict(&negate_components(components), 0.0, f64::INFINITY));
+    simplify_components(&folded, 0.0)
+}
+
+/// Distribution of |X|, folding negative mass onto the positive axis
+#[wasm_bindgen]
+pub fn dist_abs(components_data: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let result = abs_components(&parse_components(&data));
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+// ===========================================
+// Dist Solvers
+// ===========================================
+
+/// Find the weight p in [0, 1] for `dist_mix(dist1, dist2, p)` that minimizes
+/// the variance of the resulting mixture.
+///
+/// With means m1, m2 and variances v1, v2 the mixture variance is
+/// `(1-p)*v1 + p*v2 + p*(1-p)*(m2-m1)^2`, a quadratic in p whose leading
+/// coefficient `-(m2-m1)^2` is never positive. The minimum over [0, 1] is
+/// therefore always at an endpoint: whichever input has the smaller variance.
+/// When both endpoints tie (e.g. identical inputs) every p is a minimizer and
+/// 0.5 is returned as the flagged "any value" answer.
+#[wasm_bindgen]
+pub fn solve_min_variance_mix(dist1_data: Float64Array, dist2_data: Float64Array) -> f64 {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    
+    let comps1 = parse_components(&data1);
+    let comps2 = parse_components(&data2);
+    
+    min_variance_mix_weight(&comps1, &comps2)
+}
+
+fn min_variance_mix_weight(comps1: &[Component], comps2: &[Component]) -> f64 {
+    let m1 = mixture_mean(comps1);
+    let m2 = mixture_mean(comps2);
+    let v1 = mixture_variance(comps1);
+    let v2 = mixture_variance(comps2);
+    let d2 = (m2 - m1).powi(2);
+    
+    let var_at = |p: f64| (1.0 - p) * v1 + p * v2 + p * (1.0 - p) * d2;
+    let (at0, at1) = (var_at(0.0), var_at(1.0));
+    
+    let tol = 1e-12 * at0.abs().max(at1.abs()).max(1.0);
+    if (at0 - at1).abs() <= tol && d2 <= tol {
+        0.5
+    } else if at0 <= at1 {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// ===========================================
+// CDF / Quantile
+// ===========================================
+
+/// Total probability mass of components
+fn total_mass(components: &[Component]) -> f64 {
+    components.iter().map(get_weight).sum()
+}
+
+/// Unnormalized mass of a component with value <= x
+fn component_cdf(c: &Component, x: f64) -> f64 {
+    match c {
+        Component::Atom { x: ax, p } => {
+            if *ax <= x { *p } else { 0.0 }
+        }
+        Component::Bin { a, b, p } => {
+            if x >= *b {
+                *p
+            } else if x <= *a {
+                0.0
+            } else {
+                p * (x - a) / (b - a)
+            }
+        }
+        Component::Tail { x0, mass, lambda, is_right } => {
+            if *is_right {
+                if x <= *x0 { 0.0 } else { -mass * (-(x - x0) * lambda).exp_m1() }
+            } else if x >= *x0 {
+                *mass
+            } else {
+                mass * (-(x0 - x) * lambda).exp()
+            }
+        }
+    }
+}
+
+/// Unnormalized mass of a component with value < x (left limit of the CDF)
+fn component_cdf_left(c: &Component, x: f64) -> f64 {
+    match c {
+        Component::Atom { x: ax, p } => {
+            if *ax < x { *p } else { 0.0 }
+        }
+        Component::Bin { a, b, p } if a == b => {
+            if *a < x { *p } else { 0.0 }
+        }
+        _ => component_cdf(c, x),
+    }
+}
+
+/// Sorted, deduplicated x positions where the mixture CDF changes shape
+/// (atoms, bin endpoints, tail anchors)
+fn breakpoints(components: &[Component]) -> Vec<f64> {
+    let mut points: Vec<f64> = Vec::with_capacity(components.len() * 2);
+    for c in components {
+        match c {
+            Component::Atom { x, .. } => points.push(*x),
+            Component::Bin { a, b, .. } => {
+                points.push(*a);
+                points.push(*b);
+            }
+            Component::Tail { x0, .. } => points.push(*x0),
+        }
+    }
+    points.retain(|x| x.is_finite());
+    points.sort_by(|a, b| a.total_cmp(b));
+    points.dedup();
+    points
+}
+
+/// Smallest and largest values carrying mass (infinite on a tail's side)
+fn support_bounds(components: &[Component]) -> (f64, f64) {
+    let mut lo = f64::INFINITY;
+    let mut hi = f64::NEG_INFINITY;
+    for c in components.iter().filter(|c| get_weight(c) > 0.0) {
+        let (l, h) = match c {
+            Component::Atom { x, .. } => (*x, *x),
+            Component::Bin { a, b, .. } => (*a, *b),
+            Component::Tail { x0, is_right, .. } => {
+                if *is_right { (*x0, f64::INFINITY) } else { (f64::NEG_INFINITY, *x0) }
+            }
+        };
+        lo = lo.min(l);
+        hi = hi.max(h);
+    }
+    (lo, hi)
+}
+
+/// Whether any tail has density inside the open interval between two
+/// consecutive breakpoints
+fn tails_active(components: &[Component], lo: f64, hi: f64) -> bool {
+    components.iter().any(|c| match c {
+        Component::Tail { x0, is_right, .. } => {
+            if *is_right { *x0 <= lo } else { *x0 >= hi }
+        }
+        _ => false,
+    })
+}
+
+/// Invert a continuous, non-decreasing CDF on (lo, hi) by bisection,
+/// expanding infinite ends until they bracket the target
+fn invert_cdf(cdf: impl Fn(f64) -> f64, target: f64, mut lo: f64, mut hi: f64) -> f64 {
+    if lo == f64::NEG_INFINITY {
+        let mut step = hi.abs().max(1.0);
+        lo = hi - step;
+        while cdf(lo) >= target && lo.is_finite() {
+            step *= 2.0;
+            lo = hi - step;
+        }
+    }
+    if hi == f64::INFINITY {
+        let mut step = lo.abs().max(1.0);
+        hi = lo + step;
+        while cdf(hi) < target && hi.is_finite() {
+            step *= 2.0;
+            hi = lo + step;
+        }
+    }
+    if !lo.is_finite() || !hi.is_finite() {
+        return if lo.is_finite() { hi } else { lo };
+    }
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        if cdf(mid) >= target {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    hi
+}
+
+/// Location of a quantile level relative to the breakpoints
+enum QuantileBracket {
+    /// Answer is known exactly (atom jump, support bound, or invalid level)
+    Exact(f64),
+    /// Answer lies where the CDF is continuous, between lo and hi
+    Interval { lo: f64, hi: f64, target: f64 },
+}
+
+fn quantile_bracket(components: &[Component], q: f64) -> QuantileBracket {
+    let total = total_mass(components);
+    if total <= 0.0 || !(0.0..=1.0).contains(&q) {
+        return QuantileBracket::Exact(f64::NAN);
+    }
+    let (lo_bound, hi_bound) = support_bounds(components);
+    if q == 0.0 {
+        return QuantileBracket::Exact(lo_bound);
+    }
+    if q == 1.0 {
+        return QuantileBracket::Exact(hi_bound);
+    }
+
+    let target = q * total;
+    let points = breakpoints(components);
+    let k = points.iter().position(|&x| mass_at_or_below(components, x) >= target);
+    match k {
+        Some(k) => {
+            let hi = points[k];
+            if mass_below(components, hi) < target {
+                // Jump at hi (atom) covers the target
+                return QuantileBracket::Exact(hi);
+            }
+            let lo = if k == 0 { f64::NEG_INFINITY } else { points[k - 1] };
+            QuantileBracket::Interval { lo, hi, target }
+        }
+        None => QuantileBracket::Interval {
+            lo: points.last().copied().unwrap_or(f64::NEG_INFINITY),
+            hi: f64::INFINITY,
+            target,
+        },
+    }
+}
+
+/// Unnormalized mass with value <= x
+fn mass_at_or_below(components: &[Component], x: f64) -> f64 {
+    components.iter().map(|c| component_cdf(c, x)).sum()
+}
+
+/// Unnormalized mass with value < x
+fn mass_below(components: &[Component], x: f64) -> f64 {
+    components.iter().map(|c| component_cdf_left(c, x)).sum()
+}
+
+/// Linear interpolation of the CDF between two finite breakpoints
+fn interpolate_bracket(components: &[Component], lo: f64, hi: f64, target: f64) -> f64 {
+    let f_lo = mass_at_or_below(components, lo);
+    let f_hi = mass_below(components, hi);
+    if f_hi > f_lo {
+        lo + (target - f_lo) / (f_hi - f_lo) * (hi - lo)
+    } else {
+        hi
+    }
+}
+
+/// Quantile Q(q) = inf { x : F(x) >= q } of the normalized mixture
+///
+/// Between consecutive breakpoints the CDF is linear unless a tail has
+/// density there, so the inversion is exact by interpolation in the common
+/// case and falls back to bisection only inside tail regions.
+fn mixture_quantile(components: &[Component], q: f64) -> f64 {
+    match quantile_bracket(components, q) {
+        QuantileBracket::Exact(x) => x,
+        QuantileBracket::Interval { lo, hi, target } => {
+            if lo.is_finite() && hi.is_finite() && !tails_active(components, lo, hi) {
+                interpolate_bracket(components, lo, hi, target)
+            } else {
+                invert_cdf(|x| mass_at_or_below(components, x), target, lo, hi)
+            }
+        }
+    }
+}
+
+/// Unnormalized first moment of a component restricted to the open interval (lo, hi)
+fn component_partial_expectation(c: &Component, lo: f64, hi: f64) -> f64 {
+    match c {
+        Component::Atom { x, p } => {
+            if lo < *x && *x < hi { x * p } else { 0.0 }
+        }
+        Component::Bin { a, b, p } if a == b => {
+            if lo < *a && *a < hi { a * p } else { 0.0 }
+        }
+        Component::Bin { a, b, p } => {
+            let l = a.max(lo);
+            let u = b.min(hi);
+            if u <= l {
+                0.0
+            } else {
+                p / (b - a) * (u * u - l * l) / 2.0
+            }
+        }
+        Component::Tail { x0, mass, lambda, is_right } => {
+            if *is_right {
+                // Antiderivative of x*f(x): -(x + 1/lambda) * e^{-lambda (x - x0)}
+                let g = |x: f64| {
+                    if x == f64::INFINITY { 0.0 } else { -mass * (x + 1.0 / lambda) * (-(x - x0) * lambda).exp() }
+                };
+                let l = lo.max(*x0);
+                if hi <= l { 0.0 } else { g(hi) - g(l) }
+            } else {
+                // Antiderivative of x*f(x): (x - 1/lambda) * e^{-lambda (x0 - x)}
+                let h = |x: f64| {
+                    if x == f64::NEG_INFINITY { 0.0 } else { mass * (x - 1.0 / lambda) * (-(x0 - x) * lambda).exp() }
+                };
+                let u = hi.min(*x0);
+                if u <= lo { 0.0 } else { h(u) - h(lo) }
+            }
+        }
+    }
+}
+
+/// Integral of the quantile function over [u1, u2], i.e. the normalized
+/// first moment of the probability slice between two quantile levels
+/// (atoms straddling either edge are split)
+fn slice_expectation(components: &[Component], u1: f64, u2: f64) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 || u2 <= u1 {
+        return 0.0;
+    }
+    let x1 = mixture_quantile(components, u1);
+    let x2 = mixture_quantile(components, u2);
+    if x1 == x2 {
+        return (u2 - u1) * x1;
+    }
+
+    let inner: f64 = components.iter().map(|c| component_partial_expectation(c, x1, x2)).sum();
+    let mut sum = inner / total;
+
+    let lower_mass = mass_at_or_below(components, x1) / total - u1;
+    if lower_mass > 0.0 {
+        sum += x1 * lower_mass;
+    }
+    let upper_mass = u2 - mass_below(components, x2) / total;
+    if upper_mass > 0.0 {
+        sum += x2 * upper_mass;
+    }
+    sum
+}
+
+/// P(X <= x) of the normalized mixture
+fn mixture_cdf(components: &[Component], x: f64) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    mass_at_or_below(components, x) / total
+}
+
+/// P(X < x) of the normalized mixture
+fn mixture_prob_lt(components: &[Component], x: f64) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    mass_below(components, x) / total
+}
+
+/// CDF P(X <= x)
+///
+/// Ties: an atom at exactly x is included here and excluded from both
+/// `dist_prob_lt` and `dist_prob_gt`, so `dist_cdf + dist_prob_gt = 1` and
+/// `dist_prob_lt + P(X = x) = dist_cdf`.
+#[wasm_bindgen]
+pub fn dist_cdf(components_data: Float64Array, x: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_cdf(&components, x)
+}
+
+/// P(X < x) - strict, an atom at exactly x is not counted
+#[wasm_bindgen]
+pub fn dist_prob_lt(components_data: Float64Array, x: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_prob_lt(&components, x)
+}
+
+/// Unnormalized mass of a component in (lo, hi], computed directly so small
+/// intervals do not suffer from cancellation between two CDF values
+fn component_mass_between(c: &Component, lo: f64, hi: f64) -> f64 {
+    if hi <= lo {
+        return 0.0;
+    }
+    match c {
+        Component::Atom { x, p } => {
+            if lo < *x && *x <= hi { *p } else { 0.0 }
+        }
+        Component::Bin { a, b, p } if a == b => {
+            if lo < *a && *a <= hi { *p } else { 0.0 }
+        }
+        Component::Bin { a, b, p } => {
+            let l = a.max(lo);
+            let u = b.min(hi);
+            if u > l { p * (u - l) / (b - a) } else { 0.0 }
+        }
+        Component::Tail { x0, mass, lambda, is_right } => {
+            // Survival to the near end times the fraction lost over the width
+            let (l, u) = if *is_right { (lo.max(*x0), hi) } else { (lo, hi.min(*x0)) };
+            if u <= l {
+                return 0.0;
+            }
+            let near = if *is_right { l - x0 } else { x0 - u };
+            mass * (-near * lambda).exp() * -(-(u - l) * lambda).exp_m1()
+        }
+    }
+}
+
+/// P(lo < X <= hi) of the normalized mixture
+fn mixture_prob_between(components: &[Component], lo: f64, hi: f64) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    components.iter().map(|c| component_mass_between(c, lo, hi)).sum::<f64>() / total
+}
+
+/// P(a < X <= b), computed in one pass over the components
+#[wasm_bindgen]
+pub fn dist_prob_between(components_data: Float64Array, a: f64, b: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_prob_between(&components, a, b)
+}
+
+/// Quantile (inverse CDF) at level p in [0, 1]
+///
+/// Uses the generalized inverse inf { x : P(X <= x) >= p }, so a level that
+/// falls inside an atom's jump returns the atom. p = 0 / p = 1 return the
+/// support bounds (infinite on a tail's side); other levels outside [0, 1]
+/// give NaN.
+#[wasm_bindgen]
+pub fn dist_quantile(components_data: Float64Array, p: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_quantile(&components, p)
+}
+
+/// Median as the 0.5 quantile. When the CDF is flat at 0.5 this picks the
+/// left end of the flat stretch, consistent with `dist_quantile`.
+#[wasm_bindgen]
+pub fn dist_median(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_quantile(&components, 0.5)
+}
+
+/// Interval holding all but `epsilon` of the mass. Bounded sides use the
+/// exact support bound; only sides extended by a tail are cut at a quantile,
+/// sharing epsilon when both are unbounded.
+fn support_interval(components: &[Component], epsilon: f64) -> (f64, f64) {
+    let (lo, hi) = support_bounds(components);
+    if epsilon <= 0.0 || total_mass(components) <= 0.0 {
+        return (lo, hi);
+    }
+    let eps = epsilon.min(1.0);
+    let open_sides = (lo.is_infinite() as u32 + hi.is_infinite() as u32) as f64;
+    let share = if open_sides > 0.0 { eps / open_sides } else { 0.0 };
+    let lo = if lo.is_infinite() { mixture_quantile(components, share) } else { lo };
+    let hi = if hi.is_infinite() { mixture_quantile(components, 1.0 - share) } else { hi };
+    (lo, hi)
+}
+
+/// [min, max] such that all but `epsilon` of the mass lies inside; exact
+/// (and independent of epsilon) when the distribution has no tails
+#[wasm_bindgen]
+pub fn dist_support(components_data: Float64Array, epsilon: f64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let (lo, hi) = support_interval(&components, epsilon);
+    Float64Array::from([lo, hi].as_slice())
+}
+
+/// Unnormalized density of the continuous part of a component at x
+fn component_pdf(c: &Component, x: f64) -> f64 {
+    match c {
+        Component::Atom { .. } => 0.0,
+        Component::Bin { a, b, p } => {
+            if a < b && *a <= x && x < *b { p / (b - a) } else { 0.0 }
+        }
+        Component::Tail { x0, mass, lambda, is_right } => {
+            if *is_right {
+                if x >= *x0 { mass * lambda * (-(x - x0) * lambda).exp() } else { 0.0 }
+            } else if x < *x0 {
+                mass * lambda * (-(x0 - x) * lambda).exp()
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+/// Unnormalized density of the continuous part of the mixture at x
+fn mass_density(components: &[Component], x: f64) -> f64 {
+    components.iter().map(|c| component_pdf(c, x)).sum()
+}
+
+/// Starting point inside a bracket where exactly one tail has density:
+/// the tail's closed-form inverse applied to its share of the bracket's
+/// mass, which is exact when nothing else has density there
+fn tail_seed(components: &[Component], lo: f64, hi: f64, target: f64) -> Option<f64> {
+    let mut active = components.iter().filter(|c| match c {
+        Component::Tail { x0, is_right, .. } => if *is_right { hi > *x0 } else { lo < *x0 },
+        _ => false,
+    });
+    let tail = active.next()?;
+    if active.next().is_some() {
+        return None;
+    }
+    let Component::Tail { x0, mass, lambda, is_right } = *tail else {
+        return None;
+    };
+    let bracket = mass_below(components, hi) - mass_at_or_below(components, lo);
+    let tail_lo = component_cdf(tail, lo);
+    let tail_share = component_cdf_left(tail, hi) - tail_lo;
+    if bracket <= 0.0 || tail_share <= 0.0 {
+        return None;
+    }
+    let c = tail_lo + (target - mass_at_or_below(components, lo)) * tail_share / bracket;
+    let x = if is_right { x0 - (1.0 - c / mass).ln() / lambda } else { x0 + (c / mass).ln() / lambda };
+    x.is_finite().then(|| x.clamp(lo, hi))
+}
+
+/// Quantile refined by Newton steps on the CDF, using the density as its
+/// derivative
+///
+/// Starts from the piecewise-linear estimate (linear interpolation of the CDF
+/// between breakpoints), or inside a tail from the tail's closed-form inverse
+/// (see `tail_seed`), and keeps every iterate inside the bracketing interval.
+fn refined_quantile(components: &[Component], q: f64, newton_iters: u32) -> f64 {
+    let (lo, hi, target) = match quantile_bracket(components, q) {
+        QuantileBracket::Exact(x) => return x,
+        QuantileBracket::Interval { lo, hi, target } => (lo, hi, target),
+    };
+    let seed = if tails_active(components, lo, hi) { tail_seed(components, lo, hi, target) } else { None };
+    if (!lo.is_finite() || !hi.is_finite()) && seed.is_none() {
+        return invert_cdf(|x| mass_at_or_below(components, x), target, lo, hi);
+    }
+
+    let mut x = seed.unwrap_or_else(|| interpolate_bracket(components, lo, hi, target));
+    for _ in 0..newton_iters {
+        let f = mass_density(components, x);
+        if f <= 0.0 || !f.is_finite() {
+            break;
+        }
+        let next = (x - (mass_at_or_below(components, x) - target) / f).clamp(lo, hi);
+        if next == x {
+            break;
+        }
+        x = next;
+    }
+    x
+}
+
+/// Quantile with `newton_iters` Newton refinement steps on top of the
+/// piecewise-linear estimate
+#[wasm_bindgen]
+pub fn dist_quantile_refined(components_data: Float64Array, q: f64, newton_iters: u32) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    refined_quantile(&components, q, newton_iters)
+}
+
+/// Number of equal-mass bins used for a tail segment bounded on both sides
+const TAIL_SEGMENT_BINS: usize = 8;
+
+/// Append the part of a component lying in (lo, hi]
+///
+/// Mass is kept in absolute units. A tail cut only on its decaying side stays
+/// an exact tail (the exponential is memoryless); a tail segment bounded on
+/// both sides is replaced by equal-mass bins.
+fn restrict_component(c: &Component, lo: f64, hi: f64, out: &mut Vec<Component>) {
+    match c {
+        Component::Atom { x, .. } => {
+            if lo < *x && *x <= hi {
+                out.push(c.clone());
+            }
+        }
+        Component::Bin { a, b, .. } if a == b => {
+            if lo < *a && *a <= hi {
+                out.push(c.clone());
+            }
+        }
+        Component::Bin { a, b, p } => {
+            let l = a.max(lo);
+            let u = b.min(hi);
+            if u > l {
+                out.push(Component::Bin { a: l, b: u, p: p * (u - l) / (b - a) });
+            }
+        }
+        Component::Tail { x0, mass, lambda, is_right } => {
+            // Survival beyond distance d from the anchor is e^{-lambda d} either way
+            let (l, u) = if *is_right { (lo.max(*x0), hi) } else { (lo, hi.min(*x0)) };
+            if u <= l {
+                return;
+            }
+            if *is_right && u == f64::INFINITY {
+                out.push(Component::Tail { x0: l, mass: mass * (-(l - x0) * lambda).exp(), lambda: *lambda, is_right: true });
+                return;
+            }
+            if !*is_right && l == f64::NEG_INFINITY {
+                out.push(Component::Tail { x0: u, mass: mass * (-(x0 - u) * lambda).exp(), lambda: *lambda, is_right: false });
+                return;
+            }
+
+            // Distances from the anchor to the near and far ends of the segment
+            let (near, far) = if *is_right { (l - x0, u - x0) } else { (x0 - u, x0 - l) };
+            let s_near = (-near * lambda).exp();
+            let s_far = (-far * lambda).exp();
+            let k = TAIL_SEGMENT_BINS as f64;
+            let dist_at = |i: usize| {
+                if i == TAIL_SEGMENT_BINS { far } else { -(s_near - i as f64 / k * (s_near - s_far)).ln() / lambda }
+            };
+            let seg_mass = mass * (s_near - s_far) / k;
+            for i in 0..TAIL_SEGMENT_BINS {
+                let (d1, d2) = (dist_at(i), dist_at(i + 1));
+                let (a, b) = if *is_right { (x0 + d1, x0 + d2) } else { (x0 - d2, x0 - d1) };
+                out.push(Component::Bin { a, b, p: seg_mass });
+            }
+        }
+    }
+}
+
+/// Components restricted to the interval (lo, hi]
+fn restrict(components: &[Component], lo: f64, hi: f64) -> Vec<Component> {
+    let mut out = Vec::with_capacity(components.len());
+    for c in components {
+        restrict_component(c, lo, hi, &mut out);
+    }
+    out
+}
+
+// ===========================================
+// Discretization
+// ===========================================
+
+/// Rasterize into n bins of equal probability mass
+///
+/// Inner edges sit at the i/n quantiles. An outer edge on an unbounded tail
+/// is placed so the outermost bin keeps the mean of its probability slice.
+fn equal_mass_bins(components: &[Component], n: usize) -> Vec<Component> {
+    let total = total_mass(components);
+    if n == 0 || total <= 0.0 {
+        return vec![];
+    }
+    let nf = n as f64;
+    let mut edges: Vec<f64> = (0..=n).map(|i| mixture_quantile(components, i as f64 / nf)).collect();
+
+    if n == 1 && !edges[0].is_finite() && !edges[1].is_finite() {
+        let mean = mixture_mean(components);
+        let half = (3.0 * mixture_variance(components)).sqrt();
+        edges[0] = mean - half;
+        edges[1] = mean + half;
+    }
+    if !edges[0].is_finite() {
+        let slice_mean = slice_expectation(components, 0.0, 1.0 / nf) * nf;
+        edges[0] = 2.0 * slice_mean - edges[1];
+    }
+    if !edges[n].is_finite() {
+        let slice_mean = slice_expectation(components, (nf - 1.0) / nf, 1.0) * nf;
+        edges[n] = 2.0 * slice_mean - edges[n - 1];
+    }
+
+    edges
+        .windows(2)
+        .map(|w| Component::Bin { a: w[0], b: w[1], p: total / nf })
+        .collect()
+}
+
+/// Discretize into n atoms of equal mass, each placed at the mean of its
+/// probability slice so the overall mean is preserved
+fn equal_mass_atoms(components: &[Component], n: usize) -> Vec<Component> {
+    let total = total_mass(components);
+    if n == 0 || total <= 0.0 {
+        return vec![];
+    }
+    let nf = n as f64;
+    (0..n)
+        .map(|i| {
+            let u1 = i as f64 / nf;
+            let u2 = (i + 1) as f64 / nf;
+            Component::Atom { x: slice_expectation(components, u1, u2) * nf, p: total / nf }
+        })
+        .collect()
+}
+
+/// Spread weighted points onto n evenly spaced lattice points between their
+/// extremes, splitting each point's weight between its two neighbours so
+/// total weight and mean are preserved. Returns nothing when the total
+/// weight is not positive and finite.
+fn project_to_lattice(points: &[(f64, f64)], n: usize) -> Vec<(f64, f64)> {
+    let lo = points.iter().map(|pt| pt.0).fold(f64::INFINITY, f64::min);
+    let hi = points.iter().map(|pt| pt.0).fold(f64::NEG_INFINITY, f64::max);
+    let w: f64 = points.iter().map(|pt| pt.1).sum();
+    if points.is_empty() || n == 0 || !(w.is_finite() && w > 0.0) {
+        return vec![];
+    }
+    if n == 1 || hi <= lo {
+        let mean = points.iter().map(|pt| pt.0 * pt.1).sum::<f64>() / w;
+        return vec![(mean, w)];
+    }
+
+    let h = (hi - lo) / (n - 1) as f64;
+    let mut weights = vec![0.0; n];
+    for &(x, w) in points {
+        let t = (x - lo) / h;
+        let i = (t.floor() as usize).min(n - 2);
+        let frac = (t - i as f64).clamp(0.0, 1.0);
+        weights[i] += w * (1.0 - frac);
+        weights[i + 1] += w * frac;
+    }
+    weights
+        .into_iter()
+        .enumerate()
+        .filter(|(_, w)| *w > 0.0)
+        .map(|(i, w)| (lo + i as f64 * h, w))
+        .collect()
+}
+
+/// Stratified inverse-transform scenarios: one draw from each of the n
+/// equal-probability strata (i + U)/n, so the set spans the whole distribution
+fn stratified_scenarios(components: &[Component], n: usize, rng: &mut impl Rng) -> Vec<f64> {
+    let nf = n as f64;
+    (0..n)
+        .map(|i| {
+            let u: f64 = rng.sample(Open01);
+            mixture_quantile(components, (i as f64 + u) / nf)
+        })
+        .collect()
+}
+
+/// Equally-weighted scenario set for scenario-based optimization, drawn by
+/// stratified inverse-transform sampling (reproducible from `seed`)
+#[wasm_bindgen]
+pub fn dist_scenarios(components_data: Float64Array, n_scenarios: u32, seed: u64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let mut rng = StdRng::seed_from_u64(seed);
+    let scenarios = stratified_scenarios(&components, n_scenarios as usize, &mut rng);
+    Float64Array::from(scenarios.as_slice())
+}
+
+/// Discretize into n bins of equal mass (1/n of the total each) with edges
+/// at the i/n quantiles, giving finer resolution where mass concentrates
+#[wasm_bindgen]
+pub fn dist_to_equal_mass_bins(components_data: Float64Array, n: u32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let result = equal_mass_bins(&components, n as usize);
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+// ===========================================
+// Growth Transforms
+// ===========================================
+
+/// Value after deterministic compounding: X * (1 + rate)^periods
+///
+/// A rate at or below -100% wipes the value out after the first period,
+/// leaving an atom at 0; a non-finite rate or growth factor is an error.
+fn compound_growth(components: &[Component], rate: f64, periods: u32) -> Result<Vec<Component>, String> {
+    if !rate.is_finite() {
+        return Err(format!("growth rate must be finite, got {}", rate));
+    }
+    if periods == 0 {
+        return Ok(components.to_vec());
+    }
+    if rate <= -1.0 {
+        return Ok(vec![Component::Atom { x: 0.0, p: total_mass(components) }]);
+    }
+    let factor = (1.0 + rate).powi(periods as i32);
+    if !(factor.is_finite() && factor > 0.0) {
+        return Err(format!("growth factor (1 + {})^{} is out of range", rate, periods));
+    }
+    Ok(scale_components(components, factor))
+}
+
+/// Value after deterministic compounding: X * (1 + rate)^periods
+#[wasm_bindgen]
+pub fn dist_compound_growth(components_data: Float64Array, rate: f64, periods: u32) -> Result<Float64Array, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let result = compound_growth(&components, rate, periods).map_err(|e| JsValue::from_str(&e))?;
+    
+    let serialized = serialize_components(&result);
+    Ok(Float64Array::from(serialized.as_slice()))
+}
+
+/// Value after compounding with an i.i.d. random rate each period:
+/// X * prod_t (1 + R_t)
+///
+/// The rate distribution is discretized into `grid` equal-mass atoms, their
+/// log-growth is convolved `periods` times on a `grid`-point lattice, and the
+/// value distribution is scaled by every resulting growth factor. Rates at
+/// or below -100% wipe the value out and end up as an atom at 0.
+fn compound_growth_stochastic(
+    value: &[Component],
+    rate: &[Component],
+    periods: u32,
+    grid: usize,
+) -> Vec<Component> {
+    let rate_atoms = equal_mass_atoms(rate, grid.max(1));
+    let rate_total = total_mass(rate);
+    if rate_atoms.is_empty() {
+        return vec![];
+    }
+
+    let mut log_steps: Vec<(f64, f64)> = Vec::with_capacity(rate_atoms.len());
+    let mut wipe_prob = 0.0;
+    for atom in &rate_atoms {
+        if let Component::Atom { x, p } = atom {
+            if 1.0 + x > 0.0 {
+                log_steps.push(((1.0 + x).ln(), p / rate_total));
+            } else {
+                wipe_prob += p / rate_total;
+            }
+        }
+    }
+
+    // Distribution of the summed log-growth, conditional on no wipe-out
+    let mut log_sum: Vec<(f64, f64)> = vec![(0.0, 1.0)];
+    let mut survive = 1.0;
+    for _ in 0..periods {
+        let mut next: Vec<(f64, f64)> = Vec::with_capacity(log_sum.len() * log_steps.len());
+        for &(s, w) in &log_sum {
+            for &(g, q) in &log_steps {
+                next.push((s + g, w * q));
+            }
+        }
+        log_sum = project_to_lattice(&next, grid.max(1));
+        survive *= 1.0 - wipe_prob;
+    }
+
+    let value_total = total_mass(value);
+    let mut result: Vec<Component> = Vec::with_capacity(value.len() * log_sum.len() + 1);
+    for &(s, w) in &log_sum {
+        let scaled = scale_components(value, s.exp());
+        result.extend(scaled.iter().map(|c| scale_component(c, w * survive)));
+    }
+    if survive < 1.0 && value_total > 0.0 {
+        result.push(Component::Atom { x: 0.0, p: value_total * (1.0 - survive) });
+    }
+    result
+}
+
+/// Value after `periods` periods of compounding with a random rate drawn
+/// from `rate_data` each period (`grid` sets the discretization resolution)
+#[wasm_bindgen]
+pub fn dist_compound_growth_stochastic(
+    value_data: Float64Array,
+    rate_data: Float64Array,
+    periods: u32,
+    grid: u32,
+) -> Float64Array {
+    let value: Vec<f64> = value_data.to_vec();
+    let rate: Vec<f64> = rate_data.to_vec();
+    
+    let result = compound_growth_stochastic(
+        &parse_components(&value),
+        &parse_components(&rate),
+        periods,
+        grid as usize,
+    );
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+// ===========================================
+// Robust Statistics
+// ===========================================
+
+/// Mean after winsorizing at the p and 1-p quantiles: mass below Q(p) is
+/// clamped to Q(p) and mass above Q(1-p) to Q(1-p), then averaged
+fn winsorized_mean(components: &[Component], p: f64) -> f64 {
+    if !(0.0..=0.5).contains(&p) || total_mass(components) <= 0.0 {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return mixture_mean(components);
+    }
+    let lo = mixture_quantile(components, p);
+    let hi = mixture_quantile(components, 1.0 - p);
+    p * lo + slice_expectation(components, p, 1.0 - p) + p * hi
+}
+
+/// Winsorized mean at level p in [0, 0.5]
+#[wasm_bindgen]
+pub fn dist_winsorized_mean(components_data: Float64Array, p: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    winsorized_mean(&components, p)
+}
+
+// ===========================================
+// Tail Fitting
+// ===========================================
+
+/// Re-model every tail component
+///
+/// * mode 0 - keep exponential tails
+/// * mode 1 - bounded bin from the anchor to the tail's own `cutoff`
+///   quantile (a cutoff of 1 - e^-2 keeps the tail mean exactly)
+/// * mode 2 - single atom at the tail mean
+///
+/// Mass is always preserved; other modes, or a cutoff outside (0, 1) in
+/// mode 1, leave tails unchanged.
+fn convert_tails(components: &[Component], mode: u32, cutoff: f64) -> Vec<Component> {
+    components
+        .iter()
+        .map(|c| match (c, mode) {
+            (Component::Tail { x0, mass, lambda, is_right }, 1) if cutoff > 0.0 && cutoff < 1.0 => {
+                let reach = -(-cutoff).ln_1p() / lambda;
+                if *is_right {
+                    Component::Bin { a: *x0, b: x0 + reach, p: *mass }
+                } else {
+                    Component::Bin { a: x0 - reach, b: *x0, p: *mass }
+                }
+            }
+            (Component::Tail { x0, mass, lambda, is_right }, 2) => {
+                let mean = if *is_right { x0 + 1.0 / lambda } else { x0 - 1.0 / lambda };
+                Component::Atom { x: mean, p: *mass }
+            }
+            _ => c.clone(),
+        })
+        .collect()
+}
+
+/// Convert tails between exponential (mode 0), bounded bin (mode 1) and
+/// atom (mode 2) representations for tail-assumption sensitivity studies
+#[wasm_bindgen]
+pub fn dist_convert_tails(components_data: Float64Array, mode: u32, cutoff: f64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let result = convert_tails(&components, mode, cutoff);
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Replace all mass beyond `threshold` with an exponential tail of equal mass
+/// whose lambda is the reciprocal of the mean excess beyond the threshold.
+/// Positive `side` fits the right tail, otherwise the left tail.
+fn fit_tail_from_body(components: &[Component], threshold: f64, side: i32) -> Vec<Component> {
+    if side <= 0 {
+        // Mirror, fit on the right, mirror back
+        let mirrored = scale_components(components, -1.0);
+        let fitted = fit_tail_from_body(&mirrored, -threshold, 1);
+        return scale_components(&fitted, -1.0);
+    }
+
+    let beyond_mass = total_mass(components) - mass_at_or_below(components, threshold);
+    let beyond_moment: f64 = components
+        .iter()
+        .map(|c| component_partial_expectation(c, threshold, f64::INFINITY))
+        .sum();
+    if beyond_mass <= 0.0 {
+        return components.to_vec();
+    }
+    let mean_excess = beyond_moment / beyond_mass - threshold;
+    if mean_excess <= 0.0 || !mean_excess.is_finite() {
+        return components.to_vec();
+    }
+
+    let mut result = restrict(components, f64::NEG_INFINITY, threshold);
+    result.push(Component::Tail { x0: threshold, mass: beyond_mass, lambda: 1.0 / mean_excess, is_right: true });
+    result
+}
+
+/// Smooth a body's tail beyond `threshold` into a fitted exponential tail
+/// (`side`: 1 for right, -1 for left)
+#[wasm_bindgen]
+pub fn dist_fit_tail_from_body(components_data: Float64Array, threshold: f64, side: i32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let result = fit_tail_from_body(&components, threshold, side);
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+// ===========================================
+// Batch Utilities
+// ===========================================
+
+/// Canonicalized copy: re-parsed and re-serialized, so malformed trailing
+/// components and unknown type tags are dropped
+#[wasm_bindgen]
+pub fn dist_clone(components_data: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let serialized = serialize_components(&components);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Concatenate the components of several distributions
+fn concat_components(parts: &[Vec<Component>]) -> Vec<Component> {
+    parts.iter().flatten().cloned().collect()
+}
+
+/// Parse every element of a JS array of Float64Arrays
+fn parse_component_array(dists: &Array) -> Vec<Vec<Component>> {
+    dists
+        .iter()
+        .map(|d| parse_components(&Float64Array::new(&d).to_vec()))
+        .collect()
+}
+
+/// Unconditional distribution from per-scenario conditionals (law of total
+/// probability). Each conditional is normalized to unit mass before being
+/// weighted by its scenario probability.
+fn scenario_mix(conditionals: &[Vec<Component>], scenario_probs: &[f64]) -> Result<Vec<Component>, String> {
+    if conditionals.len() != scenario_probs.len() {
+        return Err("one scenario probability is required per conditional".to_string());
+    }
+    if scenario_probs.iter().any(|p| !p.is_finite() || *p < 0.0) {
+        return Err("scenario probabilities must be finite and non-negative".to_string());
+    }
+    let sum: f64 = scenario_probs.iter().sum();
+    if (sum - 1.0).abs() > 1e-9 {
+        return Err(format!("scenario probabilities must sum to 1 (got {})", sum));
+    }
+
+    let mut result: Vec<Component> = Vec::new();
+    for (comps, &prob) in conditionals.iter().zip(scenario_probs) {
+        let mass = total_mass(comps);
+        if mass <= 0.0 {
+            return Err("conditional distributions must have positive mass".to_string());
+        }
+        result.extend(comps.iter().map(|c| scale_component(c, prob / mass)));
+    }
+    Ok(result)
+}
+
+/// Combine conditional distributions (one Float64Array per scenario) weighted
+/// by scenario probabilities that must sum to 1
+#[wasm_bindgen]
+pub fn dist_scenario_mix(conditionals: Array, scenario_probs: Float64Array) -> Result<Float64Array, JsValue> {
+    let parts = parse_component_array(&conditionals);
+    let probs: Vec<f64> = scenario_probs.to_vec();
+    
+    let result = scenario_mix(&parts, &probs).map_err(|e| JsValue::from_str(&e))?;
+    
+    let serialized = serialize_components(&result);
+    Ok(Float64Array::from(serialized.as_slice()))
+}
+
+/// Concatenate an array of distributions (Float64Array each) in one call
+#[wasm_bindgen]
+pub fn dist_concat_many(dists: Array) -> Float64Array {
+    let parts = parse_component_array(&dists);
+    let result = concat_components(&parts);
+    
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+// ===========================================
+// Reconstruction
+// ===========================================
+
+/// Maximum-entropy distribution matching quantile points (ps[i], xs[i])
+///
+/// With only the probability between consecutive points fixed, entropy is
+/// maximized by a uniform density on each interval, so the interior is one
+/// bin per interval. Mass below the first point and above the last becomes
+/// an exponential tail whose density meets the neighbouring bin's density.
+fn maxent_from_quantiles(ps: &[f64], xs: &[f64]) -> Result<Vec<Component>, String> {
+    if ps.len() != xs.len() {
+        return Err("ps and xs must have the same length".to_string());
+    }
+    if ps.len() < 2 {
+        return Err("at least two quantile points are required".to_string());
+    }
+    if ps.iter().chain(xs).any(|v| !v.is_finite()) {
+        return Err("quantile points must be finite".to_string());
+    }
+    if ps[0] < 0.0 || ps[ps.len() - 1] > 1.0 || ps.windows(2).any(|w| w[1] <= w[0]) {
+        return Err("ps must be strictly increasing within [0, 1]".to_string());
+    }
+    if xs.windows(2).any(|w| w[1] < w[0]) {
+        return Err("xs must be non-decreasing".to_string());
+    }
+
+    let n = ps.len();
+    let mut result: Vec<Component> = Vec::with_capacity(n + 1);
+    for i in 0..n - 1 {
+        let p = ps[i + 1] - ps[i];
+        if xs[i + 1] > xs[i] {
+            result.push(Component::Bin { a: xs[i], b: xs[i + 1], p });
+        } else {
+            result.push(Component::Atom { x: xs[i], p });
+        }
+    }
+
+    // Density used to anchor a tail: the adjacent bin, else the overall body
+    let span = xs[n - 1] - xs[0];
+    let body_density = if span > 0.0 { (ps[n - 1] - ps[0]) / span } else { 0.0 };
+    let junction_density = |i: usize| {
+        let w = xs[i + 1] - xs[i];
+        if w > 0.0 { (ps[i + 1] - ps[i]) / w } else { body_density }
+    };
+
+    let left_mass = ps[0];
+    if left_mass > 0.0 {
+        let d = junction_density(0);
+        let lambda = if d > 0.0 { d / left_mass } else { 1.0 };
+        result.push(Component::Tail { x0: xs[0], mass: left_mass, lambda, is_right: false });
+    }
+    let right_mass = 1.0 - ps[n - 1];
+    if right_mass > 0.0 {
+        let d = junction_density(n - 2);
+        let lambda = if d > 0.0 { d / right_mass } else { 1.0 };
+        result.push(Component::Tail { x0: xs[n - 1], mass: right_mass, lambda, is_right: true });
+    }
+    Ok(result)
+}
+
+/// Least-committal (maximum-entropy) distribution consistent with the given
+/// quantile points: uniform between points, exponential beyond the extremes
+#[wasm_bindgen]
+pub fn dist_maxent_from_quantiles(ps: Float64Array, xs: Float64Array) -> Result<Float64Array, JsValue> {
+    let ps: Vec<f64> = ps.to_vec();
+    let xs: Vec<f64> = xs.to_vec();
+    
+    let result = maxent_from_quantiles(&ps, &xs).map_err(|e| JsValue::from_str(&e))?;
+    
+    let serialized = serialize_components(&result);
+    Ok(Float64Array::from(serialized.as_slice()))
+}
+
+// ===========================================
+// Risk Measures
+// ===========================================
+
+/// Quantile slices per weighting segment when integrating a spectral measure
+const SPECTRAL_SLICES: usize = 64;
+
+/// Spectral risk measure: integral of Q(u) * phi(u) over [0, 1]
+///
+/// phi is piecewise linear through the knots (a repeated knot position gives
+/// a jump). It must be non-negative, integrate to 1 and be monotone: non-increasing
+/// weights the lower quantiles (worst outcomes of a P&L), non-decreasing weights
+/// the upper quantiles (worst outcomes of a loss). CVaR is phi constant on a tail.
+fn spectral_risk(components: &[Component], knots_p: &[f64], knots_w: &[f64]) -> Result<f64, String> {
+    if knots_p.len() != knots_w.len() || knots_p.len() < 2 {
+        return Err("need at least two knots with matching p and w arrays".to_string());
+    }
+    let n = knots_p.len();
+    if knots_p[0] != 0.0 || knots_p[n - 1] != 1.0 || knots_p.windows(2).any(|w| w[1] < w[0]) {
+        return Err("knot positions must be non-decreasing from 0 to 1".to_string());
+    }
+    if knots_w.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err("weights must be finite and non-negative".to_string());
+    }
+    let increasing = knots_w.windows(2).all(|w| w[1] >= w[0]);
+    let decreasing = knots_w.windows(2).all(|w| w[1] <= w[0]);
+    if !increasing && !decreasing {
+        return Err("weighting function must be monotone".to_string());
+    }
+    let integral: f64 = (0..n - 1)
+        .map(|i| (knots_p[i + 1] - knots_p[i]) * (knots_w[i] + knots_w[i + 1]) / 2.0)
+        .sum();
+    if (integral - 1.0).abs() > 1e-6 {
+        return Err(format!("weighting function must integrate to 1 (got {})", integral));
+    }
+
+    let mut risk = 0.0;
+    for i in 0..n - 1 {
+        let (p1, p2) = (knots_p[i], knots_p[i + 1]);
+        if p2 <= p1 {
+            continue;
+        }
+        let (w1, w2) = (knots_w[i], knots_w[i + 1]);
+        let width = (p2 - p1) / SPECTRAL_SLICES as f64;
+        for j in 0..SPECTRAL_SLICES {
+            let u1 = p1 + j as f64 * width;
+            let u2 = if j + 1 == SPECTRAL_SLICES { p2 } else { u1 + width };
+            let t = (j as f64 + 0.5) / SPECTRAL_SLICES as f64;
+            let phi = w1 + t * (w2 - w1);
+            if phi > 0.0 {
+                risk += phi * slice_expectation(components, u1, u2);
+            }
+        }
+    }
+    Ok(risk)
+}
+
+/// Spectral risk measure with a user-supplied piecewise-linear weighting
+/// function phi given by knots (weight_knots_p[i], weight_knots_w[i])
+#[wasm_bindgen]
+pub fn dist_spectral_risk(
+    components_data: Float64Array,
+    weight_knots_p: Float64Array,
+    weight_knots_w: Float64Array,
+) -> Result<f64, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    spectral_risk(&components, &weight_knots_p.to_vec(), &weight_knots_w.to_vec())
+        .map_err(|e| JsValue::from_str(&e))
+}
+
+/// Value at Risk at confidence level alpha
+///
+/// Loss convention: X is a loss (positive = bad) and VaR_alpha is the
+/// alpha-quantile inf { x : P(X <= x) >= alpha }, so alpha = 0.99 gives the
+/// loss exceeded with probability at most 1%. For a P&L distribution pass the
+/// negated distribution (`dist_scale(data, -1)`). NaN unless 0 < alpha < 1.
+fn value_at_risk(components: &[Component], alpha: f64) -> f64 {
+    if !(alpha > 0.0 && alpha < 1.0) || total_mass(components) <= 0.0 {
+        return f64::NAN;
+    }
+    mixture_quantile(components, alpha)
+}
+
+/// Value at Risk at confidence level alpha (loss convention, see
+/// `value_at_risk`)
+#[wasm_bindgen]
+pub fn dist_var(components_data: Float64Array, alpha: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    value_at_risk(&components, alpha)
+}
+
+/// Conditional VaR / expected shortfall at confidence level alpha: the mean
+/// of the worst 1 - alpha of losses, (1 / (1 - alpha)) * integral of Q(u) over
+/// [alpha, 1]
+///
+/// Exact: the bin or tail holding the quantile is integrated in closed form
+/// and an atom straddling alpha contributes only its share above it. NaN
+/// unless 0 < alpha < 1.
+fn conditional_var(components: &[Component], alpha: f64) -> f64 {
+    if !(alpha > 0.0 && alpha < 1.0) || total_mass(components) <= 0.0 {
+        return f64::NAN;
+    }
+    slice_expectation(components, alpha, 1.0) / (1.0 - alpha)
+}
+
+/// Conditional VaR (expected shortfall) at confidence level alpha, loss
+/// convention as in `dist_var`
+#[wasm_bindgen]
+pub fn dist_cvar(components_data: Float64Array, alpha: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    conditional_var(&components, alpha)
+}
+
+// ===========================================
+// Moments
+// ===========================================
+
+/// Binomial coefficient C(n, k) as f64
+fn binomial(n: u32, k: u32) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+/// E[X^k] of a single component (per unit mass)
+fn component_raw_moment(c: &Component, k: u32) -> f64 {
+    match c {
+        Component::Atom { x, .. } => x.powi(k as i32),
+        Component::Bin { a, b, .. } => {
+            // X = center + half * U with U ~ Uniform(-1, 1), E[U^j] = 1/(j+1) for even j
+            let center = (a + b) / 2.0;
+            let half = (b - a) / 2.0;
+            (0..=k)
+                .step_by(2)
+                .map(|j| binomial(k, j) * center.powi((k - j) as i32) * half.powi(j as i32) / (j + 1) as f64)
+                .sum()
+        }
+        Component::Tail { x0, lambda, is_right, .. } => {
+            // X = x0 ± E with E ~ Exp(lambda), E[E^j] = j! / lambda^j
+            let sign: f64 = if *is_right { 1.0 } else { -1.0 };
+            let mut factorial = 1.0;
+            let mut sum = 0.0;
+            for j in 0..=k {
+                if j > 0 {
+                    factorial *= j as f64;
+                }
+                let e_moment = factorial / lambda.powi(j as i32) * sign.powi(j as i32);
+                sum += binomial(k, j) * x0.powi((k - j) as i32) * e_moment;
+            }
+            sum
+        }
+    }
+}
+
+/// E[X^k] of the normalized mixture
+fn mixture_raw_moment(components: &[Component], k: u32) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    components.iter().map(|c| get_weight(c) * component_raw_moment(c, k)).sum::<f64>() / total
+}
+
+/// E[(X - mean)^k] of the normalized mixture, computed on the shifted
+/// components to avoid cancellation
+fn mixture_central_moment(components: &[Component], k: u32) -> f64 {
+    let mean = mixture_mean(components);
+    let centered: Vec<Component> = components.iter().map(|c| shift_component(c, -mean)).collect();
+    mixture_raw_moment(&centered, k)
+}
+
+/// Skewness mu3 / sigma^3 (NaN for a degenerate distribution)
+fn mixture_skewness(components: &[Component]) -> f64 {
+    let var = mixture_central_moment(components, 2);
+    if var <= 0.0 {
+        return f64::NAN;
+    }
+    mixture_central_moment(components, 3) / var.powf(1.5)
+}
+
+/// Excess kurtosis mu4 / sigma^4 - 3 (NaN for a degenerate distribution)
+fn mixture_excess_kurtosis(components: &[Component]) -> f64 {
+    let var = mixture_central_moment(components, 2);
+    if var <= 0.0 {
+        return f64::NAN;
+    }
+    mixture_central_moment(components, 4) / (var * var) - 3.0
+}
+
+/// Skewness of the distribution (NaN when the variance is zero)
+#[wasm_bindgen]
+pub fn dist_skewness(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_skewness(&components)
+}
+
+/// Excess kurtosis of the distribution (0 for a normal, NaN when the
+/// variance is zero)
+#[wasm_bindgen]
+pub fn dist_kurtosis(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_excess_kurtosis(&components)
+}
+
+/// Exact k-th moment of the distribution: E[X^k], or E[(X - mean)^k] when
+/// `central` is set
+#[wasm_bindgen]
+pub fn dist_moment(components_data: Float64Array, k: u32, central: bool) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    if central { mixture_central_moment(&components, k) } else { mixture_raw_moment(&components, k) }
+}
+
+/// Cumulants kappa_1..kappa_max_order, from the central moments via the
+/// moment-cumulant recursion kappa_n = mu_n - sum C(n-1, k-1) kappa_k mu_{n-k}
+fn mixture_cumulants(components: &[Component], max_order: u32) -> Vec<f64> {
+    if max_order == 0 {
+        return vec![];
+    }
+    let mean = mixture_mean(components);
+    let centered: Vec<Component> = components.iter().map(|c| shift_component(c, -mean)).collect();
+    let mu: Vec<f64> = (0..=max_order).map(|k| mixture_raw_moment(&centered, k)).collect();
+
+    let mut kappa = vec![mean];
+    for n in 2..=max_order {
+        let mut k_n = mu[n as usize];
+        // kappa_1 of the centered distribution is 0, so start at k = 2
+        for k in 2..n {
+            k_n -= binomial(n - 1, k - 1) * kappa[(k - 1) as usize] * mu[(n - k) as usize];
+        }
+        kappa.push(k_n);
+    }
+    kappa
+}
+
+/// First `max_order` cumulants [kappa_1, kappa_2, ...]; they add under
+/// convolution
+#[wasm_bindgen]
+pub fn dist_cumulants(components_data: Float64Array, max_order: u32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let cumulants = mixture_cumulants(&components, max_order);
+    Float64Array::from(cumulants.as_slice())
+}
+
+/// E[e^{tX}] of a single component (per unit mass); infinite where a tail's
+/// MGF diverges (t >= lambda on a right tail, t <= -lambda on a left tail)
+fn component_mgf(c: &Component, t: f64) -> f64 {
+    match c {
+        Component::Atom { x, .. } => (t * x).exp(),
+        Component::Bin { a, b, .. } => {
+            let s = t * (b - a);
+            if s == 0.0 { (t * a).exp() } else { (t * a).exp() * s.exp_m1() / s }
+        }
+        Component::Tail { x0, lambda, is_right, .. } => {
+            let rate = if *is_right { lambda - t } else { lambda + t };
+            if rate <= 0.0 { f64::INFINITY } else { (t * x0).exp() * lambda / rate }
+        }
+    }
+}
+
+/// MGF of the normalized mixture at t (NaN where it diverges)
+fn mixture_mgf(components: &[Component], t: f64) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return f64::NAN;
+    }
+    let value = components
+        .iter()
+        .filter(|c| get_weight(c) > 0.0)
+        .map(|c| get_weight(c) * component_mgf(c, t))
+        .sum::<f64>()
+        / total;
+    if value.is_finite() { value } else { f64::NAN }
+}
+
+/// Moment generating function E[e^{tX}]
+///
+/// Closed form per component. Returns NaN when t lies outside the region of
+/// convergence, which for a right tail with rate lambda means t >= lambda
+/// (and t <= -lambda for a left tail).
+#[wasm_bindgen]
+pub fn dist_mgf(components_data: Float64Array, t: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_mgf(&components, t)
+}
+
+/// E[e^{itX}] of a single component (per unit mass) as (re, im)
+fn component_cf(c: &Component, t: f64) -> (f64, f64) {
+    // e^{itx} times a complex factor (fr, fi)
+    let rotate = |x: f64, fr: f64, fi: f64| {
+        let (s, c) = (t * x).sin_cos();
+        (c * fr - s * fi, s * fr + c * fi)
+    };
+    match c {
+        Component::Atom { x, .. } => rotate(*x, 1.0, 0.0),
+        Component::Bin { a, b, .. } => {
+            let half = (b - a) / 2.0;
+            let th = t * half;
+            let sinc = if th == 0.0 { 1.0 } else { th.sin() / th };
+            rotate((a + b) / 2.0, sinc, 0.0)
+        }
+        Component::Tail { x0, lambda, is_right, .. } => {
+            // lambda / (lambda -+ it) = lambda (lambda +- it) / (lambda^2 + t^2)
+            let denom = lambda * lambda + t * t;
+            let im = if *is_right { lambda * t / denom } else { -lambda * t / denom };
+            rotate(*x0, lambda * lambda / denom, im)
+        }
+    }
+}
+
+/// Characteristic function of the normalized mixture at t as (re, im)
+fn mixture_cf(components: &[Component], t: f64) -> (f64, f64) {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return (f64::NAN, f64::NAN);
+    }
+    let (re, im) = components.iter().fold((0.0, 0.0), |(re, im), c| {
+        let w = get_weight(c);
+        let (cr, ci) = component_cf(c, t);
+        (re + w * cr, im + w * ci)
+    });
+    (re / total, im / total)
+}
+
+/// Characteristic function E[e^{itX}] at t, returned as [re, im]
+#[wasm_bindgen]
+pub fn dist_cf(components_data: Float64Array, t: f64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let (re, im) = mixture_cf(&components, t);
+    Float64Array::from([re, im].as_slice())
+}
+
+/// Characteristic function at each t, interleaved as [re0, im0, re1, im1, ...]
+#[wasm_bindgen]
+pub fn dist_cf_grid(components_data: Float64Array, ts: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let values: Vec<f64> = ts
+        .to_vec()
+        .iter()
+        .flat_map(|&t| {
+            let (re, im) = mixture_cf(&components, t);
+            [re, im]
+        })
+        .collect();
+    Float64Array::from(values.as_slice())
+}
+
+/// Standard normal quantile (Acklam's rational approximation, |rel err| < 1.2e-9)
+fn normal_quantile(p: f64) -> f64 {
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Complementary error function (Numerical Recipes erfcc, |rel err| <
+/// 1.2e-7 everywhere, so it keeps its accuracy far into the tail)
+fn erfc(x: f64) -> f64 {
+    let ax = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * ax);
+    let poly = -ax * ax - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let value = t * poly.exp();
+    if x >= 0.0 {
+        value
+    } else {
+        2.0 - value
+    }
+}
+
+/// Standard normal CDF via the complementary error function
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * erfc(-z / std::f64::consts::SQRT_2)
+}
+
+/// Standard normal survival function 1 - CDF, computed directly so it keeps
+/// its relative accuracy where 1 - normal_cdf(z) would cancel to 0
+fn normal_sf(z: f64) -> f64 {
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// Cornish-Fisher adjusted standard quantile for level p given skewness and
+/// excess kurtosis
+fn cornish_fisher_z(p: f64, skew: f64, excess_kurt: f64) -> f64 {
+    let z = normal_quantile(p);
+    z + (z * z - 1.0) * skew / 6.0 + (z.powi(3) - 3.0 * z) * excess_kurt / 24.0
+        - (2.0 * z.powi(3) - 5.0 * z) * skew * skew / 36.0
+}
+
+/// Modified (Cornish-Fisher) VaR: mean + std * z_cf(alpha)
+///
+/// Loss convention: `alpha` is the confidence level (e.g. 0.99) and the result
+/// estimates the alpha-quantile of the loss distribution, with skewness and
+/// excess kurtosis fattening the normal estimate.
+fn modified_var(components: &[Component], alpha: f64) -> f64 {
+    if !(alpha > 0.0 && alpha < 1.0) || total_mass(components) <= 0.0 {
+        return f64::NAN;
+    }
+    let mean = mixture_mean(components);
+    let var = mixture_central_moment(components, 2);
+    if var <= 0.0 {
+        return mean;
+    }
+    let z = cornish_fisher_z(alpha, mixture_skewness(components), mixture_excess_kurtosis(components));
+    mean + var.sqrt() * z
+}
+
+/// Modified VaR at confidence level alpha (loss convention), the fat-tail
+/// aware Cornish-Fisher adjustment of the normal VaR
+#[wasm_bindgen]
+pub fn dist_modified_var(components_data: Float64Array, alpha: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    modified_var(&components, alpha)
+}
+
+// ===========================================
+// Sum Approximations
+// ===========================================
+
+/// Bins used by `clt_approx` for the normal
+const CLT_BINS: usize = 64;
+
+/// `clt_approx` covers mean +/- this many standard deviations
+const CLT_SPAN_SDS: f64 = 6.0;
+
+/// Normal approximation of the sum of `n` i.i.d. copies as `CLT_BINS`
+/// equal-width bins over mean +/- `CLT_SPAN_SDS` sd
+///
+/// Bin masses follow the normal CDF with the two outside tails folded into
+/// the end bins, then bins are spread about the mean so the variance is
+/// exactly n times the component variance. Total mass is the input mass to
+/// the n-th power.
+fn clt_approx(components: &[Component], n: u32) -> Vec<Component> {
+    let mass = total_mass(components).powi(n as i32);
+    let mean = n as f64 * mixture_mean(components);
+    let var = n as f64 * mixture_variance(components);
+    if n == 0 || !(var > 0.0 && var.is_finite()) {
+        return vec![Component::Atom { x: mean, p: mass }];
+    }
+    let width = 2.0 * CLT_SPAN_SDS / CLT_BINS as f64;
+    let zs = linspace(-CLT_SPAN_SDS, CLT_SPAN_SDS, CLT_BINS + 1);
+    // Lower half only (with Phi(0) = 1/2 exactly), mirrored so the mean is exact
+    let half = CLT_BINS / 2;
+    let mut masses: Vec<f64> = (0..half)
+        .map(|i| if i + 1 == half { 0.5 } else { normal_cdf(zs[i + 1]) } - normal_cdf(zs[i]))
+        .collect();
+    masses[0] += normal_cdf(zs[0]);
+    let mirrored: Vec<f64> = masses.iter().rev().copied().collect();
+    masses.extend(mirrored);
+    let total: f64 = masses.iter().sum();
+    // Standardized variance of the binned normal, to rescale onto var exactly
+    let binned_var: f64 = zs
+        .windows(2)
+        .zip(&masses)
+        .map(|(w, p)| p / total * ((w[0] + w[1]).powi(2) / 4.0 + width * width / 12.0))
+        .sum();
+    let scale = (var / binned_var).sqrt();
+    zs.windows(2)
+        .zip(masses)
+        .map(|(w, p)| Component::Bin { a: mean + w[0] * scale, b: mean + w[1] * scale, p: mass * p / total })
+        .collect()
+}
+
+/// Normal (central limit) approximation of the sum of `n` i.i.d. copies of
+/// a distribution, as bins with the exact mean and variance of the sum
+#[wasm_bindgen]
+pub fn dist_clt_approx(components_data: Float64Array, n: u32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let result = clt_approx(&parse_components(&data), n);
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Cornish-Fisher approximation of the `p` quantile of the sum of `n`
+/// i.i.d. copies, from the exact mean, variance, skewness (scaling as
+/// 1/sqrt n) and excess kurtosis (as 1/n) of the sum
+fn sum_quantile_cornish_fisher(components: &[Component], n: u32, p: f64) -> f64 {
+    if !(p > 0.0 && p < 1.0) || total_mass(components) <= 0.0 {
+        return f64::NAN;
+    }
+    let n = n as f64;
+    let mean = n * mixture_mean(components);
+    let var = n * mixture_central_moment(components, 2);
+    if n == 0.0 || var <= 0.0 {
+        return mean;
+    }
+    let skew = mixture_skewness(components) / n.sqrt();
+    let excess_kurt = mixture_excess_kurtosis(components) / n;
+    mean + var.sqrt() * cornish_fisher_z(p, skew, excess_kurt)
+}
+
+/// `p` quantile of the sum of `n` i.i.d. copies of a distribution by the
+/// Cornish-Fisher expansion: the normal quantile corrected for the sum's
+/// skewness and kurtosis
+#[wasm_bindgen]
+pub fn dist_sum_quantile(components_data: Float64Array, n: u32, p: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    sum_quantile_cornish_fisher(&parse_components(&data), n, p)
+}
+
+/// ln M(t) and the mean and variance of the exponentially tilted density
+/// e^{t x} f(x) / M(t), or None outside the MGF's region of convergence
+///
+/// Works in log space so large |t| cannot overflow; tilted bins are
+/// truncated exponentials and tilted tails exponentials with rate
+/// lambda -/+ t.
+fn tilted_moments(components: &[Component], t: f64) -> Option<(f64, f64, f64)> {
+    let mut parts: Vec<(f64, f64, f64)> = Vec::with_capacity(components.len());
+    for c in components.iter().filter(|c| get_weight(c) > 0.0) {
+        let ln_p = get_weight(c).ln();
+        parts.push(match *c {
+            Component::Atom { x, .. } => (ln_p + t * x, x, 0.0),
+            Component::Bin { a, b, .. } => {
+                let w = b - a;
+                let s = t * w;
+                // Tilted mean a + w * offset and variance w^2 * spread
+                let (ln_m, offset, spread) = if s.abs() < 1e-4 {
+                    (t * a + s / 2.0 + s * s / 24.0, 0.5 + s / 12.0, 1.0 / 12.0 - s * s / 240.0)
+                } else {
+                    // Anchor at the heavier end so the exponentials stay bounded
+                    let ln_m = if s > 0.0 { t * b + (-(-s).exp_m1() / s).ln() } else { t * a + (s.exp_m1() / s).ln() };
+                    (ln_m, 1.0 / -(-s).exp_m1() - 1.0 / s, 1.0 / (s * s) - 0.25 / (s / 2.0).sinh().powi(2))
+                };
+                (ln_p + ln_m, a + w * offset, w * w * spread)
+            }
+            Component::Tail { x0, lambda, is_right, .. } => {
+                let rate = if is_right { lambda - t } else { lambda + t };
+                if rate <= 0.0 {
+                    return None;
+                }
+                let mean = if is_right { x0 + 1.0 / rate } else { x0 - 1.0 / rate };
+                (ln_p + t * x0 + (lambda / rate).ln(), mean, 1.0 / (rate * rate))
+            }
+        });
+    }
+    let top = parts.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    if !top.is_finite() {
+        return None;
+    }
+    let weights: Vec<f64> = parts.iter().map(|p| (p.0 - top).exp()).collect();
+    let total: f64 = weights.iter().sum();
+    let mean = parts.iter().zip(&weights).map(|(p, w)| w * p.1).sum::<f64>() / total;
+    let var = parts.iter().zip(&weights).map(|(p, w)| w * (p.2 + (p.1 - mean).powi(2))).sum::<f64>() / total;
+    let ln_mgf = top + total.ln() - total_mass(components).ln();
+    Some((ln_mgf, mean, var))
+}
+
+/// Saddlepoint (Lugannani-Rice) approximation of P(S_n > x) for the sum of
+/// `n` i.i.d. copies of a continuous distribution
+///
+/// Solves n K'(t) = x for the cumulant generating function K by bisection
+/// and falls back to the normal approximation right at the mean, where the
+/// formula is singular. Beyond a bounded support the probability is 0 or 1.
+fn saddlepoint_tail(components: &[Component], n: u32, x: f64) -> f64 {
+    if n == 0 || total_mass(components) <= 0.0 || x.is_nan() {
+        return f64::NAN;
+    }
+    let nf = n as f64;
+    let (lo, hi) = support_bounds(components);
+    if x >= nf * hi {
+        return 0.0;
+    }
+    if x < nf * lo {
+        return 1.0;
+    }
+    let target = x / nf;
+    let mean = mixture_mean(components);
+    let sd = mixture_variance(components).sqrt();
+    if sd.is_nan() || sd <= 0.0 {
+        return if x < nf * mean { 1.0 } else { 0.0 };
+    }
+    let z = (x - nf * mean) / (sd * nf.sqrt());
+    // Bracket the root of K'(t) = target, staying inside the region of convergence
+    let tilted_mean = |t: f64| tilted_moments(components, t).map(|m| m.1);
+    let direction = if target > mean { 1.0 } else { -1.0 };
+    let (mut inner, mut outer) = (0.0, direction / sd);
+    loop {
+        match tilted_mean(outer) {
+            Some(m) if (m - target) * direction >= 0.0 => break,
+            Some(_) if outer.abs() < 1e12 / sd => {
+                inner = outer;
+                outer *= 2.0;
+            }
+            Some(_) => return if direction > 0.0 { 0.0 } else { 1.0 },
+            None => break,
+        }
+    }
+    for _ in 0..200 {
+        let mid = (inner + outer) / 2.0;
+        match tilted_mean(mid) {
+            Some(m) if (m - target) * direction < 0.0 => inner = mid,
+            _ => outer = mid,
+        }
+    }
+    let t = (inner + outer) / 2.0;
+    let Some((k, _, k2)) = tilted_moments(components, t) else {
+        return f64::NAN;
+    };
+    let w = t.signum() * (2.0 * nf * (t * target - k)).max(0.0).sqrt();
+    let u = t * (nf * k2).sqrt();
+    if w.abs() < 1e-6 || u == 0.0 {
+        return normal_sf(z);
+    }
+    let density = (-w * w / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt();
+    (normal_sf(w) + density * (1.0 / u - 1.0 / w)).clamp(0.0, 1.0)
+}
+
+/// Saddlepoint approximation of P(S_n > x), S_n the sum of `n` i.i.d.
+/// copies of a distribution; accurate far into the tail without
+/// convolution or simulation (intended for continuous distributions)
+#[wasm_bindgen]
+pub fn dist_sum_tail_prob(components_data: Float64Array, n: u32, x: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    saddlepoint_tail(&parse_components(&data), n, x)
+}
+
+// ===========================================
+// CDF Transforms
+// ===========================================
+
+/// Components with unit mass reproducing a CDF at sorted, distinct `grid`
+/// points: an atom for each jump, a bin for the mass between neighbouring
+/// points, and an exponential tail for any mass left outside the grid
+///
+/// `cdf` is right-continuous and `cdf_left` its left limit. Tail rates come
+/// from the log-slope of the CDF (or survival) over the outermost grid cell.
+fn cdf_to_components(cdf: impl Fn(f64) -> f64, cdf_left: impl Fn(f64) -> f64, grid: &[f64]) -> Vec<Component> {
+    const NEGLIGIBLE: f64 = 1e-15;
+    let mut out = Vec::new();
+    let (Some(&first), Some(&last)) = (grid.first(), grid.last()) else {
+        return out;
+    };
+    let fallback_rate = |width: f64| if width > 0.0 { 1.0 / width } else { 1.0 };
+    let below = cdf_left(first);
+    if below > NEGLIGIBLE {
+        let rate = grid.get(1).map_or(f64::NAN, |&g| (cdf_left(g) / below).ln() / (g - first));
+        let lambda = if rate.is_finite() && rate > 0.0 { rate } else { fallback_rate(grid.get(1).map_or(0.0, |g| g - first)) };
+        out.push(Component::Tail { x0: first, mass: below, lambda, is_right: false });
+    }
+    for (i, &x) in grid.iter().enumerate() {
+        let jump = cdf(x) - cdf_left(x);
+        if jump > NEGLIGIBLE {
+            out.push(Component::Atom { x, p: jump });
+        }
+        if let Some(&next) = grid.get(i + 1) {
+            let mass = cdf_left(next) - cdf(x);
+            if mass > NEGLIGIBLE {
+                out.push(Component::Bin { a: x, b: next, p: mass });
+            }
+        }
+    }
+    let above = 1.0 - cdf(last);
+    if above > NEGLIGIBLE {
+        let prev = if grid.len() > 1 { grid[grid.len() - 2] } else { last };
+        let rate = ((1.0 - cdf(prev)) / above).ln() / (last - prev);
+        let lambda = if rate.is_finite() && rate > 0.0 { rate } else { fallback_rate(last - prev) };
+        out.push(Component::Tail { x0: last, mass: above, lambda, is_right: true });
+    }
+    out
+}
+
+/// Evaluation grid for a CDF built from `parts`: their breakpoints plus
+/// `n_points` evenly spaced points, over the range holding all but 1e-9 of
+/// the combined mass
+fn transform_grid(parts: &[&[Component]], n_points: usize) -> Vec<f64> {
+    let all: Vec<Component> = parts.iter().flat_map(|p| p.iter().cloned()).collect();
+    let (lo, hi) = support_interval(&all, 1e-9);
+    let mut grid = linspace(lo, hi, n_points.max(2));
+    for p in parts {
+        grid.extend(breakpoints(p));
+    }
+    grid.retain(|&x| x.is_finite() && x >= lo && x <= hi);
+    grid.sort_by(|a, b| a.total_cmp(b));
+    grid.dedup();
+    grid
+}
+
+/// Distribution of max(X, Y) (or min with `is_max` false) for independent X
+/// and Y, from F_max = F_X F_Y and 1 - F_min = (1 - F_X)(1 - F_Y) evaluated
+/// on `n_bins` cells plus every breakpoint
+fn extreme_of_pair(comps1: &[Component], comps2: &[Component], n_bins: usize, is_max: bool) -> Vec<Component> {
+    let (m1, m2) = (total_mass(comps1), total_mass(comps2));
+    if m1 <= 0.0 || m2 <= 0.0 {
+        return Vec::new();
+    }
+    let combine = |f1: f64, f2: f64| if is_max { f1 * f2 } else { 1.0 - (1.0 - f1) * (1.0 - f2) };
+    let cdf = |x: f64| combine(mass_at_or_below(comps1, x) / m1, mass_at_or_below(comps2, x) / m2);
+    let cdf_left = |x: f64| combine(mass_below(comps1, x) / m1, mass_below(comps2, x) / m2);
+    let grid = transform_grid(&[comps1, comps2], n_bins + 1);
+    cdf_to_components(cdf, cdf_left, &grid).iter().map(|c| scale_component(c, m1 * m2)).collect()
+}
+
+/// Distribution of the larger of independent draws from two distributions,
+/// with continuous parts discretized on `n_bins` cells
+#[wasm_bindgen]
+pub fn dist_max(dist1_data: Float64Array, dist2_data: Float64Array, n_bins: u32) -> Float64Array {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    let result = extreme_of_pair(&parse_components(&data1), &parse_components(&data2), n_bins as usize, true);
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Distribution of the smaller of independent draws from two distributions,
+/// with continuous parts discretized on `n_bins` cells
+#[wasm_bindgen]
+pub fn dist_min(dist1_data: Float64Array, dist2_data: Float64Array, n_bins: u32) -> Float64Array {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    let result = extreme_of_pair(&parse_components(&data1), &parse_components(&data2), n_bins as usize, false);
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+// ===========================================
+// Products and Ratios
+// ===========================================
+
+/// Equal-mass atoms a continuous component is split into when multiplied by
+/// another continuous component
+const PRODUCT_GRID_ATOMS: usize = 64;
+
+/// Component `c` scaled by `k`, weighted by an extra factor `weight`
+/// (scaling by zero collapses to an atom at 0)
+fn scaled_part(c: &Component, k: f64, weight: f64, out: &mut Vec<Component>) {
+    if k == 0.0 {
+        out.push(Component::Atom { x: 0.0, p: get_weight(c) * weight });
+    } else {
+        out.extend(scale_components(std::slice::from_ref(c), k).iter().map(|s| scale_component(s, weight)));
+    }
+}
+
+/// Distribution of X * Y for independent X and Y
+///
+/// A pairing with an atom is exact: the other component is scaled by the
+/// atom. Two continuous components multiply by splitting one (a bin in
+/// preference to a tail, so tails keep their shape) into
+/// `PRODUCT_GRID_ATOMS` equal-mass atoms at their conditional means, which
+/// keeps E[XY] exact. The result is compressed to `max_components` (0 keeps
+/// every piece).
+fn multiply_components(comps1: &[Component], comps2: &[Component], max_components: usize) -> Vec<Component> {
+    let mut result = Vec::new();
+    for c1 in comps1 {
+        for c2 in comps2 {
+            match (c1, c2) {
+                (Component::Atom { x, p }, other) | (other, Component::Atom { x, p }) => {
+                    scaled_part(other, *x, *p, &mut result);
+                }
+                _ => {
+                    let (split, kept) = if matches!(c1, Component::Tail { .. }) { (c2, c1) } else { (c1, c2) };
+                    for atom in equal_mass_atoms(std::slice::from_ref(split), PRODUCT_GRID_ATOMS) {
+                        if let Component::Atom { x, p } = atom {
+                            scaled_part(kept, x, p, &mut result);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if max_components > 0 {
+        compress_components(&result, max_components)
+    } else {
+        result
+    }
+}
+
+/// Distribution of the product of independent draws from two
+/// distributions, compressed to at most `max_components` components (0
+/// disables compression)
+#[wasm_bindgen]
+pub fn dist_multiply(dist1_data: Float64Array, dist2_data: Float64Array, max_components: u32) -> Float64Array {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    let result = multiply_components(&parse_components(&data1), &parse_components(&data2), max_components as usize);
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Cells each continuous piece is cut into by `reciprocal_components`
+const RECIPROCAL_CELLS: usize = 32;
+
+/// Image under 1 / x of the cell [x1, x2] of positive reals holding mass `p`
+///
+/// A cell touching 0 maps to [1 / x2, inf) and becomes a right tail with
+/// the local decay rate of the 1 / y^2 density there; an unbounded cell
+/// maps to the bin next to 0.
+fn reciprocal_cell(x1: f64, x2: f64, p: f64, out: &mut Vec<Component>) {
+    if p <= 0.0 {
+        return;
+    }
+    if x1 <= 0.0 {
+        out.push(Component::Tail { x0: 1.0 / x2, mass: p, lambda: x2, is_right: true });
+    } else {
+        out.push(Component::Bin { a: 1.0 / x2, b: 1.0 / x1, p });
+    }
+}
+
+/// 1 / X for a component supported on [0, inf)
+///
+/// Bins are cut into geometrically spaced cells (halving towards 0 when the
+/// bin starts there) and right tails into equal-mass cells, so every cell
+/// carries its exact mass into the reciprocal.
+fn reciprocal_positive(c: &Component, out: &mut Vec<Component>) {
+    let k = RECIPROCAL_CELLS;
+    let (edges, mass): (Vec<f64>, Vec<f64>) = match *c {
+        Component::Atom { x, p } => {
+            out.push(Component::Atom { x: 1.0 / x, p });
+            return;
+        }
+        Component::Bin { a, b, p } if a == b => {
+            out.push(Component::Atom { x: 1.0 / a, p });
+            return;
+        }
+        Component::Bin { a, b, p } => {
+            let edges: Vec<f64> = if a > 0.0 {
+                (0..=k).map(|i| a * (b / a).powf(i as f64 / k as f64)).collect()
+            } else {
+                std::iter::once(0.0).chain((0..k).map(|i| b * 0.5f64.powi((k - 1 - i) as i32))).collect()
+            };
+            let mass = edges.windows(2).map(|w| p * (w[1] - w[0]) / (b - a)).collect();
+            (edges, mass)
+        }
+        Component::Tail { x0, mass, lambda, .. } => {
+            let edges = (0..=k)
+                .map(|i| if i == k { f64::INFINITY } else { x0 - (-(i as f64) / k as f64).ln_1p() / lambda })
+                .collect();
+            (edges, vec![mass / k as f64; k])
+        }
+    };
+    for (w, p) in edges.windows(2).zip(mass) {
+        reciprocal_cell(w[0], w[1], p, out);
+    }
+}
+
+/// Distribution of 1 / X
+///
+/// Mass with |x| <= `zero_tolerance` has no usable reciprocal and is
+/// dropped; the dropped weight is returned alongside. Each side of zero is
+/// restricted away from the band and mapped piecewise (the negative side
+/// through 1 / x = -(1 / -x)), so atoms map exactly and continuous parts
+/// keep their mass cell by cell. Mass adjacent to zero maps to a tail whose
+/// true density decays like 1 / y^2, heavier than the exponential used.
+fn reciprocal_components(components: &[Component], zero_tolerance: f64) -> (Vec<Component>, f64) {
+    let tol = zero_tolerance.max(0.0);
+    let mut result = Vec::new();
+    for c in restrict(components, tol, f64::INFINITY) {
+        reciprocal_positive(&c, &mut result);
+    }
+    let mut negative = Vec::new();
+    for c in restrict(&negate_components(components), tol, f64::INFINITY) {
+        reciprocal_positive(&c, &mut negative);
+    }
+    result.extend(negate_components(&negative));
+    let dropped = (total_mass(components) - total_mass(&result)).max(0.0);
+    (result, dropped)
+}
+
+/// Distribution of 1 / X, dropping (and reporting) the mass within
+/// `zero_tolerance` of zero
+#[wasm_bindgen]
+pub fn dist_reciprocal(components_data: Float64Array, zero_tolerance: Option<f64>) -> PrunedDistribution {
+    let data: Vec<f64> = components_data.to_vec();
+    let (result, pruned_mass) = reciprocal_components(&parse_components(&data), zero_tolerance.unwrap_or(0.0));
+    PrunedDistribution { components: serialize_components(&result), pruned_mass }
+}
+
+/// Distribution of X / Y for independent X and Y: X times the reciprocal of
+/// Y, with the mass of Y within `zero_tolerance` of zero dropped and
+/// reported
+fn divide_components(
+    comps1: &[Component],
+    comps2: &[Component],
+    max_components: usize,
+    zero_tolerance: f64,
+) -> (Vec<Component>, f64) {
+    let (inverse, dropped) = reciprocal_components(comps2, zero_tolerance);
+    (multiply_components(comps1, &inverse, max_components), dropped)
+}
+
+/// Distribution of the ratio of independent draws from two distributions,
+/// compressed to at most `max_components` components (0 disables
+/// compression); the pruned mass is the divisor's mass within
+/// `zero_tolerance` of zero
+#[wasm_bindgen]
+pub fn dist_divide(
+    dist1_data: Float64Array,
+    dist2_data: Float64Array,
+    max_components: u32,
+    zero_tolerance: Option<f64>,
+) -> PrunedDistribution {
+    let data1: Vec<f64> = dist1_data.to_vec();
+    let data2: Vec<f64> = dist2_data.to_vec();
+    let (result, pruned_mass) = divide_components(
+        &parse_components(&data1),
+        &parse_components(&data2),
+        max_components as usize,
+        zero_tolerance.unwrap_or(0.0),
+    );
+    PrunedDistribution { components: serialize_components(&result), pruned_mass }
+}
+
+// ===========================================
+// Log and Exp Transforms
+// ===========================================
+
+/// Ascending edges of `n` equal-mass cells of a tail, the outermost one
+/// unbounded
+fn tail_cell_edges(x0: f64, lambda: f64, is_right: bool, n: usize) -> Vec<f64> {
+    let dist = |i: usize| if i == n { f64::INFINITY } else { -(-(i as f64) / n as f64).ln_1p() / lambda };
+    if is_right {
+        (0..=n).map(|i| x0 + dist(i)).collect()
+    } else {
+        (0..=n).rev().map(|i| x0 - dist(i)).collect()
+    }
+}
+
+/// e^X for one component, continuous parts cut into `n` cells
+///
+/// Bins are cut evenly (so cells are evenly spaced on the log scale of the
+/// result) and tails into equal-mass cells; each cell keeps its exact mass.
+/// The unbounded cell of a left tail becomes the bin next to 0, and that of
+/// a right tail a right tail with the local decay rate of the Pareto tail
+/// e^X actually has.
+fn exp_component(c: &Component, n: usize, out: &mut Vec<Component>) {
+    let tail_rate = if let Component::Tail { lambda, .. } = *c { lambda } else { 0.0 };
+    let (edges, masses): (Vec<f64>, Vec<f64>) = match *c {
+        Component::Atom { x, p } => {
+            out.push(Component::Atom { x: x.exp(), p });
+            return;
+        }
+        Component::Bin { a, b, p } if a == b => {
+            out.push(Component::Atom { x: a.exp(), p });
+            return;
+        }
+        Component::Bin { a, b, p } => (linspace(a, b, n + 1), vec![p / n as f64; n]),
+        Component::Tail { x0, mass, lambda, is_right } => (tail_cell_edges(x0, lambda, is_right, n), vec![mass / n as f64; n]),
+    };
+    for (w, p) in edges.windows(2).zip(masses) {
+        let (y1, y2) = (w[0].exp(), w[1].exp());
+        if y2.is_infinite() {
+            out.push(Component::Tail { x0: y1, mass: p, lambda: tail_rate / y1, is_right: true });
+        } else {
+            out.push(Component::Bin { a: y1, b: y2, p });
+        }
+    }
+}
+
+/// ln X for one component supported on [0, inf), continuous parts cut into
+/// `n` cells
+///
+/// Bins are cut geometrically (evenly on the log scale; a bin starting at 0
+/// keeps 1/n of its mass in the cell next to 0) and right tails into
+/// equal-mass cells; each
+/// cell keeps its exact mass. A cell starting at 0 maps to a left tail of
+/// rate 1, exact for mass spread evenly near 0; the unbounded cell of a
+/// right tail maps to a right tail with the local decay rate of the
+/// double-exponential tail ln X actually has.
+fn log_component(c: &Component, n: usize, out: &mut Vec<Component>) {
+    let tail_rate = if let Component::Tail { lambda, .. } = *c { lambda } else { 0.0 };
+    let (edges, masses): (Vec<f64>, Vec<f64>) = match *c {
+        Component::Atom { x, p } => {
+            out.push(Component::Atom { x: x.ln(), p });
+            return;
+        }
+        Component::Bin { a, b, p } if a == b => {
+            out.push(Component::Atom { x: a.ln(), p });
+            return;
+        }
+        Component::Bin { a, b, p } => {
+            let edges: Vec<f64> = if a > 0.0 {
+                (0..=n).map(|i| a * (b / a).powf(i as f64 / n as f64)).collect()
+            } else {
+                let nf = n as f64;
+                std::iter::once(0.0).chain((0..n).map(|i| b * nf.powf(i as f64 / (nf - 1.0).max(1.0) - 1.0))).collect()
+            };
+            let masses = edges.windows(2).map(|w| p * (w[1] - w[0]) / (b - a)).collect();
+            (edges, masses)
+        }
+        Component::Tail { x0, mass, lambda, is_right } => (tail_cell_edges(x0, lambda, is_right, n), vec![mass / n as f64; n]),
+    };
+    for (w, p) in edges.windows(2).zip(masses) {
+        if p <= 0.0 {
+            continue;
+        }
+        if w[0] <= 0.0 {
+            out.push(Component::Tail { x0: w[1].ln(), mass: p, lambda: 1.0, is_right: false });
+        } else if w[1].is_infinite() {
+            out.push(Component::Tail { x0: w[0].ln(), mass: p, lambda: tail_rate * w[0], is_right: true });
+        } else {
+            out.push(Component::Bin { a: w[0].ln(), b: w[1].ln(), p });
+        }
+    }
+}
+
+/// Distribution of e^X, each bin and tail cut into `n_cells` cells
+fn exp_components(components: &[Component], n_cells: usize) -> Vec<Component> {
+    let mut out = Vec::new();
+    for c in components {
+        exp_component(c, n_cells.max(1), &mut out);
+    }
+    out
+}
+
+/// Distribution of ln X, each bin and tail cut into `n_cells` cells
+///
+/// Mass at or below zero has no logarithm; it is dropped and its weight
+/// returned alongside.
+fn log_components(components: &[Component], n_cells: usize) -> (Vec<Component>, f64) {
+    let mut out = Vec::new();
+    for c in restrict(components, 0.0, f64::INFINITY) {
+        log_component(&c, n_cells.max(1), &mut out);
+    }
+    let dropped = (total_mass(components) - total_mass(&out)).max(0.0);
+    (out, dropped)
+}
+
+/// Distribution of e^X, e.g. gross returns from log returns; atoms map
+/// exactly and each bin and tail is cut into `n_cells` cells to follow the
+/// curvature of the map
+#[wasm_bindgen]
+pub fn dist_exp(components_data: Float64Array, n_cells: u32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let result = exp_components(&parse_components(&data), n_cells as usize);
+    let serialized = serialize_components(&result);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Distribution of ln X, e.g. log returns from gross returns; atoms map
+/// exactly, each bin and tail is cut into `n_cells` cells, and the pruned
+/// mass is the mass at or below zero
+#[wasm_bindgen]
+pub fn dist_log(components_data: Float64Array, n_cells: u32) -> PrunedDistribution {
+    let data: Vec<f64> = components_data.to_vec();
+    let (result, pruned_mass) = log_components(&parse_components(&data), n_cells as usize);
+    PrunedDistribution { components: serialize_components(&result), pruned_mass }
+}
+
+// ===========================================
+// Compression
+// ===========================================
+
+/// Center of mass of a component
+fn component_center(c: &Component) -> f64 {
+    component_raw_moment(c, 1)
+}
+
+/// Reduce to at most `max_components` components by merging neighbours
+///
+/// Tails are kept as they are unless they would take more than half the
+/// budget, in which case the tails on each side merge into one. Atoms and
+/// bins are sorted by center and cut into contiguous groups of roughly equal
+/// mass; each group becomes one bin (or atom if it has no spread) with the
+/// group's mass, mean and variance, so total mass, mean and variance of the
+/// whole distribution are preserved.
+///
+/// When the tails alone fill the budget the rest is folded in too: with a
+/// budget of two, the body merges into the heavier of the two tails, and
+/// with a budget of one everything becomes a single bin. A budget of zero
+/// leaves the components unchanged.
+fn compress_components(components: &[Component], max_components: usize) -> Vec<Component> {
+    if components.len() <= max_components || max_components == 0 {
+        return components.to_vec();
+    }
+    if max_components == 1 {
+        return vec![merge_group(components)];
+    }
+    let (mut tails, mut body): (Vec<Component>, Vec<Component>) =
+        components.iter().cloned().partition(|c| matches!(c, Component::Tail { .. }));
+    if tails.len() > max_components / 2 {
+        let (right, left): (Vec<Component>, Vec<Component>) =
+            tails.into_iter().partition(|c| matches!(c, Component::Tail { is_right: true, .. }));
+        tails = [right, left].iter().filter(|side| !side.is_empty()).map(|side| merge_tails(side)).collect();
+    }
+    if tails.len() >= max_components && !body.is_empty() {
+        let heavier = if get_weight(&tails[0]) >= get_weight(&tails[1]) { 0 } else { 1 };
+        let mut group = vec![tails[heavier].clone()];
+        group.append(&mut body);
+        tails[heavier] = merge_tails(&group);
+        return tails;
+    }
+    let groups = max_components.saturating_sub(tails.len()).max(1);
+    if body.len() <= groups {
+        tails.extend(body);
+        return tails;
+    }
+    body.sort_by(|a, b| component_center(a).total_cmp(&component_center(b)));
+
+    let body_mass = total_mass(&body);
+    let mut result = tails;
+    let mut group: Vec<Component> = Vec::new();
+    let mut cumulative = 0.0;
+    let mut next_cut = 1;
+    for c in body {
+        cumulative += get_weight(&c);
+        group.push(c);
+        if cumulative >= body_mass * next_cut as f64 / groups as f64 {
+            result.push(merge_group(&group));
+            group.clear();
+            while next_cut < groups && cumulative >= body_mass * next_cut as f64 / groups as f64 {
+                next_cut += 1;
+            }
+        }
+    }
+    if !group.is_empty() {
+        result.push(merge_group(&group));
+    }
+    result
+}
+
+/// Single tail with the same mass, mean and variance as a group of tails on
+/// one side
+fn merge_tails(group: &[Component]) -> Component {
+    let is_right = matches!(group[0], Component::Tail { is_right: true, .. });
+    let mass = total_mass(group);
+    if group.len() == 1 || mass <= 0.0 {
+        return group[0].clone();
+    }
+    let sd = mixture_variance(group).sqrt();
+    let mean = mixture_mean(group);
+    let x0 = if is_right { mean - sd } else { mean + sd };
+    Component::Tail { x0, mass, lambda: 1.0 / sd, is_right }
+}
+
+/// Single atom or bin with the same mass, mean and variance as a group
+fn merge_group(group: &[Component]) -> Component {
+    let mass = total_mass(group);
+    if group.len() == 1 || mass <= 0.0 {
+        return group[0].clone();
+    }
+    let mean = mixture_mean(group);
+    let var = mixture_variance(group);
+    if var <= 1e-24 * mean.abs().max(1.0) {
+        Component::Atom { x: mean, p: mass }
+    } else {
+        let half = (3.0 * var).sqrt();
+        Component::Bin { a: mean - half, b: mean + half, p: mass }
+    }
+}
+
+/// Extra evenly spaced points for `wasserstein_distance`, where tails make
+/// the CDFs curved between breakpoints
+const W1_GRID_POINTS: usize = 256;
+
+/// Wasserstein-1 distance between two distributions (each normalized to
+/// unit mass): the integral of |F - G|, with tails cut at 1e-9 mass
+///
+/// Between grid points |F - G| is integrated as a linear function, which is
+/// exact for atoms and bins.
+fn wasserstein_distance(p: &[Component], q: &[Component]) -> f64 {
+    let (mp, mq) = (total_mass(p), total_mass(q));
+    if mp <= 0.0 || mq <= 0.0 {
+        return f64::NAN;
+    }
+    let (lo_p, hi_p) = support_interval(p, 1e-9);
+    let (lo_q, hi_q) = support_interval(q, 1e-9);
+    let (lo, hi) = (lo_p.min(lo_q), hi_p.max(hi_q));
+    if !(lo.is_finite() && hi.is_finite()) || hi <= lo {
+        return 0.0;
+    }
+    let mut xs = breakpoints(p);
+    xs.extend(breakpoints(q));
+    xs.extend(linspace(lo, hi, W1_GRID_POINTS));
+    xs.retain(|&x| x >= lo && x <= hi);
+    xs.sort_by(|a, b| a.total_cmp(b));
+    xs.dedup();
+    xs.windows(2)
+        .map(|w| {
+            let du = mass_at_or_below(p, w[0]) / mp - mass_at_or_below(q, w[0]) / mq;
+            let dv = mass_below(p, w[1]) / mp - mass_below(q, w[1]) / mq;
+            let h = w[1] - w[0];
+            if du * dv >= 0.0 {
+                (du.abs() + dv.abs()) / 2.0 * h
+            } else {
+                (du * du + dv * dv) / (2.0 * (du.abs() + dv.abs())) * h
+            }
+        })
+        .sum()
+}
+
+/// A merged group of original components, its replacement and the
+/// mass-weighted W1 distance between the two
+fn merged_group_cost(members: &[Component]) -> (Component, f64) {
+    let merged = merge_group(members);
+    let cost = total_mass(members) * wasserstein_distance(members, std::slice::from_ref(&merged));
+    (merged, if cost.is_nan() { 0.0 } else { cost })
+}
+
+/// Reduce to at most `target_k` components by greedily merging the
+/// neighbouring pair (in center order) whose merge adds the least W1 error
+///
+/// Each merged group is replaced by the same moment-matched component as
+/// `compress_components`, but groups follow the shape instead of equal mass
+/// cuts, so separate modes stay separate. The error tracked is the sum of
+/// per-group W1 distances (weighted by mass), an upper bound on the total;
+/// merging stops early once the next merge would push it above `max_error`.
+/// Tails are kept as they are.
+fn greedy_wasserstein_merge(components: &[Component], target_k: usize, max_error: f64) -> Vec<Component> {
+    let (tails, mut body): (Vec<Component>, Vec<Component>) =
+        components.iter().cloned().partition(|c| matches!(c, Component::Tail { .. }));
+    body.sort_by(|a, b| component_center(a).total_cmp(&component_center(b)));
+    let total = total_mass(components);
+    let mut groups: Vec<Vec<Component>> = body.into_iter().map(|c| vec![c]).collect();
+    let mut merged: Vec<Component> = groups.iter().map(|g| g[0].clone()).collect();
+    let mut costs = vec![0.0; groups.len()];
+    let pair = |groups: &[Vec<Component>], i: usize| merged_group_cost(&[groups[i].as_slice(), groups[i + 1].as_slice()].concat());
+    let mut pairs: Vec<(Component, f64)> = (0..groups.len().saturating_sub(1)).map(|i| pair(&groups, i)).collect();
+    let mut error = 0.0;
+    while groups.len() + tails.len() > target_k && groups.len() > 1 {
+        let (i, increase) = (0..pairs.len())
+            .map(|i| (i, pairs[i].1 - costs[i] - costs[i + 1]))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        if total > 0.0 && (error + increase) / total > max_error {
+            break;
+        }
+        error += increase;
+        let (component, cost) = pairs.remove(i);
+        let next = groups.remove(i + 1);
+        groups[i].extend(next);
+        merged.remove(i + 1);
+        merged[i] = component;
+        costs.remove(i + 1);
+        costs[i] = cost;
+        if i > 0 {
+            pairs[i - 1] = pair(&groups, i - 1);
+        }
+        if i + 1 < groups.len() {
+            pairs[i] = pair(&groups, i);
+        }
+    }
+    let mut result = tails;
+    result.extend(merged);
+    result
+}
+
+/// Compressed distribution and how far it moved from the original
+#[wasm_bindgen]
+pub struct CompressedDistribution {
+    components: Vec<f64>,
+    w1_error: f64,
+}
+
+#[wasm_bindgen]
+impl CompressedDistribution {
+    /// Flat component array of the compressed distribution
+    pub fn components(&self) -> Float64Array {
+        Float64Array::from(self.components.as_slice())
+    }
+
+    /// Wasserstein-1 distance to the original (in units of x)
+    pub fn w1_error(&self) -> f64 {
+        self.w1_error
+    }
+}
+
+/// Reduce a distribution to at most `target_k` components, matching mass,
+/// mean and variance within each merged region
+///
+/// Tails count toward `target_k` and are merged per side only when they
+/// would take more than half of it.
+#[wasm_bindgen]
+pub fn dist_compress(components_data: Float64Array, target_k: u32) -> CompressedDistribution {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let compressed = compress_components(&components, target_k.max(1) as usize);
+    CompressedDistribution {
+        w1_error: wasserstein_distance(&components, &compressed),
+        components: serialize_components(&compressed),
+    }
+}
+
+/// Reduce a distribution by greedy W1-optimal merging of neighbouring
+/// components, stopping at `target_k` components or once the W1 error would
+/// exceed `max_error` (no budget when not given)
+///
+/// Slower than `dist_compress`, but keeps multimodal shapes intact.
+#[wasm_bindgen]
+pub fn dist_compress_wasserstein(components_data: Float64Array, target_k: u32, max_error: Option<f64>) -> CompressedDistribution {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let compressed = greedy_wasserstein_merge(&components, target_k.max(1) as usize, max_error.unwrap_or(f64::INFINITY));
+    CompressedDistribution {
+        w1_error: wasserstein_distance(&components, &compressed),
+        components: serialize_components(&compressed),
+    }
+}
+
+// ===========================================
+// Simplification
+// ===========================================
+
+/// Combine atoms lying within `x_tolerance` of each other and bins with the
+/// same endpoints (to within `x_tolerance`), summing their probabilities
+///
+/// Atoms are clustered in order of x, each cluster spanning at most
+/// `x_tolerance` from its first atom, and replaced by one atom at the
+/// cluster's mean, so mass and mean are unchanged. Tails are kept as they
+/// are. Output is atoms then bins in ascending order, then tails.
+fn simplify_components(components: &[Component], x_tolerance: f64) -> Vec<Component> {
+    let tol = if x_tolerance.is_finite() { x_tolerance.max(0.0) } else { 0.0 };
+    let mut atoms: Vec<(f64, f64)> = Vec::new();
+    let mut bins: Vec<(f64, f64, f64)> = Vec::new();
+    let mut tails: Vec<Component> = Vec::new();
+    for c in components {
+        match *c {
+            Component::Atom { x, p } => atoms.push((x, p)),
+            Component::Bin { a, b, p } => bins.push((a, b, p)),
+            Component::Tail { .. } => tails.push(c.clone()),
+        }
+    }
+    atoms.sort_by(|a, b| a.0.total_cmp(&b.0));
+    bins.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let mut result = Vec::with_capacity(components.len());
+    let mut i = 0;
+    while i < atoms.len() {
+        let start = atoms[i].0;
+        let (mut mass, mut moment) = (0.0, 0.0);
+        let mut j = i;
+        while j < atoms.len() && atoms[j].0 - start <= tol {
+            mass += atoms[j].1;
+            moment += atoms[j].0 * atoms[j].1;
+            j += 1;
+        }
+        let x = if j - i == 1 || mass == 0.0 { start } else { moment / mass };
+        result.push(Component::Atom { x, p: mass });
+        i = j;
+    }
+    let mut merged: Vec<(f64, f64, f64)> = Vec::with_capacity(bins.len());
+    for (a, b, p) in bins {
+        // Bins are sorted by left edge, so only the latest merged bins can match
+        match merged.iter_mut().rev().take_while(|m| a - m.0 <= tol).find(|m| (b - m.1).abs() <= tol) {
+            Some(m) => m.2 += p,
+            None => merged.push((a, b, p)),
+        }
+    }
+    result.extend(merged.into_iter().map(|(a, b, p)| Component::Bin { a, b, p }));
+    result.extend(tails);
+    result
+}
+
+/// Combine near-duplicate atoms and identical bins (see `x_tolerance`)
+///
+/// # Returns
+/// Flat array of the simplified distribution; mass and mean are unchanged
+#[wasm_bindgen]
+pub fn dist_simplify(components_data: Float64Array, x_tolerance: f64) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let simplified = simplify_components(&parse_components(&data), x_tolerance);
+    let serialized = serialize_components(&simplified);
+    Float64Array::from(serialized.as_slice())
+}
+
+/// Drop components whose weight is below `threshold`; returns the kept
+/// components and the dropped mass
+///
+/// With `redistribute` the kept weights are scaled up so the total mass is
+/// unchanged (when anything is kept).
+fn prune_components(components: &[Component], threshold: f64, redistribute: bool) -> (Vec<Component>, f64) {
+    let (kept, pruned): (Vec<Component>, Vec<Component>) =
+        components.iter().cloned().partition(|c| get_weight(c) >= threshold);
+    let pruned_mass = total_mass(&pruned);
+    let kept_mass = total_mass(&kept);
+    if !redistribute || pruned_mass == 0.0 || kept_mass <= 0.0 {
+        return (kept, pruned_mass);
+    }
+    let factor = (kept_mass + pruned_mass) / kept_mass;
+    (kept.iter().map(|c| scale_component(c, factor)).collect(), pruned_mass)
+}
+
+/// Distribution after pruning and the mass that was removed
+#[wasm_bindgen]
+pub struct PrunedDistribution {
+    components: Vec<f64>,
+    pruned_mass: f64,
+}
+
+#[wasm_bindgen]
+impl PrunedDistribution {
+    /// Flat component array of the kept components
+    pub fn components(&self) -> Float64Array {
+        Float64Array::from(self.components.as_slice())
+    }
+
+    /// Total weight of the dropped components
+    pub fn pruned_mass(&self) -> f64 {
+        self.pruned_mass
+    }
+}
+
+/// Drop components with weight below `threshold`, optionally scaling the
+/// rest up proportionally to keep the total mass
+#[wasm_bindgen]
+pub fn dist_prune(components_data: Float64Array, threshold: f64, redistribute: bool) -> PrunedDistribution {
+    let data: Vec<f64> = components_data.to_vec();
+    let (kept, pruned_mass) = prune_components(&parse_components(&data), threshold, redistribute);
+    PrunedDistribution { components: serialize_components(&kept), pruned_mass }
+}
+
+/// Project onto `n_bins` equal-width bins over (x_min, x_max]
+///
+/// Bin i holds the mass in (e_i, e_i+1], so an atom on an edge goes to the
+/// bin on its left and one at x_min lies outside. Mass outside the range is
+/// handled by `tail_policy`:
+///
+/// * 0 - dropped (the result is sub-normalized)
+/// * 1 - folded into the first and last bins
+/// * 2 - kept as the original components restricted to the outside
+fn rebin(components: &[Component], x_min: f64, x_max: f64, n_bins: usize, tail_policy: u32) -> Result<Vec<Component>, String> {
+    if !(x_min.is_finite() && x_max.is_finite()) || x_max <= x_min {
+        return Err("rebin range must be finite with x_min < x_max".to_string());
+    }
+    if n_bins == 0 {
+        return Err("n_bins must be positive".to_string());
+    }
+    if tail_policy > 2 {
+        return Err(format!("unknown tail policy {tail_policy}"));
+    }
+    let edges = linspace(x_min, x_max, n_bins + 1);
+    let cdf: Vec<f64> = edges.iter().map(|&e| mass_at_or_below(components, e)).collect();
+    let mut masses: Vec<f64> = cdf.windows(2).map(|w| (w[1] - w[0]).max(0.0)).collect();
+    if tail_policy == 1 {
+        masses[0] += cdf[0];
+        masses[n_bins - 1] += (total_mass(components) - cdf[n_bins]).max(0.0);
+    }
+    let bins = edges.windows(2).zip(masses).map(|(e, p)| Component::Bin { a: e[0], b: e[1], p });
+    if tail_policy != 2 {
+        return Ok(bins.collect());
+    }
+    let mut result = restrict(components, f64::NEG_INFINITY, x_min);
+    result.extend(bins);
+    result.extend(restrict(components, x_max, f64::INFINITY));
+    Ok(result)
+}
+
+/// Project a distribution onto `n_bins` uniform bins over (x_min, x_max],
+/// with outside mass dropped (policy 0), folded into the end bins (1) or
+/// kept as the original outside components (2)
+#[wasm_bindgen]
+pub fn dist_rebin(
+    components_data: Float64Array,
+    x_min: f64,
+    x_max: f64,
+    n_bins: u32,
+    tail_policy: u32,
+) -> Result<Float64Array, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let result = rebin(&parse_components(&data), x_min, x_max, n_bins as usize, tail_policy).map_err(|e| JsValue::from_str(&e))?;
+    let serialized = serialize_components(&result);
+    Ok(Float64Array::from(serialized.as_slice()))
+}
+
+// ===========================================
+// Running Sum Handle
+// ===========================================
+
+/// Stateful accumulator for the distribution of a running sum
+///
+/// Each `add` convolves the current total with a new distribution and
+/// compresses the result, so aggregates can grow as risks arrive without
+/// re-convolving everything from scratch.
+#[wasm_bindgen]
+pub struct RunningSum {
+    components: Vec<Component>,
+    mass_lost: f64,
+}
+
+#[wasm_bindgen]
+impl RunningSum {
+    /// Start from the empty sum (an atom at 0)
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> RunningSum {
+        RunningSum { components: vec![Component::Atom { x: 0.0, p: 1.0 }], mass_lost: 0.0 }
+    }
+
+    /// Add an independent distribution, keeping at most `max_components`
+    /// components (0 disables compression)
+    pub fn add(&mut self, component_data: Float64Array, max_components: u32) {
+        let data: Vec<f64> = component_data.to_vec();
+        self.add_components(&parse_components(&data), max_components as usize);
+    }
+
+    /// Current distribution of the sum
+    pub fn current(&self) -> Float64Array {
+        let serialized = serialize_components(&self.components);
+        Float64Array::from(serialized.as_slice())
+    }
+
+    /// Total probability mass dropped by unsupported pairings so far
+    pub fn mass_lost(&self) -> f64 {
+        self.mass_lost
+    }
+}
+
+impl RunningSum {
+    fn add_components(&mut self, components: &[Component], max_components: usize) {
+        let (sum, lost) = convolve_with_loss(&self.components, components);
+        self.mass_lost += lost;
+        self.components = if max_components > 0 { compress_components(&sum, max_components) } else { sum };
+    }
+}
+
+impl Default for RunningSum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ===========================================
+// Distribution Handle
+// ===========================================
+
+/// A distribution parsed once from the flat format, with its alias table
+/// built up front, for callers that query many statistics or draw repeatedly
+/// from the same distribution
+#[wasm_bindgen]
+pub struct Distribution {
+    alias_table: AliasTable,
+}
+
+#[wasm_bindgen]
+impl Distribution {
+    /// Parse the flat component array and build the sampling table
+    #[wasm_bindgen(constructor)]
+    pub fn new(components_data: Float64Array) -> Distribution {
+        let data: Vec<f64> = components_data.to_vec();
+        Distribution::from_components(parse_components(&data))
+    }
+
+    /// Components in flat array format
+    pub fn components(&self) -> Float64Array {
+        let serialized = serialize_components(self.parts());
+        Float64Array::from(serialized.as_slice())
+    }
+
+    pub fn num_components(&self) -> u32 {
+        self.parts().len() as u32
+    }
+
+    pub fn mean(&self) -> f64 {
+        mixture_mean(self.parts())
+    }
+
+    pub fn variance(&self) -> f64 {
+        mixture_variance(self.parts())
+    }
+
+    pub fn std(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    pub fn skewness(&self) -> f64 {
+        mixture_skewness(self.parts())
+    }
+
+    /// Excess kurtosis
+    pub fn kurtosis(&self) -> f64 {
+        mixture_excess_kurtosis(self.parts())
+    }
+
+    /// P(X > x)
+    pub fn prob_gt(&self, x: f64) -> f64 {
+        mixture_prob_gt(self.parts(), x)
+    }
+
+    /// P(X < x)
+    pub fn prob_lt(&self, x: f64) -> f64 {
+        mixture_prob_lt(self.parts(), x)
+    }
+
+    /// P(X <= x)
+    pub fn cdf(&self, x: f64) -> f64 {
+        mixture_cdf(self.parts(), x)
+    }
+
+    pub fn quantile(&self, p: f64) -> f64 {
+        mixture_quantile(self.parts(), p)
+    }
+
+    pub fn median(&self) -> f64 {
+        mixture_quantile(self.parts(), 0.5)
+    }
+
+    /// Value at Risk at confidence level alpha (loss convention)
+    pub fn var(&self, alpha: f64) -> f64 {
+        value_at_risk(self.parts(), alpha)
+    }
+
+    /// Conditional VaR at confidence level alpha (loss convention)
+    pub fn cvar(&self, alpha: f64) -> f64 {
+        conditional_var(self.parts(), alpha)
+    }
+
+    /// Draw `n` i.i.d. samples; the same seed gives the same draws
+    pub fn sample(&self, n: u32, seed: u64) -> Float64Array {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let samples = sample_n(&self.alias_table, n as usize, &mut rng);
+        Float64Array::from(samples.as_slice())
+    }
+
+    /// Draw `n` samples with exact per-component counts (see
+    /// `dist_sample_stratified`)
+    pub fn sample_stratified(&self, n: u32, seed: u64) -> Float64Array {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let samples = sample_n_stratified(&self.alias_table, n as usize, &mut rng);
+        Float64Array::from(samples.as_slice())
+    }
+}
+
+impl Distribution {
+    fn from_components(components: Vec<Component>) -> Distribution {
+        Distribution { alias_table: AliasTable::new(components) }
+    }
+
+    /// The cached components (owned by the alias table)
+    fn parts(&self) -> &[Component] {
+        &self.alias_table.components
+    }
+}
+
+// ===========================================
+// Grid Evaluation
+// ===========================================
+
+/// n evenly spaced points from x_min to x_max inclusive
+fn linspace(x_min: f64, x_max: f64, n: usize) -> Vec<f64> {
+    match n {
+        0 => vec![],
+        1 => vec![x_min],
+        _ => {
+            let step = (x_max - x_min) / (n - 1) as f64;
+            (0..n).map(|i| if i + 1 == n { x_max } else { x_min + i as f64 * step }).collect()
+        }
+    }
+}
+
+/// Normalized density at each x. Atoms are drawn as spikes of width
+/// `atom_width` (density p / width around the atom) or left out when the
+/// width is not positive.
+fn density_at(components: &[Component], xs: &[f64], atom_width: f64) -> Vec<f64> {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return vec![0.0; xs.len()];
+    }
+    let half = atom_width / 2.0;
+    xs.iter()
+        .map(|&x| {
+            let mut d = mass_density(components, x);
+            if atom_width > 0.0 {
+                for c in components {
+                    match c {
+                        Component::Atom { x: ax, p } if x >= ax - half && x < ax + half => d += p / atom_width,
+                        Component::Bin { a, b, p } if a == b && x >= a - half && x < a + half => d += p / atom_width,
+                        _ => {}
+                    }
+                }
+            }
+            d / total
+        })
+        .collect()
+}
+
+/// Density on `n_points` evenly spaced points over [x_min, x_max], with the
+/// same tail semantics as the rest of the engine. Atoms become spikes of
+/// width `atom_width`; pass 0 to leave them out and plot them separately.
+#[wasm_bindgen]
+pub fn dist_density_grid(
+    components_data: Float64Array,
+    x_min: f64,
+    x_max: f64,
+    n_points: u32,
+    atom_width: f64,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let xs = linspace(x_min, x_max, n_points as usize);
+    let density = density_at(&components, &xs, atom_width);
+    Float64Array::from(density.as_slice())
+}
+
+/// P(X <= x) at each x
+fn cdf_at(components: &[Component], xs: &[f64]) -> Vec<f64> {
+    xs.iter().map(|&x| mixture_cdf(components, x)).collect()
+}
+
+/// P(X > x) at each x
+fn survival_at(components: &[Component], xs: &[f64]) -> Vec<f64> {
+    xs.iter().map(|&x| mixture_prob_gt(components, x)).collect()
+}
+
+/// CDF on `n_points` evenly spaced points over [x_min, x_max]
+#[wasm_bindgen]
+pub fn dist_cdf_grid(components_data: Float64Array, x_min: f64, x_max: f64, n_points: u32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let values = cdf_at(&components, &linspace(x_min, x_max, n_points as usize));
+    Float64Array::from(values.as_slice())
+}
+
+/// Survival function P(X > x) on `n_points` evenly spaced points over [x_min, x_max]
+#[wasm_bindgen]
+pub fn dist_survival_grid(components_data: Float64Array, x_min: f64, x_max: f64, n_points: u32) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let values = survival_at(&components, &linspace(x_min, x_max, n_points as usize));
+    Float64Array::from(values.as_slice())
+}
+
+/// CDF at caller-supplied points
+#[wasm_bindgen]
+pub fn dist_cdf_points(components_data: Float64Array, xs: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let values = cdf_at(&components, &xs.to_vec());
+    Float64Array::from(values.as_slice())
+}
+
+/// Survival function P(X > x) at caller-supplied points
+#[wasm_bindgen]
+pub fn dist_survival_points(components_data: Float64Array, xs: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let values = survival_at(&components, &xs.to_vec());
+    Float64Array::from(values.as_slice())
+}
+
+/// P(X > t) for every threshold, in input order, with the same per-component
+/// conventions as `mixture_prob_gt`
+///
+/// Thresholds are sorted once; each component then adds its full mass to the
+/// prefix of thresholds below it through a difference array and only visits
+/// the thresholds it partially covers. NaN thresholds give NaN.
+fn exceedance_curve(components: &[Component], thresholds: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..thresholds.len()).filter(|&i| !thresholds[i].is_nan()).collect();
+    order.sort_by(|&i, &j| thresholds[i].total_cmp(&thresholds[j]));
+    let ts: Vec<f64> = order.iter().map(|&i| thresholds[i]).collect();
+    let m = ts.len();
+
+    // diff[k] accumulates mass applied to all sorted positions < k
+    let mut diff = vec![0.0; m + 1];
+    let mut partial = vec![0.0; m];
+    let below = |x: f64| ts.partition_point(|&t| t < x);
+    for c in components {
+        match c {
+            Component::Atom { x, p } => diff[below(*x)] += p,
+            Component::Bin { a, b, p } => {
+                let (ka, kb) = (below(*a), below(*b));
+                diff[ka] += p;
+                for k in ka..kb {
+                    partial[k] += p * (b - ts[k]) / (b - a);
+                }
+            }
+            Component::Tail { x0, mass, lambda, is_right } => {
+                let k0 = below(*x0);
+                if *is_right {
+                    diff[k0] += mass;
+                    for k in k0..m {
+                        partial[k] += mass * (-(ts[k] - x0) * lambda).exp();
+                    }
+                } else {
+                    for k in 0..k0 {
+                        partial[k] += -mass * (-(x0 - ts[k]) * lambda).exp_m1();
+                    }
+                }
+            }
+        }
+    }
+
+    let total = total_mass(components);
+    let mut out = vec![f64::NAN; thresholds.len()];
+    let mut full = 0.0;
+    for k in (0..m).rev() {
+        full += diff[k + 1];
+        out[order[k]] = if total == 0.0 { 0.0 } else { (full + partial[k]) / total };
+    }
+    out
+}
+
+/// Exceedance probabilities P(X > t) for an array of thresholds (any order)
+#[wasm_bindgen]
+pub fn dist_exceedance_curve(components_data: Float64Array, thresholds: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let values = exceedance_curve(&components, &thresholds.to_vec());
+    Float64Array::from(values.as_slice())
+}
+
+// ===========================================
+// Mode Detection
+// ===========================================
+
+/// Point masses as (x, p): atoms and zero-width bins
+fn point_masses(components: &[Component]) -> Vec<(f64, f64)> {
+    components
+        .iter()
+        .filter_map(|c| match c {
+            Component::Atom { x, p } if *p > 0.0 => Some((*x, *p)),
+            Component::Bin { a, b, p } if a == b && *p > 0.0 => Some((*a, *p)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Components with density at `mid`, i.e. those active on the open segment
+/// between the breakpoints around it
+fn segment_active(components: &[Component], mid: f64) -> Vec<&Component> {
+    components
+        .iter()
+        .filter(|c| match c {
+            Component::Bin { a, b, p } => a < b && *p > 0.0 && *a <= mid && mid < *b,
+            Component::Tail { .. } => component_pdf(c, mid) > 0.0,
+            Component::Atom { .. } => false,
+        })
+        .collect()
+}
+
+/// Density and slope at x of the components active on an open segment,
+/// extended continuously to the segment ends
+fn segment_density(active: &[&Component], x: f64) -> (f64, f64) {
+    let mut value = 0.0;
+    let mut slope = 0.0;
+    for c in active {
+        match c {
+            Component::Bin { a, b, p } => value += p / (b - a),
+            Component::Tail { x0, mass, lambda, is_right } => {
+                let d = if x.is_finite() { mass * lambda * (-(x - x0).abs() * lambda).exp() } else { 0.0 };
+                value += d;
+                slope += if *is_right { -lambda * d } else { lambda * d };
+            }
+            Component::Atom { .. } => {}
+        }
+    }
+    (value, slope)
+}
+
+/// Local maxima of the continuous part of the density as (x, density),
+/// unnormalized. Between breakpoints the density is a sum of a constant and
+/// exponentials, hence convex, so maxima sit at breakpoints (one-sided
+/// limits) or on flat stretches, which are reported by their midpoint.
+fn continuous_modes(components: &[Component]) -> Vec<(f64, f64)> {
+    let (lo, hi) = support_bounds(components);
+    if lo >= hi {
+        return vec![];
+    }
+    let mut edges = vec![lo];
+    edges.extend(breakpoints(components).into_iter().filter(|&x| x > lo && x < hi));
+    edges.push(hi);
+
+    // Profile of the density from left to right as (x_start, x_end, value);
+    // x_start < x_end marks a flat stretch
+    let mut profile: Vec<(f64, f64, f64)> = vec![(lo, lo, 0.0)];
+    for w in edges.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        let mid = match (a.is_finite(), b.is_finite()) {
+            (true, true) => 0.5 * (a + b),
+            (true, false) => a + 1.0,
+            (false, true) => b - 1.0,
+            (false, false) => 0.0,
+        };
+        let active = segment_active(components, mid);
+        let (fa, da) = segment_density(&active, a);
+        let (fb, db) = segment_density(&active, b);
+        if da == 0.0 && db == 0.0 {
+            profile.push((a, b, fa));
+        } else {
+            profile.push((a, a, fa));
+            if da < 0.0 && db > 0.0 {
+                // Convex dip strictly below both ends
+                profile.push((mid, mid, fa.min(fb) * 0.5));
+            }
+            profile.push((b, b, fb));
+        }
+    }
+    profile.push((hi, hi, 0.0));
+
+    let scale = profile.iter().fold(0.0f64, |m, s| m.max(s.2));
+    if scale <= 0.0 {
+        return vec![];
+    }
+    let tol = 1e-12 * scale;
+
+    // Collapse runs of equal values, then keep runs above both neighbours
+    let mut runs: Vec<(f64, f64, f64)> = Vec::new();
+    for &(s, e, v) in &profile {
+        match runs.last_mut() {
+            Some(last) if (last.2 - v).abs() <= tol => last.1 = e,
+            _ => runs.push((s, e, v)),
+        }
+    }
+    let mut modes = Vec::new();
+    for i in 1..runs.len().saturating_sub(1) {
+        let (s, e, v) = runs[i];
+        if v > runs[i - 1].2 && v > runs[i + 1].2 && s.is_finite() && e.is_finite() {
+            modes.push((0.5 * (s + e), v));
+        }
+    }
+    modes
+}
+
+/// Most likely outcome. The heaviest point mass wins whenever there is one
+/// (it is an infinite spike in the density); otherwise the global maximum of
+/// the continuous density. NaN for an empty distribution.
+fn mixture_mode(components: &[Component]) -> f64 {
+    let masses = point_masses(components);
+    let candidates = if masses.is_empty() { continuous_modes(components) } else { masses };
+    candidates
+        .iter()
+        .fold(None, |best: Option<(f64, f64)>, &(x, v)| match best {
+            Some((_, bv)) if bv >= v => best,
+            _ => Some((x, v)),
+        })
+        .map_or(f64::NAN, |(x, _)| x)
+}
+
+/// All local maxima of the density in increasing order: every point mass
+/// plus the local maxima of the continuous part
+fn mixture_modes(components: &[Component]) -> Vec<f64> {
+    let mut xs: Vec<f64> = point_masses(components).into_iter().map(|(x, _)| x).collect();
+    xs.extend(continuous_modes(components).into_iter().map(|(x, _)| x));
+    xs.sort_by(|a, b| a.total_cmp(b));
+    xs.dedup();
+    xs
+}
+
+/// Mode (most likely outcome) of the distribution
+#[wasm_bindgen]
+pub fn dist_mode(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_mode(&components)
+}
+
+/// Locations of all local maxima of the density, sorted
+#[wasm_bindgen]
+pub fn dist_modes(components_data: Float64Array) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    
+    let modes = mixture_modes(&components);
+    Float64Array::from(modes.as_slice())
+}
+
+// ===========================================
+// Entropy
+// ===========================================
+
+/// Simpson panels per segment for the parts of the density with tails
+const ENTROPY_PANELS: usize = 2000;
+
+/// -f ln f, with 0 ln 0 = 0
+fn neg_f_ln_f(f: f64) -> f64 {
+    if f > 0.0 { -f * f.ln() } else { 0.0 }
+}
+
+/// Entropy of the normalized mixture in nats
+///
+/// Convention for mixed distributions: entropy relative to counting measure
+/// on the atoms plus Lebesgue measure on the rest, i.e.
+/// H = -sum p_i ln p_i - integral f ln f dx, where p_i are the atom
+/// probabilities and f the continuous density (both normalized so that
+/// sum p_i + integral f = 1). Pure atoms give the Shannon entropy, pure
+/// densities the differential entropy.
+fn mixture_entropy(components: &[Component]) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return f64::NAN;
+    }
+    // Merge coincident atoms before taking logs
+    let mut masses = point_masses(components);
+    masses.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut discrete = 0.0;
+    let mut i = 0;
+    while i < masses.len() {
+        let mut p = 0.0;
+        let x = masses[i].0;
+        while i < masses.len() && masses[i].0 == x {
+            p += masses[i].1;
+            i += 1;
+        }
+        discrete += neg_f_ln_f(p / total);
+    }
+
+    let (lo, hi) = support_bounds(components);
+    let mut edges = vec![lo];
+    edges.extend(breakpoints(components).into_iter().filter(|&x| x > lo && x < hi));
+    edges.push(hi);
+    let min_rate = components
+        .iter()
+        .filter_map(|c| match c {
+            Component::Tail { lambda, mass, .. } if *mass > 0.0 => Some(*lambda),
+            _ => None,
+        })
+        .fold(f64::INFINITY, f64::min);
+
+    let mut continuous = 0.0;
+    for w in edges.windows(2) {
+        let (mut a, mut b) = (w[0], w[1]);
+        if a >= b {
+            continue;
+        }
+        // e^-40 of the slowest tail is far below f64 resolution of the total
+        if a == f64::NEG_INFINITY {
+            a = b - 40.0 / min_rate;
+        }
+        if b == f64::INFINITY {
+            b = a + 40.0 / min_rate;
+        }
+        let mid = 0.5 * (a + b);
+        // Evaluate the segment's own density so the ends see the inside limits
+        let active = segment_active(components, mid);
+        let f = |x: f64| segment_density(&active, x).0 / total;
+        if !tails_active(components, w[0], w[1]) {
+            continuous += neg_f_ln_f(f(mid)) * (b - a);
+            continue;
+        }
+        let h = (b - a) / ENTROPY_PANELS as f64;
+        let mut sum = neg_f_ln_f(f(a)) + neg_f_ln_f(f(b));
+        for k in 1..ENTROPY_PANELS {
+            let weight = if k % 2 == 1 { 4.0 } else { 2.0 };
+            sum += weight * neg_f_ln_f(f(a + k as f64 * h));
+        }
+        continuous += sum * h / 3.0;
+    }
+    discrete + continuous
+}
+
+/// Entropy in nats: Shannon entropy of the atoms plus differential entropy
+/// of the continuous part (see `mixture_entropy` for the convention)
+#[wasm_bindgen]
+pub fn dist_entropy(components_data: Float64Array) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mixture_entropy(&components)
+}
+
+// ===========================================
+// Conditional Expectations
+// ===========================================
+
+/// E[X | X > t] (NaN when no mass lies above t)
+fn cond_mean_gt(components: &[Component], t: f64) -> f64 {
+    // Summed per component so deep tail mass does not cancel against the total
+    let mass = mixture_prob_gt(components, t) * total_mass(components);
+    if mass <= 0.0 {
+        return f64::NAN;
+    }
+    let partial: f64 = components.iter().map(|c| component_partial_expectation(c, t, f64::INFINITY)).sum();
+    partial / mass
+}
+
+/// E[X | X < t] (NaN when no mass lies below t)
+fn cond_mean_lt(components: &[Component], t: f64) -> f64 {
+    let mass = mass_below(components, t);
+    if mass <= 0.0 {
+        return f64::NAN;
+    }
+    let partial: f64 = components.iter().map(|c| component_partial_expectation(c, f64::NEG_INFINITY, t)).sum();
+    partial / mass
+}
+
+/// Conditional mean above a threshold, E[X | X > t] (strict, so an atom at t
+/// is excluded). NaN when P(X > t) = 0.
+#[wasm_bindgen]
+pub fn dist_cond_mean_gt(components_data: Float64Array, t: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    cond_mean_gt(&components, t)
+}
+
+/// Conditional mean below a threshold, E[X | X < t] (strict). NaN when
+/// P(X < t) = 0.
+#[wasm_bindgen]
+pub fn dist_cond_mean_lt(components_data: Float64Array, t: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    cond_mean_lt(&components, t)
+}
+
+/// E[(X - d)+] restricted to a single component (unnormalized), closed form
+fn component_stop_loss(c: &Component, d: f64) -> f64 {
+    match c {
+        Component::Atom { x, p } => p * (x - d).max(0.0),
+        Component::Bin { a, b, p } => {
+            if d >= *b {
+                0.0
+            } else if d <= *a {
+                p * ((a + b) / 2.0 - d)
+            } else {
+                p * (b - d) * (b - d) / (2.0 * (b - a))
+            }
+        }
+        Component::Tail { x0, mass, lambda, is_right } => {
+            if *is_right {
+                if d <= *x0 { mass * (x0 + 1.0 / lambda - d) } else { mass * (-(d - x0) * lambda).exp() / lambda }
+            } else if d >= *x0 {
+                0.0
+            } else {
+                // X = x0 - E on (d, x0]: integral of (x - d) f(x) = L + expm1(-lambda L) / lambda
+                let span = x0 - d;
+                mass * (span + (-lambda * span).exp_m1() / lambda)
+            }
+        }
+    }
+}
+
+/// E[X - d | X > d] (NaN when no mass lies above d)
+fn mean_excess(components: &[Component], d: f64) -> f64 {
+    let mass = mixture_prob_gt(components, d) * total_mass(components);
+    if mass <= 0.0 {
+        return f64::NAN;
+    }
+    components.iter().map(|c| component_stop_loss(c, d)).sum::<f64>() / mass
+}
+
+/// Mean excess function e(d) = E[X - d | X > d]. Computed from the excess
+/// over d directly, so an exponential tail beyond the body gives exactly
+/// 1 / lambda even for large d. NaN when P(X > d) = 0.
+#[wasm_bindgen]
+pub fn dist_mean_excess(components_data: Float64Array, d: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    mean_excess(&components, d)
+}
+
+/// Stop-loss premium E[(X - d)+] of the normalized mixture
+fn stop_loss(components: &[Component], d: f64) -> f64 {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    components.iter().map(|c| component_stop_loss(c, d)).sum::<f64>() / total
+}
+
+/// Stop-loss premium E[(X - d)+]: expected payout above deductible d
+#[wasm_bindgen]
+pub fn dist_stop_loss(components_data: Float64Array, d: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    stop_loss(&components, d)
+}
+
+/// Expected loss to a layer, E[min((X - a)+, l)] = E[(X - a)+] - E[(X - a - l)+]
+fn layer_mean(components: &[Component], attachment: f64, limit: f64) -> f64 {
+    if limit <= 0.0 {
+        return 0.0;
+    }
+    let upper = if limit == f64::INFINITY { 0.0 } else { stop_loss(components, attachment + limit) };
+    stop_loss(components, attachment) - upper
+}
+
+/// Expected loss to a layer with the given attachment point and limit,
+/// E[min(max(X - attachment, 0), limit)]. An infinite limit gives the
+/// stop-loss premium; a non-positive limit gives 0.
+#[wasm_bindgen]
+pub fn dist_layer_mean(components_data: Float64Array, attachment: f64, limit: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    layer_mean(&components, attachment, limit)
+}
+
+// ===========================================
+// Aggregate Loss
+// ===========================================
+
+/// Mass treated as negligible when truncating lattice distributions
+const LATTICE_TAIL_MASS: f64 = 1e-12;
+
+/// Longest severity or aggregate lattice produced
+const LATTICE_MAX_POINTS: usize = 1 << 20;
+
+/// Discretize a non-negative severity on the lattice 0, h, 2h, ... by the
+/// method of rounding: f_j = F((j + 1/2) h) - F((j - 1/2) h), normalized and
+/// truncated once the remaining tail is negligible (the remainder goes to the
+/// last point)
+fn discretize_severity(components: &[Component], span: f64) -> Result<Vec<f64>, String> {
+    let total = total_mass(components);
+    if total <= 0.0 {
+        return Err("severity distribution is empty".to_string());
+    }
+    if !(span > 0.0 && span.is_finite()) {
+        return Err("lattice span must be positive".to_string());
+    }
+    if mass_below(components, 0.0) > 0.0 {
+        return Err("severity must be non-negative".to_string());
+    }
+    let mut f = Vec::new();
+    let mut prev = 0.0;
+    while prev < 1.0 - LATTICE_TAIL_MASS && f.len() < LATTICE_MAX_POINTS {
+        let cdf = (mass_at_or_below(components, (f.len() as f64 + 0.5) * span) / total).min(1.0);
+        f.push(cdf - prev);
+        prev = cdf;
+    }
+    if let Some(last) = f.last_mut() {
+        *last += 1.0 - prev;
+    }
+    Ok(f)
+}
+
+/// Aggregate pmf g_k = P(S = k h) of S = X_1 + ... + X_N by Panjer's
+/// recursion, g_k = sum_{j=1..k} (a + b j / k) f_j g_{k-j} / (1 - a f_0)
+///
+/// A binomial count with p = 1 (N = n surely) has no finite Panjer
+/// coefficients; its aggregate is the n-fold convolution of f.
+fn panjer_recursion(frequency: Frequency, f: &[f64]) -> Result<Vec<f64>, String> {
+    if let Frequency::Binomial { n, p } = frequency {
+        if p == 1.0 {
+            return Ok(lattice_power(f, n));
+        }
+    }
+    let (a, b) = frequency.panjer_ab();
+    let g0 = frequency.pgf(f[0]);
+    if g0 <= 0.0 {
+        return Err("P(S = 0) underflows; use the FFT method for large claim counts".to_string());
+    }
+    let scale = 1.0 / (1.0 - a * f[0]);
+    let mut g = vec![g0];
+    let mut total = g0;
+    while total < 1.0 - LATTICE_TAIL_MASS && g.len() < LATTICE_MAX_POINTS {
+        let k = g.len();
+        let kf = k as f64;
+        let gk: f64 = (1..=k.min(f.len() - 1)).map(|j| (a + b * j as f64 / kf) * f[j] * g[k - j]).sum::<f64>() * scale;
+        g.push(gk);
+        total += gk;
+    }
+    Ok(g)
+}
+
+/// n-fold convolution of a lattice pmf, truncated at `LATTICE_MAX_POINTS`
+fn lattice_power(f: &[f64], n: u32) -> Vec<f64> {
+    let mut g = vec![1.0];
+    for _ in 0..n {
+        let len = (g.len() + f.len() - 1).min(LATTICE_MAX_POINTS);
+        let mut next = vec![0.0; len];
+        for (i, gi) in g.iter().enumerate() {
+            for (j, fj) in f.iter().enumerate().take(len.saturating_sub(i)) {
+                next[i + j] += gi * fj;
+            }
+        }
+        g = next;
+    }
+    g
+}
+
+fn complex_mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn complex_div(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let d = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / d, (a.1 * b.0 - a.0 * b.1) / d)
+}
+
+fn complex_exp(z: (f64, f64)) -> (f64, f64) {
+    let r = z.0.exp();
+    (r * z.1.cos(), r * z.1.sin())
+}
+
+/// Principal power z^w for real w
+fn complex_powf(z: (f64, f64), w: f64) -> (f64, f64) {
+    let r = z.0.hypot(z.1);
+    if r == 0.0 {
+        return (0.0, 0.0);
+    }
+    complex_exp((w * r.ln(), w * z.1.atan2(z.0)))
+}
+
+/// In-place iterative radix-2 FFT; `values.len()` must be a power of two.
+/// `inverse` uses the conjugate twiddles and divides by n.
+fn fft(values: &mut [(f64, f64)], inverse: bool) {
+    let n = values.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * std::f64::consts::PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let w = (angle * k as f64).cos();
+                let w = (w, (angle * k as f64).sin());
+                let u = values[start + k];
+                let v = complex_mul(values[start + k + len / 2], w);
+                values[start + k] = (u.0 + v.0, u.1 + v.1);
+                values[start + k + len / 2] = (u.0 - v.0, u.1 - v.1);
+            }
+        }
+        len <<= 1;
+    }
+    if inverse {
+        for v in values.iter_mut() {
+            v.0 /= n as f64;
+            v.1 /= n as f64;
+        }
+    }
+}
+
+/// Probabilities below this are FFT round-off and dropped
+const FFT_ROUNDOFF: f64 = 1e-15;
+
+/// Aggregate pmf on the lattice via FFT: g = IFFT(P_N(FFT(f))) on `n_points`
+/// points (rounded up to a power of two; 0 sizes the lattice to cover the
+/// aggregate mean plus 20 standard deviations)
+///
+/// Mass beyond the lattice wraps around, so the lattice must cover the
+/// aggregate's effective support; one shorter than the severity lattice
+/// itself is an error.
+fn fft_aggregate(frequency: Frequency, f: &[f64], n_points: usize) -> Result<Vec<f64>, String> {
+    let n = if n_points == 0 {
+        let ex: f64 = f.iter().enumerate().map(|(j, p)| j as f64 * p).sum();
+        let ex2: f64 = f.iter().enumerate().map(|(j, p)| (j * j) as f64 * p).sum();
+        let mean = frequency.mean() * ex;
+        let var = frequency.mean() * (ex2 - ex * ex) + frequency.variance() * ex * ex;
+        (mean + 20.0 * var.sqrt()) as usize + f.len()
+    } else {
+        n_points
+    }
+    .next_power_of_two();
+    if n > LATTICE_MAX_POINTS {
+        return Err(format!("FFT lattice of {} points exceeds the limit of {}", n, LATTICE_MAX_POINTS));
+    }
+    if n < f.len() {
+        return Err(format!("FFT lattice of {} points is shorter than the {}-point severity", n, f.len()));
+    }
+    let mut values = vec![(0.0, 0.0); n];
+    for (j, &p) in f.iter().enumerate() {
+        values[j].0 += p;
+    }
+    fft(&mut values, false);
+    for v in values.iter_mut() {
+        *v = frequency.pgf_complex(*v);
+    }
+    fft(&mut values, true);
+    Ok(values.into_iter().map(|v| if v.0 > FFT_ROUNDOFF { v.0 } else { 0.0 }).collect())
+}
+
+/// Atoms at k * span for a lattice pmf
+fn lattice_atoms(g: &[f64], span: f64) -> Vec<Component> {
+    g.iter()
+        .enumerate()
+        .filter(|(_, &p)| p > 0.0)
+        .map(|(k, &p)| Component::Atom { x: k as f64 * span, p })
+        .collect()
+}
+
+/// Aggregate loss distribution of N claims with the given severity, computed
+/// by Panjer's recursion on the severity rounded to a lattice of spacing
+/// `grid`
+///
+/// `freq_params` uses the flat frequency format (`[0, lambda]` Poisson,
+/// `[1, r, p]` negative binomial, `[2, n, p]` binomial); the result is a
+/// set of atoms on the lattice. The severity must be non-negative.
+#[wasm_bindgen]
+pub fn compound_distribution(
+    freq_params: Float64Array,
+    severity_data: Float64Array,
+    grid: f64,
+) -> Result<Float64Array, JsValue> {
+    let params = freq_params.to_vec();
+    let data: Vec<f64> = severity_data.to_vec();
+    let components = parse_components(&data);
+    let frequency = Frequency::parse(&params).map_err(|e| JsValue::from_str(&e))?;
+    let f = discretize_severity(&components, grid).map_err(|e| JsValue::from_str(&e))?;
+    let g = panjer_recursion(frequency, &f).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(serialize_components(&lattice_atoms(&g, grid)).as_slice()))
+}
+
+/// Aggregate loss distribution computed by FFT on a lattice of spacing
+/// `grid`; the faster alternative to `compound_distribution` for large
+/// expected claim counts
+///
+/// `n_points` is the lattice length (rounded up to a power of two; 0 picks
+/// one covering the mean plus 20 standard deviations). Mass beyond the
+/// lattice wraps around, so it must cover the aggregate's support; a lattice
+/// shorter than the discretized severity is an error.
+#[wasm_bindgen]
+pub fn compound_distribution_fft(
+    freq_params: Float64Array,
+    severity_data: Float64Array,
+    grid: f64,
+    n_points: u32,
+) -> Result<Float64Array, JsValue> {
+    let params = freq_params.to_vec();
+    let data: Vec<f64> = severity_data.to_vec();
+    let components = parse_components(&data);
+    let frequency = Frequency::parse(&params).map_err(|e| JsValue::from_str(&e))?;
+    let f = discretize_severity(&components, grid).map_err(|e| JsValue::from_str(&e))?;
+    let g = fft_aggregate(frequency, &f, n_points as usize).map_err(|e| JsValue::from_str(&e))?;
+    Ok(Float64Array::from(serialize_components(&lattice_atoms(&g, grid)).as_slice()))
+}
+
+/// Lundberg adjustment coefficient R > 0 solving E[exp(-R (X + c))] = 1 for
+/// per-step increment X (losses negative, as in the simulation engine) and
+/// premium c = (1 + loading) E[-X]
+///
+/// With loss L = -X this is E[exp(R (L - c))] = 1. Its log is convex, zero
+/// at R = 0 and decreasing there when the loading is positive, so the root
+/// is bracketed by doubling and then bisected; where the MGF diverges the
+/// function counts as positive. NaN without a positive loading and mean
+/// loss; infinite when X + c >= 0 surely (ruin impossible).
+fn lundberg_coefficient(components: &[Component], loading: f64) -> f64 {
+    if total_mass(components) <= 0.0 {
+        return f64::NAN;
+    }
+    let mean_loss = -mixture_mean(components);
+    if loading.is_nan() || loading <= 0.0 || mean_loss.is_nan() || mean_loss <= 0.0 {
+        return f64::NAN;
+    }
+    let premium = (1.0 + loading) * mean_loss;
+    if support_bounds(components).0 >= -premium {
+        return f64::INFINITY;
+    }
+    let net: Vec<Component> = negate_components(components).iter().map(|c| shift_component(c, -premium)).collect();
+    mgf_root(&net, mean_loss)
+}
+
+/// Positive root r of E[exp(r Y)] = 1 for Y with negative mean and some mass
+/// above zero; `scale` (a typical magnitude of Y) seeds the bracket
+fn mgf_root(net: &[Component], scale: f64) -> f64 {
+    let positive = |r: f64| {
+        let m = mixture_mgf(net, r);
+        m.is_nan() || m > 1.0
+    };
+    let mut lo = 0.0;
+    let mut hi = 1.0 / scale;
+    while !positive(hi) {
+        lo = hi;
+        hi *= 2.0;
+    }
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        if positive(mid) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Lundberg adjustment coefficient R for a per-step increment distribution
+/// (losses negative, the same data `run_monte_carlo` takes) with premium
+/// (1 + premium_loading) E[-X] added per step: the positive root of
+/// E[exp(-R (X + c))] = 1. Infinite when X + c is never negative; NaN for a
+/// non-positive loading or mean loss.
+#[wasm_bindgen]
+pub fn adjustment_coefficient(components_data: Float64Array, premium_loading: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    lundberg_coefficient(&components, premium_loading)
+}
+
+/// Lundberg upper bound exp(-R u) on the infinite-horizon ruin probability
+/// from initial surplus u, with R from `adjustment_coefficient` (same
+/// increment convention)
+#[wasm_bindgen]
+pub fn lundberg_bound(components_data: Float64Array, premium_loading: f64, init_wealth: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    (-lundberg_coefficient(&components, premium_loading) * init_wealth).exp()
+}
+
+// ===========================================
+// Analytic Ruin
+// ===========================================
+
+/// Up-step a, down-step b and up-probability of an atom pair {+a, -b}
+fn two_atom_walk(components: &[Component]) -> Option<(f64, f64, f64)> {
+    let atoms = point_masses(components);
+    if atoms.len() != 2 || atoms.len() != components.len() {
+        return None;
+    }
+    let (up, down) = if atoms[0].0 > atoms[1].0 { (atoms[0], atoms[1]) } else { (atoms[1], atoms[0]) };
+    if up.0 <= 0.0 || down.0 >= 0.0 {
+        return None;
+    }
+    Some((up.0, -down.0, up.1 / (up.1 + down.1)))
+}
+
+/// Most band entries `lattice_walk_ruin` will allocate
+const WALK_RUIN_MAX_ENTRIES: usize = LATTICE_MAX_POINTS;
+
+/// Gambler's ruin probability for a walk of +a w.p. p and -b w.p. 1 - p
+/// with a / b rational
+///
+/// Equal steps use the textbook formula: ruin needs k = ceil(u / a) net
+/// down-steps and success j = ceil((T - u) / a) net up-steps, so with
+/// r = q / p and N = k + j, psi = (r^k - r^N) / (1 - r^N), or 1 - k / N for
+/// a fair walk, and psi = min(r, 1)^k without a target. Unequal steps go to
+/// `lattice_walk_ruin`. NaN unless the distribution is two atoms of opposite
+/// sign.
+fn gamblers_ruin_probability(components: &[Component], init_wealth: f64, target: f64) -> f64 {
+    let Some((a, b, p)) = two_atom_walk(components) else {
+        return f64::NAN;
+    };
+    if init_wealth <= 0.0 {
+        return 1.0;
+    }
+    if init_wealth >= target {
+        return 0.0;
+    }
+    if a != b {
+        return match lattice_span(components) {
+            Some(span) => lattice_walk_ruin(a, b, p, span, init_wealth, target),
+            None => f64::NAN,
+        };
+    }
+    let k = (init_wealth / a).ceil();
+    let r = (1.0 - p) / p;
+    if !target.is_finite() {
+        return r.min(1.0).powf(k);
+    }
+    let n = k + ((target - init_wealth) / a).ceil();
+    if r == 1.0 {
+        1.0 - k / n
+    } else if r < 1.0 {
+        (r.powf(k) - r.powf(n)) / (1.0 - r.powf(n))
+    } else {
+        let s = 1.0 / r;
+        (1.0 - s.powf(n - k)) / (1.0 - s.powf(n))
+    }
+}
+
+/// Ruin probability of the walk +a w.p. p / -b w.p. 1 - p on the lattice of
+/// span h (a and b multiples of h) by solving the absorbing-chain equations
+///
+/// Wealth stays on u + k h. Each alive level satisfies
+/// psi_k = p psi_{k + a/h} + q psi_{k - b/h}, with psi = 1 at or below zero
+/// and 0 at or above the target; the banded system is eliminated without
+/// pivoting (it is diagonally dominant). Without a target the walk needs
+/// positive drift to escape, and the top level is placed where the Lundberg
+/// bound e^{-R w} falls below e^-40. NaN when the band exceeds
+/// `WALK_RUIN_MAX_ENTRIES`.
+fn lattice_walk_ruin(a: f64, b: f64, p: f64, span: f64, init_wealth: f64, target: f64) -> f64 {
+    let q = 1.0 - p;
+    let (up, down) = ((a / span).round() as usize, (b / span).round() as usize);
+    let k_lo = (-init_wealth / span).floor() as i64 + 1;
+    let k_hi = if target.is_finite() {
+        ((target - init_wealth) / span).ceil() as i64 - 1
+    } else {
+        if p * a <= q * b {
+            return 1.0;
+        }
+        let net = [Component::Atom { x: -a, p }, Component::Atom { x: b, p: q }];
+        let r = mgf_root(&net, a.max(b));
+        ((40.0 / r - init_wealth) / span).ceil().max(0.0) as i64 + up as i64
+    };
+    let n = (k_hi - k_lo + 1) as usize;
+    let width = up + down + 1;
+    if n.saturating_mul(width) > WALK_RUIN_MAX_ENTRIES {
+        return f64::NAN;
+    }
+    // band[i][d] holds the coefficient of level j = i + d - down in row i
+    let mut band = vec![vec![0.0; width]; n];
+    let mut rhs = vec![0.0; n];
+    for i in 0..n {
+        band[i][down] = 1.0;
+        if i + up < n {
+            band[i][down + up] = -p;
+        }
+        if i >= down {
+            band[i][0] = -q;
+        } else {
+            rhs[i] = q;
+        }
+    }
+    for j in 0..n {
+        let pivot = band[j][down];
+        for i in j + 1..n.min(j + down + 1) {
+            let factor = band[i][j + down - i] / pivot;
+            if factor == 0.0 {
+                continue;
+            }
+            for col in j..n.min(j + up + 1) {
+                band[i][col + down - i] -= factor * band[j][col + down - j];
+            }
+            rhs[i] -= factor * rhs[j];
+        }
+    }
+    let mut psi = vec![0.0; n];
+    for i in (0..n).rev() {
+        let upper: f64 = (i + 1..n.min(i + up + 1)).map(|col| band[i][col + down - i] * psi[col]).sum();
+        psi[i] = (rhs[i] - upper) / band[i][down];
+    }
+    psi[(-k_lo) as usize].clamp(0.0, 1.0)
+}
+
+/// Exact infinite-horizon ruin probability for a two-step walk of atoms +a
+/// and -b (a / b rational), starting from `init_wealth` with an absorbing
+/// `target` (infinite or NaN for none)
+///
+/// Returns NaN for any other distribution.
+#[wasm_bindgen]
+pub fn gamblers_ruin(components_data: Float64Array, init_wealth: f64, target: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let target = if target.is_nan() { f64::INFINITY } else { target };
+    gamblers_ruin_probability(&components, init_wealth, target)
+}
+
+/// Largest span h such that every atom lies on a multiple of h (relative
+/// tolerance 1e-9); None unless the distribution is purely atoms
+fn lattice_span(components: &[Component]) -> Option<f64> {
+    let atoms = point_masses(components);
+    if atoms.is_empty() || atoms.len() != components.len() {
+        return None;
+    }
+    let scale = atoms.iter().map(|a| a.0.abs()).fold(0.0, f64::max);
+    if scale == 0.0 {
+        return None;
+    }
+    let tol = scale * 1e-9;
+    let span = atoms.iter().map(|a| a.0.abs()).fold(0.0, |g, x| {
+        let (mut a, mut b) = (g, x);
+        while b > tol {
+            let r = a % b;
+            a = b;
+            b = r;
+        }
+        a
+    });
+    if atoms.iter().all(|a| ((a.0 / span).round() * span - a.0).abs() <= tol) {
+        Some(span)
+    } else {
+        None
+    }
+}
+
+/// Exact probability of ruin within `steps` steps for a distribution on a
+/// lattice, by propagating the distribution of surviving wealth levels
+///
+/// Wealth stays on u + k h, so one step convolves the alive vector with the
+/// step pmf; levels at or below zero are absorbed as ruin and levels at or
+/// above `target` as success. Cost is O(steps * levels * atoms).
+fn lattice_ruin_probability(components: &[Component], init_wealth: f64, steps: u32, target: f64) -> f64 {
+    let Some(span) = lattice_span(components) else {
+        return f64::NAN;
+    };
+    if init_wealth <= 0.0 {
+        return 1.0;
+    }
+    if init_wealth >= target {
+        return 0.0;
+    }
+    let total = total_mass(components);
+    let jumps: Vec<(i64, f64)> = point_masses(components)
+        .iter()
+        .map(|&(x, p)| ((x / span).round() as i64, p / total))
+        .collect();
+    // Level k means wealth u + k h; alive[i] holds level lo + i
+    let first_alive = (-init_wealth / span).floor() as i64 + 1;
+    let mut lo = 0i64;
+    let mut alive = vec![1.0];
+    let mut ruin = 0.0;
+    let min_jump = jumps.iter().map(|j| j.0).min().unwrap_or(0);
+    let max_jump = jumps.iter().map(|j| j.0).max().unwrap_or(0);
+    for _ in 0..steps {
+        let new_lo = (lo + min_jump).max(first_alive);
+        let mut new_hi = lo + alive.len() as i64 - 1 + max_jump;
+        if target.is_finite() {
+            new_hi = new_hi.min(((target - init_wealth) / span).ceil() as i64 - 1);
+        }
+        let mut next = vec![0.0; (new_hi - new_lo + 1).max(0) as usize];
+        for (i, &mass) in alive.iter().enumerate().filter(|(_, m)| **m > 0.0) {
+            for &(jump, p) in &jumps {
+                let level = lo + i as i64 + jump;
+                if level < first_alive {
+                    ruin += mass * p;
+                } else if level <= new_hi {
+                    next[(level - new_lo) as usize] += mass * p;
+                }
+            }
+        }
+        lo = new_lo;
+        alive = next;
+        if alive.is_empty() {
+            break;
+        }
+    }
+    ruin
+}
+
+/// Exact finite-horizon ruin probability for a distribution of atoms on a
+/// common lattice (all atoms at multiples of some h), with an absorbing
+/// `target` (infinite or NaN for none)
+///
+/// A dynamic-programming ground truth for the Monte Carlo engine; returns
+/// NaN when the distribution is not a pure lattice of atoms.
+#[wasm_bindgen]
+pub fn lattice_ruin(components_data: Float64Array, init_wealth: f64, steps: u32, target: f64) -> f64 {
+    let data: Vec<f64> = components_data.to_vec();
+    let components = parse_components(&data);
+    let target = if target.is_nan() { f64::INFINITY } else { target };
+    lattice_ruin_probability(&components, init_wealth, steps, target)
+}
+
+// ===========================================
+// Monte Carlo Engine
+// ===========================================
+
+/// Ruin simulation configured once and run repeatedly
+///
+/// Holds the alias table and an RNG whose state carries over between runs,
+/// so repeated `run` calls continue one stream (useful for convergence
+/// plots), while `run_seeded` restarts from a given seed. The default
+/// generator matches `run_monte_carlo`: for the same seed and
+/// settings, `run_seeded` returns the same count.
+#[wasm_bindgen]
+pub struct MonteCarloEngine {
+    increments: Increments,
+    init_wealth: f64,
+    steps: u32,
+    algorithm: Option<RngAlgorithm>,
+    rng: SimRng,
+    confidence_level: f64,
+    rules: StepRules,
+    /// Claim-count model making each step a compound sum of draws
+    counts: Option<ClaimCounts>,
+    /// Run trials in antithetic pairs
+    antithetic: bool,
+    /// Low-discrepancy sequence replacing the generator, if selected
+    halton: Option<HaltonSequence>,
+    /// Trials accumulated by `run_chunk` since the last reset
+    totals: RunTotals,
+    /// JS callback and the number of trials between calls
+    progress: Option<(js_sys::Function, u32)>,
+    /// Cancellation flag shared with an `AbortHandle`
+    abort: Option<AbortFlag>,
+}
+
+#[wasm_bindgen]
+impl MonteCarloEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(components_data: Float64Array, init_wealth: f64, steps: u32, seed: u64) -> MonteCarloEngine {
+        let data: Vec<f64> = components_data.to_vec();
+        MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed)
+    }
+
+    /// Replace the step distribution (rebuilds the alias table); clears any
+    /// step schedule, regimes, autocorrelation or importance tilt
+    pub fn set_distribution(&mut self, components_data: Float64Array) {
+        let data: Vec<f64> = components_data.to_vec();
+        self.increments = Increments::Iid(AliasTable::new(parse_components(&data)));
+    }
+
+    /// Use a different step distribution per block of `block_len` steps:
+    /// `dists[k]` drives steps k * block_len + 1 ..= (k + 1) * block_len and the
+    /// last one continues past the end of the schedule (block_len = 1 gives
+    /// one distribution per step)
+    pub fn set_step_schedule(&mut self, dists: Array, block_len: u32) -> Result<(), JsValue> {
+        self.set_schedule(parse_component_array(&dists), block_len).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Switch between regimes, each with its own step distribution
+    /// (`dists[i]`): a trial starts in regime `initial` and after every step
+    /// moves from regime i to j with probability `transition[i * n + j]`
+    /// (row-major n x n; rows are normalized)
+    pub fn set_regimes(&mut self, dists: Array, transition: Float64Array, initial: u32) -> Result<(), JsValue> {
+        self.set_regime_tables(parse_component_array(&dists), &transition.to_vec(), initial as usize)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Correlate consecutive increments through an AR(1) Gaussian copula with
+    /// lag-one latent correlation `rho` in [-1, 1], keeping the step
+    /// distribution as the marginal; 0 restores i.i.d. draws. Applies to a
+    /// single step distribution (not a schedule or regimes).
+    pub fn set_autocorrelation(&mut self, rho: f64) -> Result<(), JsValue> {
+        self.set_rho(rho).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Importance sampling for rare ruin: draw increments from the
+    /// exponentially tilted density e^{theta x} f(x) / M(theta) and weight
+    /// trials by the likelihood ratio. NaN picks theta = -R, with R solving
+    /// E[e^{-R X}] = 1 (optimal for unit stakes); 0 restores plain sampling.
+    /// Estimate with `run_importance`; the other outputs describe the tilted
+    /// process.
+    pub fn set_importance_tilt(&mut self, theta: f64) -> Result<(), JsValue> {
+        self.set_tilt(theta).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Likelihood-ratio weighted ruin probability estimate from `num_trials`
+    /// trials (plain Monte Carlo without a tilt); with antithetic pairs on
+    /// (and no Halton sampling, which replaces them), each pair's mean is
+    /// one sample of the variance estimate
+    pub fn run_importance(&mut self, num_trials: u32) -> Estimate {
+        let weights: Vec<f64> = self
+            .simulate(num_trials)
+            .iter()
+            .map(|o| if o.ruin_step.is_some() { o.log_weight.exp() } else { 0.0 })
+            .collect();
+        if self.antithetic && self.halton.is_none() {
+            let pair_means: Vec<f64> = weights.chunks(2).map(|w| 0.5 * (w[0] + w[1])).collect();
+            let mut estimate = Estimate::from_samples(&pair_means);
+            estimate.num_trials = weights.len() as u32;
+            estimate
+        } else {
+            Estimate::from_samples(&weights)
+        }
+    }
+
+    /// Control-variate estimate of `target`: every trial also sums all its
+    /// step draws over the full horizon, whose mean steps * E[X] (times the
+    /// mean claim count with a frequency model) is known from the
+    /// distribution, and the estimate is regressed on it. Needs a single
+    /// step distribution (optionally autocorrelated).
+    pub fn run_control_variate(&mut self, num_trials: u32, target: ControlTarget) -> Result<Estimate, JsValue> {
+        self.control_variate(num_trials, target).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Choose pseudo-random or quasi-random (scrambled Halton) uniforms for
+    /// the trials run by `run`, `run_detailed` and the outputs built on
+    /// them; the scrambling and the padding past `HALTON_MAX_DIMENSIONS`
+    /// coordinates are drawn from the engine's generator.
+    /// Selecting Halton restarts the sequence and takes precedence over
+    /// antithetic pairing.
+    pub fn set_sampling_scheme(&mut self, scheme: SamplingScheme) {
+        self.halton = match scheme {
+            SamplingScheme::PseudoRandom => None,
+            SamplingScheme::Halton => Some(HaltonSequence::new(self.rng.next_u64())),
+        };
+    }
+
+    /// Antithetic variates: each trial is followed by its mirror image,
+    /// driven by the reflected uniforms 1 - u of every draw (component
+    /// choice and within-component position alike). Trial counts round up
+    /// to an even number; wealth percentiles and sample paths stay
+    /// unpaired.
+    pub fn set_antithetic(&mut self, antithetic: bool) {
+        self.antithetic = antithetic;
+    }
+
+    pub fn set_init_wealth(&mut self, init_wealth: f64) {
+        self.init_wealth = init_wealth;
+    }
+
+    pub fn set_steps(&mut self, steps: u32) {
+        self.steps = steps;
+    }
+
+    /// Switch to another RNG algorithm, seeded with `seed`
+    pub fn set_rng(&mut self, algorithm: RngAlgorithm, seed: u64) {
+        self.algorithm = Some(algorithm);
+        self.rng = SimRng::new(algorithm, seed);
+    }
+
+    /// Confidence level of the interval reported by `run_detailed`
+    /// (default 0.95)
+    pub fn set_confidence_level(&mut self, level: f64) {
+        self.confidence_level = level;
+    }
+
+    /// Restart the current RNG algorithm from `seed`
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = match self.algorithm {
+            Some(algorithm) => SimRng::new(algorithm, seed),
+            None => SimRng::std(seed),
+        };
+    }
+
+    /// Upper absorbing barrier: trials reaching `target` stop as successes
+    /// (infinite or NaN removes the barrier)
+    pub fn set_target(&mut self, target: f64) {
+        self.rules.target = if target.is_nan() { f64::INFINITY } else { target };
+    }
+
+    /// Per-step interest: wealth *= 1 + rate before each increment, as in
+    /// discrete-time surplus models (a negative rate discounts, e.g.
+    /// v - 1 for discount factor v; 0 disables)
+    pub fn set_interest_rate(&mut self, rate: f64) {
+        self.rules.interest = rate;
+    }
+
+    /// Deterministic income c added every step on top of the random
+    /// increment, giving the discrete Cramer-Lundberg surplus
+    /// u + c * t - losses (with losses as negative increments)
+    pub fn set_premium(&mut self, premium: f64) {
+        self.rules.premium = premium;
+    }
+
+    /// Compound frequency-severity steps: each step draws a claim count N
+    /// from `frequency_params` (see `Frequency` for the format) and adds the
+    /// sum of N draws from the step distribution; an empty array restores one
+    /// draw per step
+    pub fn set_frequency(&mut self, frequency_params: Float64Array) -> Result<(), JsValue> {
+        let params = frequency_params.to_vec();
+        self.set_frequency_params(&params).map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Stake one unit per step (the default): increments are absolute
+    pub fn set_stake_constant(&mut self) {
+        self.rules.stake = StakeRule::Constant;
+    }
+
+    /// Stake `fraction` of current wealth each step
+    pub fn set_stake_fixed_fraction(&mut self, fraction: f64) {
+        self.rules.stake = StakeRule::Fraction { fraction, cap: f64::INFINITY };
+    }
+
+    /// Stake `fraction` of current wealth, but never more than `cap`
+    pub fn set_stake_capped_fraction(&mut self, fraction: f64, cap: f64) {
+        self.rules.stake = StakeRule::Fraction { fraction, cap };
+    }
+
+    /// Stake looked up from a wealth table: `stakes[i]` applies from
+    /// `thresholds[i]` up to the next threshold, and `stakes[0]` also below
+    /// the first one. Thresholds must be increasing.
+    pub fn set_stake_table(&mut self, thresholds: Float64Array, stakes: Float64Array) -> Result<(), JsValue> {
+        let thresholds = thresholds.to_vec();
+        let stakes = stakes.to_vec();
+        if thresholds.is_empty() || thresholds.len() != stakes.len() {
+            return Err(JsValue::from_str("need matching, non-empty thresholds and stakes"));
+        }
+        if thresholds.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(JsValue::from_str("thresholds must be increasing"));
+        }
+        self.rules.stake = StakeRule::Table { thresholds, stakes };
+        Ok(())
+    }
+
+    /// Multiplicative dynamics: wealth *= 1 + fraction * X each step, with
+    /// ruin once wealth falls to `drawdown_floor` times its running peak
+    /// (e.g. 0.5 = ruin at a 50% drawdown; 0 = only at zero)
+    pub fn set_multiplicative(&mut self, fraction: f64, drawdown_floor: f64) {
+        self.rules.stake = StakeRule::Fraction { fraction, cap: f64::INFINITY };
+        self.rules.drawdown_floor = drawdown_floor;
+    }
+
+    /// Stake returned by a JS function of current wealth, called every step
+    /// (slow; prefer the built-in rules when they fit)
+    pub fn set_stake_callback(&mut self, callback: js_sys::Function) {
+        self.rules.stake = StakeRule::Callback(callback);
+    }
+
+    /// Run `num_trials` trials continuing the RNG stream; returns the ruin count
+    pub fn run(&mut self, num_trials: u32) -> u32 {
+        let mut ruins = 0;
+        self.simulate_streaming(num_trials, |batch| {
+            ruins += batch.iter().filter(|o| o.ruin_step.is_some()).count() as u32;
+        });
+        ruins
+    }
+
+    /// Reseed, then run `num_trials` trials; returns the ruin count
+    pub fn run_seeded(&mut self, num_trials: u32, seed: u64) -> u32 {
+        self.reseed(seed);
+        self.run(num_trials)
+    }
+
+    /// Run `num_trials` trials continuing the RNG stream and summarize them
+    ///
+    /// Trials are summarized as they run, in constant memory; the median
+    /// final wealth is a P-square estimate (exact for up to 5 survivors).
+    pub fn run_detailed(&mut self, num_trials: u32) -> MonteCarloResult {
+        let mut summary = RunSummary::default();
+        self.simulate_streaming(num_trials, |batch| summary.add(&batch));
+        summary.result(self.confidence_level)
+    }
+
+    /// Run one chunk of `num_trials` trials and return the summary of every
+    /// trial run by `run_chunk` since the last `reset_chunks`, so a long
+    /// simulation can be split across calls that yield to the event loop.
+    /// The RNG stream continues across chunks, so chunk sizes do not change
+    /// the trials drawn. The running median is not tracked (NaN).
+    pub fn run_chunk(&mut self, num_trials: u32) -> MonteCarloResult {
+        let mut totals = std::mem::take(&mut self.totals);
+        self.simulate_streaming(num_trials, |batch| totals.add(&batch));
+        self.totals = totals;
+        MonteCarloResult::from_totals(&self.totals, f64::NAN, self.confidence_level)
+    }
+
+    /// Call `callback(trials_done, ruins_so_far)` after every `every` trials
+    /// of each run (counts restart with each call to a run method), e.g. to
+    /// drive a progress bar and live estimate. Returning `false` from the
+    /// callback cancels the rest of the run.
+    pub fn set_progress_callback(&mut self, callback: js_sys::Function, every: u32) {
+        self.progress = Some((callback, every.max(1)));
+    }
+
+    pub fn clear_progress_callback(&mut self) {
+        self.progress = None;
+    }
+
+    /// Let `handle` cancel runs: once aborted, a run stops at the next check
+    /// (every progress interval, or every 1000 trials without a callback)
+    /// and returns the trials completed so far; later runs return at once
+    /// until the handle is reset
+    pub fn set_abort_handle(&mut self, handle: &AbortHandle) {
+        self.abort = Some(handle.flag.clone());
+    }
+
+    /// Discard the trials accumulated by `run_chunk`
+    pub fn reset_chunks(&mut self) {
+        self.totals = RunTotals::default();
+    }
+
+    /// Run batches of trials until the standard error of the ruin
+    /// probability falls below `target_se` or `max_trials` have run
+    ///
+    /// The stopping rule uses the Laplace estimate (k + 1) / (n + 2) so a
+    /// run that has seen no ruins yet does not stop on a zero error.
+    pub fn run_until(&mut self, target_se: f64, max_trials: u32) -> MonteCarloResult {
+        let mut summary = RunSummary::default();
+        while summary.totals.num_trials < max_trials {
+            let batch = ADAPTIVE_BATCH_TRIALS.min(max_trials - summary.totals.num_trials);
+            let before = summary.totals.num_trials;
+            self.simulate_streaming(batch, |b| summary.add(&b));
+            if summary.totals.num_trials == before {
+                break;
+            }
+            let n = summary.totals.num_trials as f64;
+            let p = (summary.totals.ruin_count as f64 + 1.0) / (n + 2.0);
+            if (p * (1.0 - p) / n).sqrt() < target_se {
+                break;
+            }
+        }
+        summary.result(self.confidence_level)
+    }
+
+    /// Run `num_trials` trials and return the 1-based ruin step of every
+    /// ruined trial, in trial order
+    pub fn ruin_times(&mut self, num_trials: u32) -> Float64Array {
+        let times: Vec<f64> = self.simulate(num_trials).iter().filter_map(|o| o.ruin_step).map(|t| t as f64).collect();
+        Float64Array::from(times.as_slice())
+    }
+
+    /// Run `num_trials` trials and return ruin counts in `n_bins` equal-width
+    /// bins over steps 1..=steps (0 bins means one bin per step)
+    pub fn ruin_time_histogram(&mut self, num_trials: u32, n_bins: u32) -> Float64Array {
+        let steps: Vec<u32> = self.simulate(num_trials).iter().filter_map(|o| o.ruin_step).collect();
+        let counts = ruin_time_histogram(&steps, self.steps, n_bins);
+        Float64Array::from(counts.as_slice())
+    }
+
+    /// Run `num_trials` trials and return, for each step 1..=steps, the
+    /// fraction of trials still solvent after that step
+    pub fn survival_curve(&mut self, num_trials: u32) -> Float64Array {
+        let steps: Vec<u32> = self.simulate(num_trials).iter().filter_map(|o| o.ruin_step).collect();
+        let curve = survival_curve(&steps, self.steps, num_trials);
+        Float64Array::from(curve.as_slice())
+    }
+
+    /// Run one set of `num_trials` trials out to the largest horizon and
+    /// return the cumulative ruin probability at each horizon (in steps,
+    /// input order). The engine's own `steps` is left unchanged.
+    pub fn ruin_at_horizons(&mut self, num_trials: u32, horizons: Float64Array) -> Float64Array {
+        let horizons: Vec<u32> = horizons.to_vec().iter().map(|&h| h.max(0.0) as u32).collect();
+        let curve = self.horizon_curve(num_trials, &horizons);
+        Float64Array::from(curve.as_slice())
+    }
+
+    /// Run `num_trials` trials and bin the final wealth of the survivors.
+    /// Uses `edges` when given, otherwise `n_bins` equal-width bins over the
+    /// observed range (0 bins picks Sturges' rule). Given edges must be
+    /// finite and strictly increasing, at least two of them.
+    pub fn final_wealth_histogram(
+        &mut self,
+        num_trials: u32,
+        edges: Float64Array,
+        n_bins: u32,
+    ) -> Result<Histogram, JsValue> {
+        let edges = edges.to_vec();
+        check_edges(&edges).map_err(|e| JsValue::from_str(&e))?;
+        let survivors: Vec<f64> =
+            self.simulate(num_trials).iter().filter(|o| o.ruin_step.is_none()).map(|o| o.final_wealth).collect();
+        Ok(Histogram::build(&survivors, edges, n_bins))
+    }
+
+    /// Advance `num_trials` trials step by step and record the given wealth
+    /// percentiles (0-100) after every step, as a row-major
+    /// steps x percentiles matrix
+    ///
+    /// Stopped trials are absorbed: they keep the wealth they had at ruin (or
+    /// on reaching the target).
+    /// Trials advance in lockstep, so the draws differ from `run` for the same
+    /// seed.
+    pub fn wealth_percentiles(&mut self, num_trials: u32, percentiles: Float64Array) -> Float64Array {
+        let pcts = percentiles.to_vec();
+        let source = Compound { severity: &self.increments, counts: self.counts.as_ref() };
+        let matrix = wealth_percentiles(
+            &source,
+            self.init_wealth,
+            self.steps,
+            num_trials,
+            &pcts,
+            &self.rules,
+            &mut self.rng,
+        );
+        Float64Array::from(matrix.as_slice())
+    }
+
+    /// Run `num_trials` trials and summarize the per-trial maximum drawdown
+    /// (peak-to-trough, up to ruin for ruined trials) as
+    /// [mean, percentile_1, percentile_2, ...] with percentiles in 0-100
+    pub fn drawdown_summary(&mut self, num_trials: u32, percentiles: Float64Array) -> Float64Array {
+        let summary = drawdown_summary(&self.simulate(num_trials), &percentiles.to_vec());
+        Float64Array::from(summary.as_slice())
+    }
+
+    /// Run `num_trials` trials and bin their maximum drawdowns (edges or
+    /// auto bins as in `final_wealth_histogram`)
+    pub fn drawdown_histogram(
+        &mut self,
+        num_trials: u32,
+        edges: Float64Array,
+        n_bins: u32,
+    ) -> Result<Histogram, JsValue> {
+        let edges = edges.to_vec();
+        check_edges(&edges).map_err(|e| JsValue::from_str(&e))?;
+        let drawdowns: Vec<f64> = self.simulate(num_trials).iter().map(|o| o.max_drawdown).collect();
+        Ok(Histogram::build(&drawdowns, edges, n_bins))
+    }
+
+    /// Simulate `n_paths` trials and return their full wealth trajectories
+    pub fn sample_paths(&mut self, n_paths: u32) -> SamplePaths {
+        let source = Compound { severity: &self.increments, counts: self.counts.as_ref() };
+        sample_paths(&source, self.init_wealth, self.steps, n_paths, &self.rules, &mut self.rng)
+    }
+}
+
+impl MonteCarloEngine {
+    fn set_schedule(&mut self, dists: Vec<Vec<Component>>, block_len: u32) -> Result<(), String> {
+        if dists.is_empty() {
+            return Err("step schedule needs at least one distribution".to_string());
+        }
+        if block_len == 0 {
+            return Err("block_len must be at least 1".to_string());
+        }
+        let tables = dists.into_iter().map(AliasTable::new).collect();
+        self.increments = Increments::Schedule { tables, block_len };
+        Ok(())
+    }
+
+    fn set_frequency_params(&mut self, params: &[f64]) -> Result<(), String> {
+        self.counts = if params.is_empty() { None } else { Some(ClaimCounts::new(Frequency::parse(params)?)?) };
+        Ok(())
+    }
+
+    fn control_variate(&mut self, num_trials: u32, target: ControlTarget) -> Result<Estimate, String> {
+        let components = match &self.increments {
+            Increments::Iid(table) => &table.components,
+            Increments::Autocorrelated { table, .. } => &table.components,
+            _ => return Err("control variates need a single step distribution".to_string()),
+        };
+        let per_step = mixture_mean(components) * self.counts.as_ref().map_or(1.0, |c| c.mean());
+        let expected = per_step * self.steps as f64;
+        let source = Compound { severity: &self.increments, counts: self.counts.as_ref() };
+        let (ys, cs): (Vec<f64>, Vec<f64>) = (0..num_trials)
+            .map(|_| {
+                let (outcome, control) =
+                    simulate_trial_with_control(&source, self.init_wealth, self.steps, &self.rules, &mut self.rng);
+                let y = match target {
+                    ControlTarget::SurvivalProbability => outcome.ruin_step.map_or(1.0, |_| 0.0),
+                    ControlTarget::FinalWealth => outcome.final_wealth,
+                };
+                (y, control)
+            })
+            .unzip();
+        Ok(Estimate::with_control(&ys, &cs, expected))
+    }
+
+    fn set_tilt(&mut self, theta: f64) -> Result<(), String> {
+        let components = match &self.increments {
+            Increments::Iid(table) => table.components.clone(),
+            Increments::Tilted { components, .. } => components.clone(),
+            _ => return Err("importance sampling needs a single step distribution".to_string()),
+        };
+        let theta = if theta.is_nan() { -optimal_tilt(&components)? } else { theta };
+        self.increments = if theta == 0.0 {
+            Increments::Iid(AliasTable::new(components))
+        } else {
+            let (tilted, log_mgf) = tilt_components(&components, theta)?;
+            Increments::Tilted { table: AliasTable::new(tilted), components, theta, log_mgf }
+        };
+        Ok(())
+    }
+
+    fn set_rho(&mut self, rho: f64) -> Result<(), String> {
+        if !(-1.0..=1.0).contains(&rho) {
+            return Err("autocorrelation must be in [-1, 1]".to_string());
+        }
+        let components = match &self.increments {
+            Increments::Iid(table) => table.components.clone(),
+            Increments::Autocorrelated { table, .. } => table.components.clone(),
+            _ => return Err("autocorrelation needs a single step distribution".to_string()),
+        };
+        self.increments = if rho == 0.0 {
+            Increments::Iid(AliasTable::new(components))
+        } else {
+            Increments::Autocorrelated { table: QuantileTable::new(components), rho }
+        };
+        Ok(())
+    }
+
+    fn set_regime_tables(&mut self, dists: Vec<Vec<Component>>, transition: &[f64], initial: usize) -> Result<(), String> {
+        let n = dists.len();
+        if n == 0 {
+            return Err("regime switching needs at least one regime".to_string());
+        }
+        if initial >= n {
+            return Err(format!("initial regime {} out of range for {} regimes", initial, n));
+        }
+        let cumulative = cumulative_transition(transition, n)?;
+        let tables = dists.into_iter().map(AliasTable::new).collect();
+        self.increments = Increments::Regimes { tables, cumulative, initial };
+        Ok(())
+    }
+
+    fn horizon_curve(&mut self, num_trials: u32, horizons: &[u32]) -> Vec<f64> {
+        let steps = self.steps;
+        self.steps = horizons.iter().copied().max().unwrap_or(0);
+        let ruin_steps: Vec<u32> = self.simulate(num_trials).iter().filter_map(|o| o.ruin_step).collect();
+        self.steps = steps;
+        ruin_at_horizons(&ruin_steps, horizons, num_trials)
+    }
+
+    /// Every outcome of `num_trials` trials, for statistics that need them all
+    fn simulate(&mut self, num_trials: u32) -> Vec<TrialOutcome> {
+        let mut outcomes = Vec::with_capacity(num_trials as usize);
+        self.simulate_streaming(num_trials, |batch| outcomes.extend(batch));
+        outcomes
+    }
+
+    /// Run `num_trials` trials, handing each batch of outcomes to `consume`
+    /// as it completes, with the progress callback and abort flag applied
+    fn simulate_streaming(&mut self, num_trials: u32, consume: impl FnMut(Vec<TrialOutcome>)) {
+        match (self.progress.clone(), self.abort.is_some()) {
+            (None, false) => self.simulate_in_batches(num_trials, STREAM_BATCH_TRIALS, |_, _| true, consume),
+            (None, true) => self.simulate_in_batches(num_trials, ABORT_CHECK_TRIALS, |_, _| true, consume),
+            (Some((callback, every)), _) => self.simulate_in_batches(
+                num_trials,
+                every,
+                |done, ruins| {
+                    // Only an explicit `false` cancels; a throwing callback does not
+                    let result = callback.call2(&JsValue::NULL, &JsValue::from(done), &JsValue::from(ruins));
+                    result.map_or(true, |v| v.as_bool() != Some(false))
+                },
+                consume,
+            ),
+        }
+    }
+
+    /// Run `num_trials` trials in batches of `every`, reporting the trials
+    /// done and ruins so far after each batch; the trials drawn are the same
+    /// as in one batch. Stops early when `report` returns false or the abort
+    /// flag is set.
+    #[cfg(test)]
+    fn simulate_with_progress(
+        &mut self,
+        num_trials: u32,
+        every: u32,
+        report: impl FnMut(u32, u32) -> bool,
+    ) -> Vec<TrialOutcome> {
+        let mut outcomes = Vec::with_capacity(num_trials as usize);
+        self.simulate_in_batches(num_trials, every, report, |batch| outcomes.extend(batch));
+        outcomes
+    }
+
+    fn simulate_in_batches(
+        &mut self,
+        num_trials: u32,
+        every: u32,
+        mut report: impl FnMut(u32, u32) -> bool,
+        mut consume: impl FnMut(Vec<TrialOutcome>),
+    ) {
+        let (mut done, mut ruins) = (0, 0);
+        while done < num_trials && !self.abort.as_ref().is_some_and(|f| f.is_set()) {
+            let batch = self.simulate_batch(every.min(num_trials - done));
+            ruins += batch.iter().filter(|o| o.ruin_step.is_some()).count() as u32;
+            done += batch.len() as u32;
+            consume(batch);
+            if !report(done, ruins) {
+                break;
+            }
+        }
+    }
+
+    fn simulate_batch(&mut self, num_trials: u32) -> Vec<TrialOutcome> {
+        let source = Compound { severity: &self.increments, counts: self.counts.as_ref() };
+        if let Some(sequence) = self.halton.as_mut() {
+            return (0..num_trials)
+                .map(|_| {
+                    sequence.index += 1;
+                    let index = sequence.index;
+                    let mut point = HaltonPoint { sequence: &*sequence, padding: &mut self.rng, index, dim: 0 };
+                    simulate_trial(&source, self.init_wealth, self.steps, &self.rules, &mut point)
+                })
+                .collect();
+        }
+        if self.antithetic {
+            let mut outcomes = Vec::with_capacity(num_trials as usize + 1);
+            let mut words = Vec::new();
+            for _ in 0..num_trials.div_ceil(2) {
+                words.clear();
+                let mut recorder = RecordingRng { inner: &mut self.rng, words };
+                outcomes.push(simulate_trial(&source, self.init_wealth, self.steps, &self.rules, &mut recorder));
+                words = recorder.words;
+                let mut mirror = AntitheticRng { inner: &mut self.rng, words: &words, pos: 0 };
+                outcomes.push(simulate_trial(&source, self.init_wealth, self.steps, &self.rules, &mut mirror));
+            }
+            return outcomes;
+        }
+        (0..num_trials)
+            .map(|_| simulate_trial(&source, self.init_wealth, self.steps, &self.rules, &mut self.rng))
+            .collect()
+    }
+
+    fn from_components(components: Vec<Component>, init_wealth: f64, steps: u32, seed: u64) -> MonteCarloEngine {
+        MonteCarloEngine {
+            increments: Increments::Iid(AliasTable::new(components)),
+            init_wealth,
+            steps,
+            algorithm: None,
+            rng: SimRng::std(seed),
+            confidence_level: DEFAULT_CONFIDENCE_LEVEL,
+            rules: StepRules::default(),
+            counts: None,
+            antithetic: false,
+            halton: None,
+            totals: RunTotals::default(),
+            progress: None,
+            abort: None,
+        }
+    }
+}
+
+/// Trials between abort checks when no progress callback sets the interval
+const ABORT_CHECK_TRIALS: u32 = 1000;
+
+/// Trials per batch when a run streams outcomes without a progress interval
+/// or abort handle (even, so antithetic pairs never straddle batches)
+const STREAM_BATCH_TRIALS: u32 = 10_000;
+
+/// Storage of an abort flag: a plain cell for the thread that runs the
+/// engine, or the first element of an `Int32Array` over a
+/// `SharedArrayBuffer` that another thread writes with `Atomics.store`
+#[derive(Clone)]
+enum AbortFlag {
+    Local(Rc<Cell<bool>>),
+    Shared(js_sys::Int32Array),
+}
+
+impl AbortFlag {
+    fn is_set(&self) -> bool {
+        match self {
+            AbortFlag::Local(cell) => cell.get(),
+            AbortFlag::Shared(view) => js_sys::Atomics::load(view, 0).is_ok_and(|v| v != 0),
+        }
+    }
+
+    fn set(&self, value: bool) {
+        match self {
+            AbortFlag::Local(cell) => cell.set(value),
+            AbortFlag::Shared(view) => {
+                let _ = js_sys::Atomics::store(view, 0, value as i32);
+            }
+        }
+    }
+}
+
+/// Cancellation flag for `MonteCarloEngine` runs, checked between batches
+/// of trials
+///
+/// A handle from `new` lives on the engine's own thread, so `abort` only
+/// takes effect when that thread gets to run JS: from a progress callback or
+/// between `run_chunk` calls. To stop a run in flight in a web worker,
+/// create an `Int32Array` over a `SharedArrayBuffer` (needs a
+/// cross-origin-isolated page), send it to the worker and build the handle
+/// there with `from_shared`; the main thread then cancels with
+/// `Atomics.store(view, 0, 1)` while the worker is busy.
+#[wasm_bindgen]
+pub struct AbortHandle {
+    flag: AbortFlag,
+}
+
+impl Default for AbortHandle {
+    fn default() -> Self {
+        AbortHandle { flag: AbortFlag::Local(Rc::new(Cell::new(false))) }
+    }
+}
+
+#[wasm_bindgen]
+impl AbortHandle {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> AbortHandle {
+        AbortHandle::default()
+    }
+
+    /// A handle polling element 0 of `view`, an `Int32Array` over a
+    /// `SharedArrayBuffer`; non-zero means aborted
+    pub fn from_shared(view: js_sys::Int32Array) -> AbortHandle {
+        AbortHandle { flag: AbortFlag::Shared(view) }
+    }
+
+    pub fn abort(&self) {
+        self.flag.set(true);
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.flag.is_set()
+    }
+
+    /// Clear the flag so attached engines run again
+    pub fn reset(&self) {
+        self.flag.set(false);
+    }
+}
+
+/// Trials per batch of `MonteCarloEngine::run_until`
+const ADAPTIVE_BATCH_TRIALS: u32 = 1000;
+
+/// Confidence level used for ruin probability intervals unless configured
+const DEFAULT_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Wilson score interval for a binomial proportion k / n at the given
+/// two-sided confidence level. Unlike the normal approximation it stays
+/// inside [0, 1] and is not degenerate when no (or every) trial is ruined.
+fn wilson_interval(k: u32, n: u32, level: f64) -> (f64, f64) {
+    if n == 0 || !(level > 0.0 && level < 1.0) {
+        return (f64::NAN, f64::NAN);
+    }
+    let z = normal_quantile(0.5 + level / 2.0);
+    let n = n as f64;
+    let p = k as f64 / n;
+    let z2n = z * z / n;
+    let center = (p + z2n / 2.0) / (1.0 + z2n);
+    let half = z / (1.0 + z2n) * (p * (1.0 - p) / n + z2n / (4.0 * n)).sqrt();
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+/// Summary of a ruin simulation
+#[wasm_bindgen]
+pub struct MonteCarloResult {
+    ruin_count: u32,
+    success_count: u32,
+    num_trials: u32,
+    confidence_level: f64,
+    mean_final_wealth: f64,
+    median_final_wealth: f64,
+    mean_time_to_ruin: f64,
+}
+
+#[wasm_bindgen]
+impl MonteCarloResult {
+    pub fn ruin_count(&self) -> u32 {
+        self.ruin_count
+    }
+
+    /// Trials that reached the target before ruin
+    pub fn success_count(&self) -> u32 {
+        self.success_count
+    }
+
+    /// Trials that ran all steps without ruin or reaching the target
+    pub fn timeout_count(&self) -> u32 {
+        self.num_trials - self.ruin_count - self.success_count
+    }
+
+    pub fn num_trials(&self) -> u32 {
+        self.num_trials
+    }
+
+    /// Fraction of trials ruined
+    pub fn ruin_probability(&self) -> f64 {
+        if self.num_trials == 0 { f64::NAN } else { self.ruin_count as f64 / self.num_trials as f64 }
+    }
+
+    /// Binomial standard error of `ruin_probability`
+    pub fn standard_error(&self) -> f64 {
+        let p = self.ruin_probability();
+        (p * (1.0 - p) / self.num_trials as f64).sqrt()
+    }
+
+    /// Level of the `ci_lower` / `ci_upper` interval
+    pub fn confidence_level(&self) -> f64 {
+        self.confidence_level
+    }
+
+    /// Lower end of the Wilson confidence interval for the ruin probability
+    pub fn ci_lower(&self) -> f64 {
+        wilson_interval(self.ruin_count, self.num_trials, self.confidence_level).0
+    }
+
+    /// Upper end of the Wilson confidence interval for the ruin probability
+    pub fn ci_upper(&self) -> f64 {
+        wilson_interval(self.ruin_count, self.num_trials, self.confidence_level).1
+    }
+
+    /// Wilson confidence interval [lower, upper] at another level
+    pub fn confidence_interval(&self, level: f64) -> Float64Array {
+        let (lo, hi) = wilson_interval(self.ruin_count, self.num_trials, level);
+        Float64Array::from([lo, hi].as_slice())
+    }
+
+    /// Mean final wealth of the surviving trials (NaN if none survived)
+    pub fn mean_final_wealth(&self) -> f64 {
+        self.mean_final_wealth
+    }
+
+    /// Median final wealth of the surviving trials (NaN if none survived);
+    /// a P-square estimate beyond five survivors
+    pub fn median_final_wealth(&self) -> f64 {
+        self.median_final_wealth
+    }
+
+    /// Mean step at which ruined trials hit zero (NaN if none were ruined)
+    pub fn mean_time_to_ruin(&self) -> f64 {
+        self.mean_time_to_ruin
+    }
+}
+
+impl MonteCarloResult {
+    fn from_totals(totals: &RunTotals, median_final_wealth: f64, confidence_level: f64) -> MonteCarloResult {
+        let mean = |sum: f64, n: u32| if n == 0 { f64::NAN } else { sum / n as f64 };
+        MonteCarloResult {
+            ruin_count: totals.ruin_count,
+            success_count: totals.success_count,
+            num_trials: totals.num_trials,
+            confidence_level,
+            mean_final_wealth: mean(totals.survivor_wealth_sum, totals.num_trials - totals.ruin_count),
+            median_final_wealth,
+            mean_time_to_ruin: mean(totals.ruin_time_sum, totals.ruin_count),
+        }
+    }
+}
+
+/// P-square estimate of a quantile of a stream (Jain and Chlamtac), in
+/// constant memory: five markers whose heights follow the running quantile
+/// by piecewise-parabolic updates. Exact (interpolated order statistics)
+/// for up to five observations.
+struct P2Quantile {
+    p: f64,
+    heights: Vec<f64>,
+    positions: [f64; 5],
+    desired: [f64; 5],
+}
+
+impl P2Quantile {
+    fn new(p: f64) -> Self {
+        P2Quantile {
+            p,
+            heights: Vec::with_capacity(5),
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+        }
+    }
+
+    fn add(&mut self, x: f64) {
+        let q = &mut self.heights;
+        if q.len() < 5 {
+            q.push(x);
+            q.sort_by(|a, b| a.total_cmp(b));
+            return;
+        }
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < q[i + 1]).unwrap_or(3)
+        };
+        let n = &mut self.positions;
+        for pos in n.iter_mut().skip(k + 1) {
+            *pos += 1.0;
+        }
+        let step = [0.0, self.p / 2.0, self.p, (1.0 + self.p) / 2.0, 1.0];
+        for (d, s) in self.desired.iter_mut().zip(step) {
+            *d += s;
+        }
+        for i in 1..4 {
+            let offset = self.desired[i] - n[i];
+            if (offset >= 1.0 && n[i + 1] - n[i] > 1.0) || (offset <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = offset.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// Current estimate (NaN before any observation)
+    fn value(&self) -> f64 {
+        let q = &self.heights;
+        match q.len() {
+            0 => f64::NAN,
+            len if len < 5 || self.positions[4] == 5.0 => {
+                let h = self.p * (len - 1) as f64;
+                let lo = h.floor() as usize;
+                q[lo] + (h - lo as f64) * (q[(lo + 1).min(len - 1)] - q[lo])
+            }
+            _ => q[2],
+        }
+    }
+}
+
+/// Streaming summary of a run: the totals plus a P-square median of the
+/// survivors' final wealth
+struct RunSummary {
+    totals: RunTotals,
+    median: P2Quantile,
+}
+
+impl Default for RunSummary {
+    fn default() -> Self {
+        RunSummary { totals: RunTotals::default(), median: P2Quantile::new(0.5) }
+    }
+}
+
+impl RunSummary {
+    fn add(&mut self, outcomes: &[TrialOutcome]) {
+        self.totals.add(outcomes);
+        for o in outcomes.iter().filter(|o| o.ruin_step.is_none()) {
+            self.median.add(o.final_wealth);
+        }
+    }
+
+    fn result(&self, confidence_level: f64) -> MonteCarloResult {
+        MonteCarloResult::from_totals(&self.totals, self.median.value(), confidence_level)
+    }
+}
+
+/// Running sums over trials, enough to summarize them without keeping every
+/// outcome
+#[derive(Default)]
+struct RunTotals {
+    ruin_count: u32,
+    success_count: u32,
+    num_trials: u32,
+    survivor_wealth_sum: f64,
+    ruin_time_sum: f64,
+}
+
+impl RunTotals {
+    fn merge(mut self, other: RunTotals) -> RunTotals {
+        self.ruin_count += other.ruin_count;
+        self.success_count += other.success_count;
+        self.num_trials += other.num_trials;
+        self.survivor_wealth_sum += other.survivor_wealth_sum;
+        self.ruin_time_sum += other.ruin_time_sum;
+        self
+    }
+
+    fn add(&mut self, outcomes: &[TrialOutcome]) {
+        for o in outcomes {
+            self.num_trials += 1;
+            match o.ruin_step {
+                Some(t) => {
+                    self.ruin_count += 1;
+                    self.ruin_time_sum += t as f64;
+                }
+                None => self.survivor_wealth_sum += o.final_wealth,
+            }
+            if o.target_step.is_some() {
+                self.success_count += 1;
+            }
+        }
+    }
+}
+
+/// Rate R > 0 with E[e^{-R X}] = 1 for increments X with positive drift;
+/// tilting by -R makes ruin the typical outcome
+fn optimal_tilt(components: &[Component]) -> Result<f64, String> {
+    let mean = mixture_mean(components);
+    if total_mass(components) <= 0.0 || mean.is_nan() || mean <= 0.0 {
+        return Err("automatic tilt needs increments with positive mean".to_string());
+    }
+    if support_bounds(components).0 >= 0.0 {
+        return Err("increments are never negative, so ruin is impossible".to_string());
+    }
+    Ok(mgf_root(&scale_components(components, -1.0), mixture_variance(components).sqrt()))
+}
+
+/// Quantity estimated by `MonteCarloEngine::run_control_variate`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlTarget {
+    /// Probability of surviving the horizon (not ruined)
+    SurvivalProbability = 0,
+    /// Expected wealth at the end of the trial (or when it stopped)
+    FinalWealth = 1,
+}
+
+/// Estimate from i.i.d. samples with the variance of the sample mean
+#[wasm_bindgen]
+pub struct Estimate {
+    value: f64,
+    variance: f64,
+    num_trials: u32,
+}
+
+#[wasm_bindgen]
+impl Estimate {
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Variance of `value` as an estimator
+    pub fn variance(&self) -> f64 {
+        self.variance
+    }
+
+    pub fn standard_error(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Standard error relative to the estimate
+    pub fn relative_error(&self) -> f64 {
+        self.variance.sqrt() / self.value
+    }
+
+    pub fn num_trials(&self) -> u32 {
+        self.num_trials
+    }
+}
+
+impl Estimate {
+    /// Control-variate estimator mean(y) - beta (mean(c) - expected) with
+    /// the regression coefficient beta = Cov(y, c) / Var(c); the variance
+    /// uses the regression residuals
+    fn with_control(ys: &[f64], cs: &[f64], expected: f64) -> Estimate {
+        let n = ys.len();
+        if n < 3 {
+            return Estimate::from_samples(ys);
+        }
+        let nf = n as f64;
+        let y_mean = ys.iter().sum::<f64>() / nf;
+        let c_mean = cs.iter().sum::<f64>() / nf;
+        let cov: f64 = ys.iter().zip(cs).map(|(y, c)| (y - y_mean) * (c - c_mean)).sum();
+        let var_c: f64 = cs.iter().map(|c| (c - c_mean).powi(2)).sum();
+        let beta = if var_c > 0.0 { cov / var_c } else { 0.0 };
+        let residual: f64 = ys.iter().zip(cs).map(|(y, c)| (y - y_mean - beta * (c - c_mean)).powi(2)).sum();
+        Estimate {
+            value: y_mean - beta * (c_mean - expected),
+            variance: residual / ((n - 2) * n) as f64,
+            num_trials: n as u32,
+        }
+    }
+
+    fn from_samples(samples: &[f64]) -> Estimate {
+        let n = samples.len();
+        let value = if n == 0 { f64::NAN } else { samples.iter().sum::<f64>() / n as f64 };
+        let variance = if n < 2 {
+            f64::NAN
+        } else {
+            samples.iter().map(|z| (z - value).powi(2)).sum::<f64>() / ((n - 1) * n) as f64
+        };
+        Estimate { value, variance, num_trials: n as u32 }
+    }
+}
+
+/// Run a ruin simulation and return a summary instead of the bare ruin
+/// count; draws the same trials as `run_monte_carlo` for the same seed
+#[wasm_bindgen]
+pub fn run_monte_carlo_detailed(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> MonteCarloResult {
+    #[cfg(feature = "console_error_panic_hook")]
+    set_panic_hook();
+
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.run_detailed(num_trials)
+}
+
+/// Histogram of ruin steps over 1..=steps in `n_bins` equal-width bins; bin k
+/// holds steps in (k * steps / n_bins, (k + 1) * steps / n_bins]. 0 bins (or
+/// more bins than steps) gives one bin per step.
+fn ruin_time_histogram(ruin_steps: &[u32], steps: u32, n_bins: u32) -> Vec<f64> {
+    if steps == 0 {
+        return vec![];
+    }
+    let bins = if n_bins == 0 || n_bins > steps { steps } else { n_bins } as u64;
+    let mut counts = vec![0.0; bins as usize];
+    for &t in ruin_steps {
+        let k = ((t.max(1) - 1) as u64 * bins / steps as u64).min(bins - 1);
+        counts[k as usize] += 1.0;
+    }
+    counts
+}
+
+/// Run a ruin simulation and return the 1-based step at which each ruined
+/// trial hit zero (same trials as `run_monte_carlo` for the same seed)
+#[wasm_bindgen]
+pub fn run_monte_carlo_ruin_times(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.ruin_times(num_trials)
+}
+
+/// Run a ruin simulation and return a histogram of times to ruin (see
+/// `MonteCarloEngine::ruin_time_histogram`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_ruin_time_histogram(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    n_bins: u32,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.ruin_time_histogram(num_trials, n_bins)
+}
+
+/// Fraction of `num_trials` trials still solvent after each step 1..=steps
+fn survival_curve(ruin_steps: &[u32], steps: u32, num_trials: u32) -> Vec<f64> {
+    if num_trials == 0 {
+        return vec![f64::NAN; steps as usize];
+    }
+    let per_step = ruin_time_histogram(ruin_steps, steps, 0);
+    let mut ruined = 0.0;
+    per_step
+        .iter()
+        .map(|count| {
+            ruined += count;
+            1.0 - ruined / num_trials as f64
+        })
+        .collect()
+}
+
+/// Fraction of `num_trials` trials ruined at or before each horizon
+fn ruin_at_horizons(ruin_steps: &[u32], horizons: &[u32], num_trials: u32) -> Vec<f64> {
+    if num_trials == 0 {
+        return vec![f64::NAN; horizons.len()];
+    }
+    let mut sorted = ruin_steps.to_vec();
+    sorted.sort_unstable();
+    horizons.iter().map(|&h| sorted.partition_point(|&t| t <= h) as f64 / num_trials as f64).collect()
+}
+
+/// Run one set of trials out to the largest horizon and return the
+/// cumulative ruin probability at each horizon in `horizons` (steps)
+#[wasm_bindgen]
+pub fn run_monte_carlo_horizons(
+    components_data: Float64Array,
+    init_wealth: f64,
+    horizons: Float64Array,
+    num_trials: u32,
+    seed: u64,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, 0, seed);
+    engine.ruin_at_horizons(num_trials, horizons)
+}
+
+/// Run a ruin simulation and return the survival probability after each
+/// step 1..=steps (a Float64Array of length `steps`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_survival(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.survival_curve(num_trials)
+}
+
+/// Binned counts: counts[i] is the number of values in [edges[i], edges[i + 1]),
+/// with the last bin closed on the right; values outside are not counted
+#[wasm_bindgen]
+pub struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Histogram {
+    /// Bin edges (one more than the number of bins)
+    pub fn edges(&self) -> Float64Array {
+        Float64Array::from(self.edges.as_slice())
+    }
+
+    /// Count per bin
+    pub fn counts(&self) -> Float64Array {
+        Float64Array::from(self.counts.as_slice())
+    }
+}
+
+impl Histogram {
+    /// Histogram of `values` over `edges`, or over `n_bins` equal-width bins
+    /// spanning the values when `edges` has fewer than two entries
+    fn build(values: &[f64], edges: Vec<f64>, n_bins: u32) -> Histogram {
+        let edges = if edges.len() >= 2 { edges } else { auto_edges(values, n_bins) };
+        let bins = edges.len().saturating_sub(1);
+        let mut counts = vec![0.0; bins];
+        if bins > 0 {
+            let last = edges[bins];
+            for &v in values {
+                if v == last {
+                    counts[bins - 1] += 1.0;
+                    continue;
+                }
+                // First edge strictly above v closes its bin
+                let k = edges.partition_point(|&e| e <= v);
+                if k > 0 && k <= bins {
+                    counts[k - 1] += 1.0;
+                }
+            }
+        }
+        Histogram { edges, counts }
+    }
+}
+
+/// Caller-supplied histogram edges: empty (automatic bins) or at least two
+/// finite, strictly increasing values
+fn check_edges(edges: &[f64]) -> Result<(), String> {
+    if edges.len() == 1 {
+        return Err("histogram edges need at least two values".to_string());
+    }
+    if let Some(e) = edges.iter().find(|e| !e.is_finite()) {
+        return Err(format!("histogram edges must be finite, got {}", e));
+    }
+    if let Some(w) = edges.windows(2).find(|w| w[1] <= w[0]) {
+        return Err(format!("histogram edges must be strictly increasing, got {} then {}", w[0], w[1]));
+    }
+    Ok(())
+}
+
+/// `n_bins` equal-width bin edges over the range of `values` (Sturges' rule
+/// when 0); a constant sample gets a unit-width bin around it
+fn auto_edges(values: &[f64], n_bins: u32) -> Vec<f64> {
+    let finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if finite.is_empty() {
+        return vec![];
+    }
+    let n_bins = if n_bins > 0 { n_bins as usize } else { (finite.len() as f64).log2().ceil() as usize + 1 };
+    let lo = finite.iter().copied().fold(f64::INFINITY, f64::min);
+    let hi = finite.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if lo == hi {
+        return linspace(lo - 0.5, lo + 0.5, n_bins + 1);
+    }
+    linspace(lo, hi, n_bins + 1)
+}
+
+/// Run a ruin simulation and return a histogram of the final wealth of the
+/// surviving trials (see `MonteCarloEngine::final_wealth_histogram`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_final_wealth_histogram(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    edges: Float64Array,
+    n_bins: u32,
+) -> Result<Histogram, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.final_wealth_histogram(num_trials, edges, n_bins)
+}
+
+/// Linear-interpolation percentile of sorted values, q in [0, 1]
+fn sorted_percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() || !(0.0..=1.0).contains(&q) {
+        return f64::NAN;
+    }
+    let pos = q * (sorted.len() - 1) as f64;
+    let i = pos.floor() as usize;
+    let frac = pos - i as f64;
+    if i + 1 < sorted.len() { sorted[i] + frac * (sorted[i + 1] - sorted[i]) } else { sorted[i] }
+}
+
+/// Wealth percentiles (0-100) after each step across trials advanced in
+/// lockstep, row-major steps x percentiles; memory is one state per trial.
+/// Stopped trials keep their final wealth.
+fn wealth_percentiles(
+    source: &impl StepSource,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    percentiles: &[f64],
+    rules: &StepRules,
+    rng: &mut impl Rng,
+) -> Vec<f64> {
+    let mut states: Vec<PathState> = (0..num_trials).map(|_| PathState::new(init_wealth, source.initial_state())).collect();
+    let mut sorted = Vec::with_capacity(states.len());
+    let mut matrix = Vec::with_capacity(steps as usize * percentiles.len());
+    for step in 1..=steps {
+        for state in states.iter_mut().filter(|s| !s.is_done()) {
+            state.step(step, source, rules, rng);
+        }
+        sorted.clear();
+        sorted.extend(states.iter().map(|s| s.wealth));
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        matrix.extend(percentiles.iter().map(|&p| sorted_percentile(&sorted, p / 100.0)));
+    }
+    matrix
+}
+
+/// Run a ruin simulation tracking wealth percentiles (0-100) at every step;
+/// returns a row-major steps x percentiles matrix for fan charts (see
+/// `MonteCarloEngine::wealth_percentiles`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_percentiles(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    percentiles: Float64Array,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.wealth_percentiles(num_trials, percentiles)
+}
+
+/// Wealth trajectories flattened row by row: path i occupies
+/// values[i * stride .. (i + 1) * stride], starting with the initial wealth
+/// (stride = steps + 1). Entries after the step where a path stopped (ruin
+/// or target) are NaN so plots end there.
+#[wasm_bindgen]
+pub struct SamplePaths {
+    values: Vec<f64>,
+    stride: u32,
+}
+
+#[wasm_bindgen]
+impl SamplePaths {
+    pub fn values(&self) -> Float64Array {
+        Float64Array::from(self.values.as_slice())
+    }
+
+    /// Values per path (steps + 1)
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    pub fn num_paths(&self) -> u32 {
+        self.values.len() as u32 / self.stride
+    }
+}
+
+/// Full trajectories of `n_paths` independently simulated trials
+fn sample_paths(
+    source: &impl StepSource,
+    init_wealth: f64,
+    steps: u32,
+    n_paths: u32,
+    rules: &StepRules,
+    rng: &mut impl Rng,
+) -> SamplePaths {
+    let stride = steps as usize + 1;
+    let mut values = Vec::with_capacity(stride * n_paths as usize);
+    for _ in 0..n_paths {
+        let mut state = PathState::new(init_wealth, source.initial_state());
+        values.push(state.wealth);
+        for step in 1..=steps {
+            state.step(step, source, rules, rng);
+            values.push(state.wealth);
+            if state.is_done() {
+                values.extend(std::iter::repeat_n(f64::NAN, (steps - step) as usize));
+                break;
+            }
+        }
+    }
+    SamplePaths { values, stride: stride as u32 }
+}
+
+/// Run `n_paths` independent trials and return their wealth trajectories
+/// (see `SamplePaths` for the layout)
+#[wasm_bindgen]
+pub fn run_monte_carlo_paths(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    seed: u64,
+    n_paths: u32,
+) -> SamplePaths {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.sample_paths(n_paths)
+}
+
+/// [mean, percentiles...] of the trials' maximum drawdowns
+fn drawdown_summary(outcomes: &[TrialOutcome], percentiles: &[f64]) -> Vec<f64> {
+    let mut drawdowns: Vec<f64> = outcomes.iter().map(|o| o.max_drawdown).collect();
+    drawdowns.sort_by(|a, b| a.total_cmp(b));
+    let mean = if drawdowns.is_empty() { f64::NAN } else { drawdowns.iter().sum::<f64>() / drawdowns.len() as f64 };
+    std::iter::once(mean).chain(percentiles.iter().map(|&p| sorted_percentile(&drawdowns, p / 100.0))).collect()
+}
+
+/// Run a ruin simulation and summarize the maximum drawdown per trial as
+/// [mean, percentiles...] (see `MonteCarloEngine::drawdown_summary`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_drawdowns(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    percentiles: Float64Array,
+) -> Float64Array {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.drawdown_summary(num_trials, percentiles)
+}
+
+/// Run a first-passage simulation with ruin at zero and an absorbing upper
+/// barrier at `target`; the result separates ruin, success and timeout
+/// counts
+#[wasm_bindgen]
+pub fn run_monte_carlo_with_target(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    target: f64,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_target(target);
+    engine.run_detailed(num_trials)
+}
+
+/// Run a simulation with multiplicative dynamics, wealth *= 1 + fraction * X,
+/// where ruin means falling to `drawdown_floor` times the running peak (see
+/// `MonteCarloEngine::set_multiplicative`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_multiplicative(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    fraction: f64,
+    drawdown_floor: f64,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_multiplicative(fraction, drawdown_floor);
+    engine.run_detailed(num_trials)
+}
+
+/// Run a simulation whose step distribution changes over the horizon:
+/// `dists[k]` drives block k of `block_len` steps (see
+/// `MonteCarloEngine::set_step_schedule`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_schedule(
+    dists: Array,
+    block_len: u32,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Result<MonteCarloResult, JsValue> {
+    let mut engine = MonteCarloEngine::from_components(Vec::new(), init_wealth, steps, seed);
+    engine.set_step_schedule(dists, block_len)?;
+    Ok(engine.run_detailed(num_trials))
+}
+
+/// Run a regime-switching simulation: `dists[i]` is regime i's step
+/// distribution and `transition` the row-major regime transition matrix (see
+/// `MonteCarloEngine::set_regimes`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_regimes(
+    dists: Array,
+    transition: Float64Array,
+    initial_regime: u32,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Result<MonteCarloResult, JsValue> {
+    let mut engine = MonteCarloEngine::from_components(Vec::new(), init_wealth, steps, seed);
+    engine.set_regimes(dists, transition, initial_regime)?;
+    Ok(engine.run_detailed(num_trials))
+}
+
+/// Run a simulation whose consecutive increments are correlated through an
+/// AR(1) Gaussian copula with coefficient `rho` (see
+/// `MonteCarloEngine::set_autocorrelation`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_autocorrelated(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    rho: f64,
+) -> Result<MonteCarloResult, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_autocorrelation(rho)?;
+    Ok(engine.run_detailed(num_trials))
+}
+
+/// Run a discrete-time surplus simulation where wealth earns `rate` per step
+/// before each increment (see `MonteCarloEngine::set_interest_rate`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_with_interest(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    rate: f64,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_interest_rate(rate);
+    engine.run_detailed(num_trials)
+}
+
+/// Run a discrete Cramer-Lundberg surplus simulation: wealth gains `premium`
+/// every step in addition to the sampled increment (see
+/// `MonteCarloEngine::set_premium`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_with_premium(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    premium: f64,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_premium(premium);
+    engine.run_detailed(num_trials)
+}
+
+/// Run a compound frequency-severity simulation: each step adds the sum of N
+/// draws from the component distribution, N ~ `frequency_params` (see
+/// `MonteCarloEngine::set_frequency`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_compound(
+    components_data: Float64Array,
+    frequency_params: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Result<MonteCarloResult, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_frequency(frequency_params)?;
+    Ok(engine.run_detailed(num_trials))
+}
+
+/// Estimate a rare ruin probability by exponential-tilting importance
+/// sampling with tilt `theta` (NaN for the optimal -R); returns the
+/// likelihood-ratio weighted estimate and its variance (see
+/// `MonteCarloEngine::set_importance_tilt`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_importance(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    theta: f64,
+) -> Result<Estimate, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_importance_tilt(theta)?;
+    Ok(engine.run_importance(num_trials))
+}
+
+/// Run a ruin simulation in antithetic pairs (see
+/// `MonteCarloEngine::set_antithetic`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_antithetic(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.set_antithetic(true);
+    engine.run_detailed(num_trials)
+}
+
+/// Control-variate estimate of the survival probability or expected final
+/// wealth, using the known mean of the summed step draws (see
+/// `MonteCarloEngine::run_control_variate`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_control_variate(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+    target: ControlTarget,
+) -> Result<Estimate, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.run_control_variate(num_trials, target)
+}
+
+/// Run a ruin simulation in batches until the standard error of the ruin
+/// probability is below `target_se`, or `max_trials` trials have run (see
+/// `MonteCarloEngine::run_until`)
+#[wasm_bindgen]
+pub fn run_monte_carlo_until(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    seed: u64,
+    target_se: f64,
+    max_trials: u32,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let mut engine = MonteCarloEngine::from_components(parse_components(&data), init_wealth, steps, seed);
+    engine.run_until(target_se, max_trials)
+}
+
+// ===========================================
+// Parallel Runs
+// ===========================================
+
+/// Trials per independently seeded block of `run_monte_carlo_parallel`
+const PARALLEL_BLOCK_TRIALS: u32 = 10_000;
+
+/// Totals of one block: trials [block * PARALLEL_BLOCK_TRIALS, ..) drawn
+/// from a generator seeded by the run seed and the block index alone
+fn simulate_block(alias_table: &AliasTable, init_wealth: f64, steps: u32, num_trials: u32, seed: u64, block: u32) -> RunTotals {
+    let mut state = seed ^ (block as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut rng = StdRng::seed_from_u64(splitmix64(&mut state));
+    let start = block * PARALLEL_BLOCK_TRIALS;
+    let n = PARALLEL_BLOCK_TRIALS.min(num_trials - start);
+    let rules = StepRules::default();
+    let outcomes: Vec<TrialOutcome> =
+        (0..n).map(|_| simulate_trial(alias_table, init_wealth, steps, &rules, &mut rng)).collect();
+    let mut totals = RunTotals::default();
+    totals.add(&outcomes);
+    totals
+}
+
+fn parallel_totals(alias_table: &AliasTable, init_wealth: f64, steps: u32, num_trials: u32, seed: u64) -> RunTotals {
+    let blocks = num_trials.div_ceil(PARALLEL_BLOCK_TRIALS);
+    #[cfg(feature = "parallel")]
+    let blocks = (0..blocks).into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let blocks = 0..blocks;
+    // Block totals are combined in block order, so floating-point sums do
+    // not depend on how blocks were scheduled
+    let per_block: Vec<RunTotals> =
+        blocks.map(|b| simulate_block(alias_table, init_wealth, steps, num_trials, seed, b)).collect();
+    per_block.into_iter().fold(RunTotals::default(), RunTotals::merge)
+}
+
+/// Run a ruin simulation split into blocks of 10,000 trials, each seeded from
+/// `seed` and its block index; with the `parallel` feature the blocks run
+/// across threads (web workers on wasm, after `initThreadPool`)
+///
+/// The result depends only on the inputs and `seed`, not on the number of
+/// threads or whether the feature is enabled. The median is not tracked
+/// (NaN).
+#[wasm_bindgen]
+pub fn run_monte_carlo_parallel(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> MonteCarloResult {
+    let data: Vec<f64> = components_data.to_vec();
+    let alias_table = AliasTable::new(parse_components(&data));
+    let totals = parallel_totals(&alias_table, init_wealth, steps, num_trials, seed);
+    MonteCarloResult::from_totals(&totals, f64::NAN, DEFAULT_CONFIDENCE_LEVEL)
+}
+
+// ===========================================
+// SIMD Lanes
+// ===========================================
+
+/// Trials advanced together by `run_monte_carlo_simd`, one per f64x2 lane
+const SIMD_LANES: usize = 2;
+
+/// Alias lookup per lane: scale each uniform by the table size, split it into
+/// a cell and a threshold, and keep the cell or take its alias
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn lanes_pick(alias_table: &AliasTable, uniforms: [f64; SIMD_LANES]) -> [usize; SIMD_LANES] {
+    use core::arch::wasm32::*;
+    let n = alias_table.prob.len();
+    let scaled = f64x2_mul(f64x2(uniforms[0], uniforms[1]), f64x2_splat(n as f64));
+    let whole = f64x2_trunc(scaled);
+    let threshold = f64x2_sub(scaled, whole);
+    let cells = i32x4_trunc_sat_f64x2_zero(f64x2_min(whole, f64x2_splat((n - 1) as f64)));
+    let (c0, c1) = (i32x4_extract_lane::<0>(cells) as usize, i32x4_extract_lane::<1>(cells) as usize);
+    let own = u64x2_extend_low_u32x4(cells);
+    let alias = u64x2(alias_table.alias[c0] as u64, alias_table.alias[c1] as u64);
+    let keep = f64x2_lt(threshold, f64x2(alias_table.prob[c0], alias_table.prob[c1]));
+    let picked = v128_bitselect(own, alias, keep);
+    [u64x2_extract_lane::<0>(picked) as usize, u64x2_extract_lane::<1>(picked) as usize]
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+fn lanes_pick(alias_table: &AliasTable, uniforms: [f64; SIMD_LANES]) -> [usize; SIMD_LANES] {
+    lanes_pick_portable(alias_table, uniforms)
+}
+
+/// Scalar form of `lanes_pick`, with identical results
+#[cfg(any(test, not(all(target_arch = "wasm32", target_feature = "simd128"))))]
+fn lanes_pick_portable(alias_table: &AliasTable, uniforms: [f64; SIMD_LANES]) -> [usize; SIMD_LANES] {
+    let n = alias_table.prob.len();
+    uniforms.map(|u| {
+        let scaled = u * n as f64;
+        let whole = scaled.trunc();
+        let cell = whole.min((n - 1) as f64) as usize;
+        if scaled - whole < alias_table.prob[cell] { cell } else { alias_table.alias[cell] }
+    })
+}
+
+/// wealth += increment per lane; returns the bitmask of lanes now at or
+/// below zero
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn lanes_update(wealth: &mut [f64; SIMD_LANES], increments: [f64; SIMD_LANES]) -> u32 {
+    use core::arch::wasm32::*;
+    let next = f64x2_add(f64x2(wealth[0], wealth[1]), f64x2(increments[0], increments[1]));
+    *wealth = [f64x2_extract_lane::<0>(next), f64x2_extract_lane::<1>(next)];
+    i64x2_bitmask(f64x2_le(next, f64x2_splat(0.0))) as u32
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+fn lanes_update(wealth: &mut [f64; SIMD_LANES], increments: [f64; SIMD_LANES]) -> u32 {
+    lanes_update_portable(wealth, increments)
+}
+
+/// Scalar form of `lanes_update`, with identical results
+#[cfg(any(test, not(all(target_arch = "wasm32", target_feature = "simd128"))))]
+fn lanes_update_portable(wealth: &mut [f64; SIMD_LANES], increments: [f64; SIMD_LANES]) -> u32 {
+    let mut mask = 0;
+    for lane in 0..SIMD_LANES {
+        wealth[lane] += increments[lane];
+        if wealth[lane] <= 0.0 {
+            mask |= 1 << lane;
+        }
+    }
+    mask
+}
+
+/// Count ruins advancing `SIMD_LANES` trials per step with the simd128
+/// kernels when the build enables them, the portable ones otherwise
+fn count_ruins_lanes(alias_table: &AliasTable, init_wealth: f64, steps: u32, num_trials: u32, rng: &mut impl Rng) -> u32 {
+    count_ruins_with_lanes(alias_table, init_wealth, steps, num_trials, rng, lanes_pick, lanes_update)
+}
+
+/// Lane loop shared by both kernels. Each step draws every lane's uniform in
+/// lane order, picks components with `pick`, draws each lane's value from
+/// its component in lane order, then applies `update`. Lanes of a group keep
+/// drawing until all of them are ruined, so both kernels consume the stream
+/// identically; a partial final group pads with lanes that start dead.
+fn count_ruins_with_lanes(
+    alias_table: &AliasTable,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    rng: &mut impl Rng,
+    pick: fn(&AliasTable, [f64; SIMD_LANES]) -> [usize; SIMD_LANES],
+    update: fn(&mut [f64; SIMD_LANES], [f64; SIMD_LANES]) -> u32,
+) -> u32 {
+    if init_wealth <= 0.0 {
+        return num_trials;
+    }
+    if alias_table.components.is_empty() {
+        return 0;
+    }
+    let mut ruin_count = 0;
+    let mut remaining = num_trials as usize;
+    while remaining > 0 {
+        let lanes = remaining.min(SIMD_LANES);
+        remaining -= lanes;
+        let mut wealth = [init_wealth; SIMD_LANES];
+        let mut alive: u32 = (1 << lanes) - 1;
+        for _ in 0..steps {
+            let uniforms = [(); SIMD_LANES].map(|_| rng.gen::<f64>());
+            let increments = pick(alias_table, uniforms).map(|idx| alias_table.sample_component(idx, rng));
+            let ruined = update(&mut wealth, increments) & alive;
+            ruin_count += ruined.count_ones();
+            alive &= !ruined;
+            if alive == 0 {
+                break;
+            }
+        }
+    }
+    ruin_count
+}
+
+/// Run a ruin simulation with the lane-parallel loop (wasm simd128 when the
+/// build enables it, a portable loop otherwise; both give the same counts)
+///
+/// Trials interleave their draws, so for a given seed the count differs from
+/// `run_monte_carlo` while following the same distribution.
+#[wasm_bindgen]
+pub fn run_monte_carlo_simd(
+    components_data: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: Option<u64>,
+) -> u32 {
+    let data: Vec<f64> = components_data.to_vec();
+    let alias_table = AliasTable::new(parse_components(&data));
+    match seed {
+        Some(seed) => count_ruins_lanes(&alias_table, init_wealth, steps, num_trials, &mut StdRng::seed_from_u64(seed)),
+        None => count_ruins_lanes(&alias_table, init_wealth, steps, num_trials, &mut rand::thread_rng()),
+    }
+}
+
+// ===========================================
+// Sensitivity Analysis
+// ===========================================
+
+/// Change in ruin probability from perturbing each distribution parameter
+#[wasm_bindgen]
+pub struct Sensitivity {
+    base_probability: f64,
+    changes: Vec<f64>,
+    standard_errors: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl Sensitivity {
+    /// Ruin probability of the unperturbed distribution
+    pub fn base_probability(&self) -> f64 {
+        self.base_probability
+    }
+
+    /// Perturbed minus base ruin probability, one entry per slot of the flat
+    /// component array (0 where the delta was 0)
+    pub fn changes(&self) -> Float64Array {
+        Float64Array::from(self.changes.as_slice())
+    }
+
+    /// Standard error of each change from the paired trial differences
+    pub fn standard_errors(&self) -> Float64Array {
+        Float64Array::from(self.standard_errors.as_slice())
+    }
+}
+
+/// Which slots of a flat component array hold perturbable parameters: atom
+/// positions, bin edges, tail starts and rates (not types, weights or sides)
+fn perturbable_slots(data: &[f64]) -> Vec<bool> {
+    let mut slots = vec![false; data.len()];
+    let mut i = 0;
+    while i < data.len() {
+        let (len, params): (usize, &[usize]) = match data[i] as i32 {
+            0 => (3, &[1]),
+            1 => (4, &[1, 2]),
+            2 => (5, &[1, 3]),
+            _ => (1, &[]),
+        };
+        for &k in params {
+            if i + len <= data.len() {
+                slots[i + k] = true;
+            }
+        }
+        i += len;
+    }
+    slots
+}
+
+/// Ruin indicator of each trial, trial k drawing from its own generator
+/// seeded with `trial_seeds[k]` so that perturbed runs stay aligned
+fn ruin_indicators(alias_table: &AliasTable, init_wealth: f64, steps: u32, trial_seeds: &[u64]) -> Vec<bool> {
+    trial_seeds
+        .iter()
+        .map(|&seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            simulate_trial(alias_table, init_wealth, steps, &StepRules::default(), &mut rng).ruin_step.is_some()
+        })
+        .collect()
+}
+
+/// Ruin probability change from adding `deltas[i]` to `data[i]`, one slot at
+/// a time, with every perturbed run reusing the base run's random numbers
+///
+/// The component choice depends only on the weights, which are not
+/// perturbed, and positions are drawn by inverse transform, so each trial's
+/// path moves continuously with the parameter.
+fn ruin_sensitivities(
+    data: &[f64],
+    deltas: &[f64],
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Result<Sensitivity, String> {
+    if deltas.len() != data.len() {
+        return Err(format!("deltas has {} entries, expected {}", deltas.len(), data.len()));
+    }
+    let slots = perturbable_slots(data);
+    if let Some(i) = deltas.iter().zip(&slots).position(|(&d, &ok)| d != 0.0 && !ok) {
+        return Err(format!("slot {i} is not a position or rate parameter"));
+    }
+    if num_trials == 0 {
+        return Err("num_trials must be positive".to_string());
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let trial_seeds: Vec<u64> = (0..num_trials).map(|_| rng.gen()).collect();
+    let base = ruin_indicators(&AliasTable::new(parse_components(data)), init_wealth, steps, &trial_seeds);
+    let n = num_trials as f64;
+    let mut changes = vec![0.0; data.len()];
+    let mut standard_errors = vec![0.0; data.len()];
+    for (i, &delta) in deltas.iter().enumerate() {
+        if delta == 0.0 {
+            continue;
+        }
+        let mut perturbed = data.to_vec();
+        perturbed[i] += delta;
+        let ruins = ruin_indicators(&AliasTable::new(parse_components(&perturbed)), init_wealth, steps, &trial_seeds);
+        let diffs: Vec<f64> = ruins.iter().zip(&base).map(|(&r, &b)| r as i32 as f64 - b as i32 as f64).collect();
+        let mean = diffs.iter().sum::<f64>() / n;
+        let var = diffs.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        changes[i] = mean;
+        standard_errors[i] = (var / n).sqrt();
+    }
+    let base_probability = base.iter().filter(|&&r| r).count() as f64 / n;
+    Ok(Sensitivity { base_probability, changes, standard_errors })
+}
+
+/// Perturb distribution parameters and report the change in ruin probability
+///
+/// `deltas` runs parallel to `components_data`; each nonzero entry is added
+/// to its slot on its own and the run is repeated with common random
+/// numbers. Only atom positions, bin edges, tail starts and tail rates may
+/// be perturbed.
+#[wasm_bindgen]
+pub fn ruin_sensitivity(
+    components_data: Float64Array,
+    deltas: Float64Array,
+    init_wealth: f64,
+    steps: u32,
+    num_trials: u32,
+    seed: u64,
+) -> Result<Sensitivity, JsValue> {
+    let data: Vec<f64> = components_data.to_vec();
+    let deltas: Vec<f64> = deltas.to_vec();
+    ruin_sensitivities(&data, &deltas, init_wealth, steps, num_trials, seed).map_err(|e| JsValue::from_str(&e))
 }